@@ -0,0 +1,283 @@
+//! Tiled amplicon sequencing from a primer scheme.
+//!
+//! Amplicon panels such as the ARTIC schemes for SARS-CoV-2 or mpox are described by a BED
+//! file of primers, named `<PREFIX>_<AMPLICON>_LEFT` or `_RIGHT` (with optional suffixes for
+//! alternative primers). Each read is a full amplicon, from the start of its left primer to
+//! the end of its right primer, read from either strand. Amplicon depths vary with primer
+//! efficiency, and some amplicons drop out entirely.
+
+use crate::io::bed::BedRecord;
+use crate::io::fasta::Reference;
+use crate::utils::{derive_rng, sample_normal};
+use anyhow::{Result, anyhow, bail};
+use rand::Rng;
+use std::collections::HashMap;
+
+/// Random stream used for amplicon depths and dropouts (see [`derive_rng`]).
+const AMPLICON_STREAM: u64 = 2;
+
+/// An amplicon of a primer scheme, including its primers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Amplicon {
+    pub name: String,
+    pub sequence_index: usize,
+    /// Zero-based start of the left primer
+    pub start: usize,
+    /// Zero-based, exclusive end of the right primer
+    pub end: usize,
+}
+
+/// Amplicons of a primer scheme with their relative depths.
+pub struct PrimerScheme {
+    amplicons: Vec<Amplicon>,
+    cumulative_weights: Vec<f64>,
+    dropped: Vec<String>,
+}
+
+impl PrimerScheme {
+    /// Derives the amplicons of a primer scheme, all with the same depth.
+    ///
+    /// Primers are paired by the part of their name before `LEFT` or `RIGHT`. Each amplicon
+    /// spans from its outermost left primer to its outermost right primer, so alternative
+    /// primers widen it if needed. Amplicons are returned in order of first appearance.
+    ///
+    /// # Arguments
+    /// * `records` - Primer BED records (name in the 4th column)
+    /// * `reference` - Reference the primers refer to
+    ///
+    /// # Errors
+    /// Returns an error if a primer is unnamed, not a `LEFT` or `RIGHT` primer, refers to an
+    /// unknown sequence or lies outside it, or if an amplicon lacks one of its primers, spans
+    /// two sequences or has its right primer before its left one
+    pub fn from_bed(records: &[BedRecord], reference: &Reference) -> Result<Self> {
+        let sequence_indices: HashMap<&str, usize> = (0..reference.len())
+            .map(|i| (reference.sequence_id(i), i))
+            .collect();
+
+        let mut amplicon_indices: HashMap<String, usize> = HashMap::new();
+        let mut primers: Vec<(String, usize, Option<usize>, Option<usize>)> = Vec::new();
+        for record in records {
+            let region = format!("{}:{}-{}", record.chrom, record.start, record.end);
+            let name = record
+                .name()
+                .ok_or_else(|| anyhow!("Primer {} has no name", region))?;
+            let tokens: Vec<&str> = name.split('_').collect();
+            let Some(side) = tokens
+                .iter()
+                .position(|token| matches!(*token, "LEFT" | "RIGHT"))
+                .filter(|&side| side > 0)
+            else {
+                bail!(
+                    "Primer {} ({}) is not named as a LEFT or RIGHT primer",
+                    name,
+                    region
+                );
+            };
+            let Some(&sequence_index) = sequence_indices.get(record.chrom.as_str()) else {
+                bail!("Primer {} refers to a sequence not in the reference", name);
+            };
+            if record.end > reference.sequence_length(sequence_index) {
+                bail!(
+                    "Primer {} extends past the sequence end ({} bp)",
+                    name,
+                    reference.sequence_length(sequence_index)
+                );
+            }
+
+            let amplicon = tokens[..side].join("_");
+            let index = *amplicon_indices.entry(amplicon.clone()).or_insert_with(|| {
+                primers.push((amplicon, sequence_index, None, None));
+                primers.len() - 1
+            });
+            let (amplicon, amplicon_sequence, left, right) = &mut primers[index];
+            if *amplicon_sequence != sequence_index {
+                bail!(
+                    "Primers of amplicon {} lie on different sequences",
+                    amplicon
+                );
+            }
+            if tokens[side] == "LEFT" {
+                *left = Some(left.map_or(record.start, |start: usize| start.min(record.start)));
+            } else {
+                *right = Some(right.map_or(record.end, |end: usize| end.max(record.end)));
+            }
+        }
+
+        let amplicons = primers
+            .into_iter()
+            .map(|(name, sequence_index, left, right)| {
+                let (Some(start), Some(end)) = (left, right) else {
+                    bail!("Amplicon {} lacks a LEFT or RIGHT primer", name);
+                };
+                if end <= start {
+                    bail!(
+                        "Amplicon {} has its RIGHT primer before its LEFT primer",
+                        name
+                    );
+                }
+                Ok(Amplicon {
+                    name,
+                    sequence_index,
+                    start,
+                    end,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if amplicons.is_empty() {
+            bail!("No amplicons found in the primer scheme");
+        }
+
+        let cumulative_weights = (1..=amplicons.len()).map(|i| i as f64).collect();
+        Ok(Self {
+            amplicons,
+            cumulative_weights,
+            dropped: Vec::new(),
+        })
+    }
+
+    /// Draws the depth of every amplicon.
+    ///
+    /// Relative depths are log-normal, and every amplicon drops out (gets no reads) with the
+    /// given probability. Depths depend only on the seed, so all shards of a run agree.
+    ///
+    /// # Arguments
+    /// * `depth_sd` - Standard deviation of the natural logarithm of the depths
+    /// * `dropout` - Probability of an amplicon getting no reads
+    /// * `seed` - Random seed of the run
+    ///
+    /// # Errors
+    /// Returns an error if `depth_sd` is negative, `dropout` is not between 0 and 1, or every
+    /// amplicon drops out
+    pub fn with_depth_variation(mut self, depth_sd: f64, dropout: f64, seed: u64) -> Result<Self> {
+        if !(depth_sd >= 0.0 && depth_sd.is_finite()) {
+            bail!(
+                "Amplicon depth SD must be a non-negative number, got {}",
+                depth_sd
+            );
+        }
+        if !(0.0..=1.0).contains(&dropout) {
+            bail!("Amplicon dropout must be between 0 and 1, got {}", dropout);
+        }
+
+        let mut rng = derive_rng(seed, AMPLICON_STREAM, 0);
+        let mut total = 0.0;
+        self.dropped.clear();
+        for (amplicon, cumulative) in self.amplicons.iter().zip(&mut self.cumulative_weights) {
+            let depth = sample_normal(&mut rng, 0.0, depth_sd).exp();
+            if rng.random_bool(dropout) {
+                self.dropped.push(amplicon.name.clone());
+            } else {
+                total += depth;
+            }
+            *cumulative = total;
+        }
+        if total <= 0.0 {
+            bail!("All {} amplicons dropped out", self.amplicons.len());
+        }
+
+        Ok(self)
+    }
+
+    /// Returns the amplicons of the scheme.
+    pub fn amplicons(&self) -> &[Amplicon] {
+        &self.amplicons
+    }
+
+    /// Returns the names of the amplicons that dropped out.
+    pub fn dropped(&self) -> &[String] {
+        &self.dropped
+    }
+
+    /// Chooses an amplicon at random according to the depths.
+    pub fn choose<R: Rng>(&self, rng: &mut R) -> &Amplicon {
+        let total = *self.cumulative_weights.last().unwrap();
+        let point = rng.random_range(0.0..total);
+        let idx = self
+            .cumulative_weights
+            .partition_point(|&cumulative| cumulative <= point)
+            .min(self.amplicons.len() - 1);
+        &self.amplicons[idx]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::fasta::FastaRecord;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn primer(start: usize, end: usize, name: &str) -> BedRecord {
+        BedRecord {
+            chrom: "MN908947.3".to_string(),
+            start,
+            end,
+            fields: vec![name.to_string(), "1".to_string(), "+".to_string()],
+        }
+    }
+
+    fn reference() -> Reference {
+        Reference::from(vec![FastaRecord {
+            id: "MN908947.3".to_string(),
+            sequence: vec![b'A'; 1000],
+        }])
+    }
+
+    #[test]
+    fn test_amplicons_from_scheme() {
+        let records = vec![
+            primer(30, 54, "SARS-CoV-2_1_LEFT"),
+            primer(385, 410, "SARS-CoV-2_1_RIGHT"),
+            primer(320, 342, "SARS-CoV-2_2_LEFT_1"),
+            primer(704, 726, "SARS-CoV-2_2_RIGHT_1"),
+            primer(710, 732, "SARS-CoV-2_2_RIGHT_alt0"),
+        ];
+        let scheme = PrimerScheme::from_bed(&records, &reference()).unwrap();
+        let amplicons = scheme.amplicons();
+        assert_eq!(amplicons.len(), 2);
+        assert_eq!(amplicons[0].name, "SARS-CoV-2_1");
+        assert_eq!((amplicons[0].start, amplicons[0].end), (30, 410));
+        assert_eq!(amplicons[1].name, "SARS-CoV-2_2");
+        assert_eq!((amplicons[1].start, amplicons[1].end), (320, 732));
+
+        assert!(PrimerScheme::from_bed(&records[..1], &reference()).is_err());
+        assert!(PrimerScheme::from_bed(&[primer(10, 20, "probe")], &reference()).is_err());
+        assert!(PrimerScheme::from_bed(&[primer(990, 1010, "X_1_LEFT")], &reference()).is_err());
+    }
+
+    #[test]
+    fn test_depth_variation_and_dropout() {
+        let records: Vec<BedRecord> = (0..20)
+            .flat_map(|i| {
+                [
+                    primer(i * 40, i * 40 + 5, &format!("scheme_{}_LEFT", i)),
+                    primer(i * 40 + 30, i * 40 + 35, &format!("scheme_{}_RIGHT", i)),
+                ]
+            })
+            .collect();
+
+        let scheme = PrimerScheme::from_bed(&records, &reference())
+            .unwrap()
+            .with_depth_variation(1.0, 0.25, 42)
+            .unwrap();
+        let dropped = scheme.dropped().to_vec();
+        assert!(!dropped.is_empty() && dropped.len() < 20);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..1000 {
+            assert!(!dropped.contains(&scheme.choose(&mut rng).name));
+        }
+
+        let again = PrimerScheme::from_bed(&records, &reference())
+            .unwrap()
+            .with_depth_variation(1.0, 0.25, 42)
+            .unwrap();
+        assert_eq!(again.dropped(), dropped);
+        assert!(
+            PrimerScheme::from_bed(&records, &reference())
+                .unwrap()
+                .with_depth_variation(1.0, 1.0, 42)
+                .is_err()
+        );
+    }
+}
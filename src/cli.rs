@@ -1,8 +1,16 @@
+use crate::io::{ExpressionUnit, OutputFormat};
+use crate::models::{CloneSpec, IupacPolicy, MotifRate, SoftMaskPolicy, TargetMode, TooLongPolicy};
+use crate::repeats::CopyNumber;
+use crate::run::{HeaderStyle, MetadataSource};
+use crate::sv::SvType;
+use crate::transcriptome::RnaProtocol;
 use clap::Parser;
 use clap::builder::styling::{AnsiColor, Effects, Styles};
 use console::style;
 use std::fmt::Display;
+use std::ops::Range;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 fn get_styles() -> Styles {
     Styles::styled()
@@ -21,18 +29,350 @@ fn get_styles() -> Styles {
     styles = get_styles()
 )]
 pub struct Cli {
-    /// Reference sequences (FASTA format) to sample reads from
-    #[arg(short = 'r', long, value_name = "FASTA")]
-    pub reference: PathBuf,
+    /// Reference sequences (FASTA format, optionally gzip/bgzip-compressed) to sample reads from;
+    /// can be given multiple times
+    #[arg(
+        short = 'r',
+        long,
+        value_name = "FASTA",
+        required_unless_present_any = ["haplotype", "transcripts"],
+        num_args = 1..
+    )]
+    pub reference: Vec<PathBuf>,
+
+    /// FASTA file with the sequences of one haplotype; give one per haplotype to simulate a
+    /// diploid or polyploid genome instead of a single reference
+    #[arg(long, value_name = "FASTA", num_args = 1.., conflicts_with_all = ["reference", "vcf"])]
+    pub haplotype: Vec<PathBuf>,
+
+    /// Fetch reference windows on demand from the FASTA index (.fai, plus .gzi if bgzipped)
+    /// instead of loading all sequences into memory
+    #[arg(long)]
+    pub indexed_reference: bool,
+
+    /// Handling of soft-masked (lowercase) reference bases
+    #[arg(long, value_enum, value_name = "POLICY", default_value = "uppercase")]
+    pub soft_masked: SoftMaskPolicy,
+
+    /// Error probability multiplier for soft-masked reference bases (default: 1.0)
+    #[arg(long, value_name = "FACTOR")]
+    pub soft_masked_error_factor: Option<f64>,
+
+    /// Handling of IUPAC ambiguity codes (including N) in the reference
+    #[arg(long, value_enum, value_name = "POLICY", default_value = "resolve")]
+    pub iupac: IupacPolicy,
+
+    /// Maximum fraction of N bases in a sampled reference window (default: 0.1)
+    #[arg(long, value_name = "FRACTION")]
+    pub max_n_fraction: Option<f64>,
+
+    /// VCF of variants to apply to the reference before sampling reads (SNVs, indels and
+    /// symbolic DEL/INS/INV/DUP alleles; the first ALT allele is used)
+    #[arg(long, value_name = "VCF")]
+    pub vcf: Option<PathBuf>,
+
+    /// Number of haplotypes to derive from the genotypes of a VCF sample (default: 1, which
+    /// applies the first ALT allele of every record, or the first one carried by --sample)
+    #[arg(long, value_name = "N", default_value = "1")]
+    pub ploidy: usize,
+
+    /// Sample of the --vcf VCF whose genotypes define the haplotypes (default: the first sample)
+    #[arg(long, value_name = "NAME", requires = "vcf")]
+    pub sample: Option<String>,
+
+    /// BED file of structural variants to inject (name column: DEL, INS, INV, DUP or TRA;
+    /// 5th column: inserted sequence for INS, CHROM:POS destination for TRA); entries must not
+    /// overlap
+    #[arg(long, value_name = "BED", conflicts_with = "haplotype")]
+    pub sv_list: Option<PathBuf>,
+
+    /// Number of structural variants to inject at random positions
+    #[arg(
+        long,
+        value_name = "N",
+        default_value = "0",
+        conflicts_with = "haplotype"
+    )]
+    pub random_svs: usize,
+
+    /// Types of the random structural variants, used in turn
+    #[arg(
+        long,
+        value_enum,
+        value_name = "TYPES",
+        value_delimiter = ',',
+        default_value = "del,ins,inv,dup,tra"
+    )]
+    pub sv_types: Vec<SvType>,
+
+    /// Minimum size of random structural variants
+    #[arg(long, value_name = "BP", default_value = "50")]
+    pub sv_min_size: usize,
+
+    /// Maximum size of random structural variants (sizes are log-uniform between both bounds)
+    #[arg(long, value_name = "BP", default_value = "10000")]
+    pub sv_max_size: usize,
+
+    /// Relative share of reads drawn from each haplotype, comma-separated (default: equal)
+    #[arg(long, value_name = "W1,W2,...", value_delimiter = ',')]
+    pub haplotype_weights: Option<Vec<f64>>,
+
+    /// VCF of somatic variants to simulate a tumour sample; INFO CLONE assigns a variant to a
+    /// clone and INFO VAF sets its allele fraction instead (default: founding clone)
+    #[arg(long, value_name = "VCF", conflicts_with = "haplotype")]
+    pub somatic_vcf: Option<PathBuf>,
+
+    /// Tumour sample of the somatic VCF whose genotypes place the somatic variants (default:
+    /// its only sample; must be given if it has several and --ploidy is above 1)
+    #[arg(long, value_name = "NAME", requires = "somatic_vcf")]
+    pub somatic_sample: Option<String>,
+
+    /// Tumour subclone as NAME:CCF[:PARENT], with its cancer cell fraction (can be repeated)
+    #[arg(
+        long = "clone",
+        value_name = "NAME:CCF[:PARENT]",
+        requires = "somatic_vcf"
+    )]
+    pub clones: Vec<CloneSpec>,
+
+    /// Fraction of tumour cells in the sample
+    #[arg(
+        long,
+        value_name = "FRACTION",
+        default_value = "1.0",
+        requires = "somatic_vcf"
+    )]
+    pub purity: f64,
+
+    /// BED file of short tandem repeat loci: the tract, locus ID, motif and the copy numbers of
+    /// the alleles (N, MEAN:SD or MIN-MAX, comma-separated per haplotype)
+    #[arg(long, value_name = "BED")]
+    pub str_loci: Option<PathBuf>,
+
+    /// Global base modification rate of a motif as MOTIF:CODE:RATE, with code m (5mC), h (5hmC)
+    /// or a (6mA), e.g. CG:m:0.75; can be repeated. Calls are written as MM/ML tags
+    #[arg(long, value_name = "MOTIF:CODE:RATE")]
+    pub methylation: Vec<MotifRate>,
+
+    /// bedMethyl file (e.g. from modkit pileup) with per-site modification fractions
+    #[arg(long, value_name = "BED")]
+    pub bedmethyl: Option<PathBuf>,
+
+    /// Write the true state and called probability of every modification site in each read
+    #[arg(long, value_name = "TSV")]
+    pub methylation_truth: Option<PathBuf>,
+
+    /// Transcript sequences (FASTA) to simulate cDNA reads from instead of a genome
+    #[arg(
+        long,
+        value_name = "FASTA",
+        conflicts_with_all = ["reference", "gtf", "haplotype", "vcf", "somatic_vcf", "sv_list",
+            "random_svs", "str_loci", "methylation", "bedmethyl", "targets"]
+    )]
+    pub transcripts: Option<PathBuf>,
+
+    /// GTF annotation of the reference; cDNA reads are simulated from the transcripts built
+    /// from its exons
+    #[arg(
+        long,
+        value_name = "GTF",
+        requires = "reference",
+        conflicts_with_all = ["haplotype", "vcf", "somatic_vcf", "sv_list", "random_svs",
+            "str_loci", "methylation", "bedmethyl", "targets"]
+    )]
+    pub gtf: Option<PathBuf>,
+
+    /// Transcript expression table: a quantification output such as salmon's quant.sf, or two
+    /// columns with transcript ID and value (default: all transcripts equally expressed)
+    #[arg(long, value_name = "TSV")]
+    pub expression: Option<PathBuf>,
+
+    /// Quantity given by the expression table
+    #[arg(long, value_enum, value_name = "UNIT", default_value = "tpm")]
+    pub expression_unit: ExpressionUnit,
+
+    /// Fraction of cDNA molecules missing part of their 5' end
+    #[arg(long, value_name = "FRACTION", default_value = "0.3")]
+    pub truncated_fraction: f64,
+
+    /// Length of poly-A tails (N, MEAN:SD or MIN-MAX)
+    #[arg(long, value_name = "LENGTH", default_value = "30:10")]
+    pub polya_length: CopyNumber,
+
+    /// RNA library preparation: cdna reads come from both strands, direct-rna reads from the
+    /// sense strand, reported 5'→3' as basecalled and with their own error rate defaults
+    #[arg(long, value_enum, value_name = "PROTOCOL", default_value = "cdna")]
+    pub rna_protocol: RnaProtocol,
+
+    /// Write direct RNA reads with U instead of T (FASTQ, FASTA and SAM; BAM and CRAM keep T)
+    #[arg(long)]
+    pub uracil: bool,
+
+    /// Primer scheme BED (e.g. ARTIC) whose amplicons every read spans, primers included
+    #[arg(
+        long,
+        value_name = "BED",
+        conflicts_with_all = ["targets", "haplotype", "transcripts", "gtf", "str_loci",
+            "methylation", "bedmethyl"]
+    )]
+    pub primer_scheme: Option<PathBuf>,
+
+    /// Standard deviation of the log depth of amplicons, modelling uneven amplification
+    #[arg(long, value_name = "SD", default_value = "0.5")]
+    pub amplicon_depth_sd: f64,
+
+    /// Probability of an amplicon dropping out and getting no reads
+    #[arg(long, value_name = "FRACTION", default_value = "0.0")]
+    pub amplicon_dropout: f64,
+
+    /// BED file of Cas9 cut sites that reads start from: the cut lies before the interval start
+    /// and the strand column sets the read direction
+    #[arg(
+        long,
+        value_name = "BED",
+        conflicts_with_all = ["targets", "primer_scheme", "haplotype", "transcripts", "gtf",
+            "str_loci"]
+    )]
+    pub cut_sites: Option<PathBuf>,
+
+    /// Fraction of off-target reads drawn from random positions in a Cas9 enrichment
+    #[arg(long, value_name = "FRACTION", default_value = "0.05")]
+    pub background_fraction: f64,
+
+    /// BED file of adaptive sampling targets: reads whose first bases miss every target are
+    /// ejected and cut short at the decision length
+    #[arg(
+        long,
+        value_name = "BED",
+        conflicts_with_all = ["targets", "primer_scheme", "cut_sites", "transcripts", "gtf",
+            "str_loci"]
+    )]
+    pub adaptive_sampling: Option<PathBuf>,
+
+    /// Bases read before adaptive sampling ejects an off-target molecule: N, MEAN:SD or MIN-MAX
+    #[arg(long, value_name = "LENGTH", default_value = "500:150")]
+    pub decision_length: CopyNumber,
+
+    /// Fraction of molecules whose complement strand is read right after the template strand
+    #[arg(long, value_name = "FRACTION")]
+    pub duplex_fraction: Option<f64>,
+
+    /// Also write a duplex consensus read for every template and complement pair
+    #[arg(long, requires = "duplex_fraction")]
+    pub duplex_consensus: bool,
+
+    /// Phred quality added to the bases of duplex consensus reads (capped at Q50)
+    #[arg(long, value_name = "Q", default_value = "10")]
+    pub duplex_quality_boost: u8,
+
+    /// BED file of target regions to draw reads from
+    #[arg(long, value_name = "BED")]
+    pub targets: Option<PathBuf>,
+
+    /// Weight target regions and cut sites by the BED score column (default: uniform)
+    #[arg(long)]
+    pub target_weights: bool,
+
+    /// Whether reads must lie inside target regions or only overlap them
+    #[arg(long, value_enum, value_name = "MODE", default_value = "contained")]
+    pub target_mode: TargetMode,
+
+    /// What to do with reads longer than their reference sequence
+    #[arg(long, value_enum, value_name = "POLICY", default_value = "resample")]
+    pub too_long: TooLongPolicy,
+
+    /// Maximum attempts to generate a single read before failing
+    #[arg(long, value_name = "N", default_value = "1000")]
+    pub max_attempts: usize,
 
     /// Input file to extract quality and length models (FASTQ or BAM)
     #[arg(short = 'i', long, value_name = "FILE")]
     pub input: PathBuf,
 
-    /// Output file for simulated reads (FASTQ or BAM, detected by extension)
+    /// Output file for simulated reads (FASTQ, FASTA, SAM, BAM or CRAM, detected by extension),
+    /// or the run directory with --live
     #[arg(short = 'o', long, value_name = "FILE")]
     pub output: PathBuf,
 
+    /// Format of the output, instead of detecting it from the file extension
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    pub output_format: Option<OutputFormat>,
+
+    /// Write MinKNOW/dorado run metadata (run ID, flow cell, channel, start time, ...) with
+    /// every read: simulated values, or those of the input reads
+    #[arg(long, value_enum, value_name = "SOURCE")]
+    pub run_metadata: Option<MetadataSource>,
+
+    /// FASTQ header style of the run metadata (default: that of the input, or minknow)
+    #[arg(long, value_enum, value_name = "STYLE", requires = "run_metadata")]
+    pub header_style: Option<HeaderStyle>,
+
+    /// Number of channels of the simulated flow cell
+    #[arg(long, value_name = "N", default_value = "512")]
+    pub channels: u32,
+
+    /// Translocation speed, which sets read durations
+    #[arg(long, value_name = "N", default_value = "400")]
+    pub bases_per_second: f64,
+
+    /// Hours after which half of the pores have stopped reading, slowing the read rate
+    #[arg(long, value_name = "HOURS")]
+    pub pore_half_life: Option<f64>,
+
+    /// Relative change of read lengths per hour of the run (e.g. -0.01 for 1% shorter reads
+    /// every hour)
+    #[arg(long, value_name = "FRACTION", allow_negative_numbers = true)]
+    pub length_drift: Option<f64>,
+
+    /// Change of base qualities per hour of the run, in Phred units
+    #[arg(long, value_name = "Q", allow_negative_numbers = true)]
+    pub quality_drift: Option<f64>,
+
+    /// Estimate the pore half-life and length and quality drifts from the start times of the
+    /// input reads
+    #[arg(long, conflicts_with_all = ["pore_half_life", "length_drift", "quality_drift"])]
+    pub drift_from_input: bool,
+
+    /// Write a per-read truth table (origin on the reference and carried variants) as TSV
+    #[arg(long, value_name = "TSV")]
+    pub truth: Option<PathBuf>,
+
+    /// Mean read quality (computed as by dorado) reads need to pass filtering, for the pass/fail
+    /// split, live chunks and the sequencing summary
+    #[arg(long, value_name = "Q", default_value = "10")]
+    pub min_qscore: f32,
+
+    /// Write reads whose mean quality is below --min-qscore to this file instead of the output;
+    /// both files get the mean quality of every read in a qs tag
+    #[arg(long, value_name = "FILE", conflicts_with = "live")]
+    pub fail_output: Option<PathBuf>,
+
+    /// Write the output directory as a live MinKNOW run, with reads in rolling chunks in
+    /// fastq_pass/ and fastq_fail/
+    #[arg(long)]
+    pub live: bool,
+
+    /// Number of reads after which a live chunk is closed
+    #[arg(long, value_name = "N", default_value = "4000", requires = "live")]
+    pub chunk_reads: usize,
+
+    /// Also close live chunks every given number of seconds of run time
+    #[arg(long, value_name = "SECONDS", requires = "live")]
+    pub chunk_seconds: Option<f64>,
+
+    /// Pace the live output to the run time, sped up by this factor (1 for real time)
+    #[arg(long, value_name = "FACTOR", requires = "live")]
+    pub live_speed: Option<f64>,
+
+    /// Write a MinKNOW-style sequencing_summary.txt of the reads (channel, start time, duration,
+    /// length, mean qscore, pass/fail, barcode and end reason)
+    #[arg(long, value_name = "TXT")]
+    pub sequencing_summary: Option<PathBuf>,
+
+    /// Write the structural variants applied on every haplotype, and any somatic variants, as a VCF
+    #[arg(long, value_name = "VCF")]
+    pub truth_vcf: Option<PathBuf>,
+
     /// Number of reads to generate
     #[arg(short = 'n', long, default_value = "100000")]
     pub num_reads: usize,
@@ -41,11 +381,19 @@ pub struct Cli {
     #[arg(short = 's', long)]
     pub seed: Option<u64>,
 
+    /// Generate only one shard of the reads, as INDEX/TOTAL (e.g. 3/16, requires --seed)
+    #[arg(long, value_name = "INDEX/TOTAL")]
+    pub shard: Option<Shard>,
+
+    /// Number of read generation threads (default: 0, auto-detection)
+    #[arg(short = 't', long, default_value = "0")]
+    pub threads: usize,
+
     /// Number of compression threads (default: 4, use 0 for auto-detection)
     #[arg(long = "compression-threads", default_value = "4")]
     pub compression_threads: usize,
 
-    /// Error substitution rate (default: 0.7)
+    /// Error substitution rate (default: 0.7, 0.35 for direct RNA)
     #[arg(long, value_name = "RATE")]
     pub error_sub: Option<f64>,
 
@@ -53,7 +401,7 @@ pub struct Cli {
     #[arg(long, value_name = "RATE")]
     pub error_ins: Option<f64>,
 
-    /// Error deletion rate (default: 0.2)
+    /// Error deletion rate (default: 0.2, 0.55 for direct RNA)
     #[arg(long, value_name = "RATE")]
     pub error_del: Option<f64>,
 
@@ -61,7 +409,7 @@ pub struct Cli {
     #[arg(long, value_name = "RATE")]
     pub error_ins_ext: Option<f64>,
 
-    /// Error deletion extension rate (default: 0.4)
+    /// Error deletion extension rate (default: 0.4, 0.5 for direct RNA)
     #[arg(long, value_name = "RATE")]
     pub error_del_ext: Option<f64>,
 
@@ -70,6 +418,60 @@ pub struct Cli {
     pub verbose: bool,
 }
 
+/// A slice of the simulated reads, for splitting a run across independent jobs.
+///
+/// Shards are 1-based: `1/4` is the first quarter of the reads and `4/4` the last one.
+/// Concatenating all shards of a run in order yields the same reads as the unsharded run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: usize,
+    pub total: usize,
+}
+
+impl Shard {
+    /// Returns the range of read indices covered by this shard.
+    ///
+    /// # Arguments
+    /// * `num_reads` - Total number of reads in the unsharded run
+    pub fn read_range(&self, num_reads: usize) -> Range<usize> {
+        let bound = |i: usize| (num_reads as u128 * i as u128 / self.total as u128) as usize;
+        bound(self.index - 1)..bound(self.index)
+    }
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, total) = s
+            .split_once('/')
+            .ok_or_else(|| format!("expected INDEX/TOTAL, got '{}'", s))?;
+        let index: usize = index
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid shard index '{}'", index))?;
+        let total: usize = total
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid shard total '{}'", total))?;
+
+        if total == 0 || index == 0 || index > total {
+            return Err(format!(
+                "shard index must be between 1 and {}, got {}",
+                total, index
+            ));
+        }
+
+        Ok(Self { index, total })
+    }
+}
+
+impl Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.total)
+    }
+}
+
 /// Formatting utilities for console output
 pub mod fmt {
     use super::*;
@@ -98,4 +500,41 @@ pub mod fmt {
     pub fn success(text: impl Display) -> String {
         format!("{} {}", style("✓").green().bold(), style(text).green())
     }
+
+    pub fn warning(text: impl Display) -> String {
+        format!("{} {}", style("!").yellow().bold(), style(text).yellow())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shard() {
+        assert_eq!(
+            "3/16".parse::<Shard>().unwrap(),
+            Shard {
+                index: 3,
+                total: 16
+            }
+        );
+        assert!("0/16".parse::<Shard>().is_err());
+        assert!("17/16".parse::<Shard>().is_err());
+        assert!("3".parse::<Shard>().is_err());
+        assert!("a/b".parse::<Shard>().is_err());
+    }
+
+    #[test]
+    fn test_shards_cover_all_reads() {
+        let num_reads = 1003;
+        let total = 16;
+        let mut expected_start = 0;
+        for index in 1..=total {
+            let range = Shard { index, total }.read_range(num_reads);
+            assert_eq!(range.start, expected_start);
+            expected_start = range.end;
+        }
+        assert_eq!(expected_start, num_reads);
+    }
 }
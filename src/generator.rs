@@ -1,16 +1,77 @@
-use crate::io::fasta::FastaRecord;
+use crate::amplicons::PrimerScheme;
+use crate::haplotype::Haplotype;
+use crate::io::fasta::Reference;
 use crate::models::error::AlterationType;
-use crate::models::{ErrorModel, LengthModel, QualityModel};
-use crate::utils::QUALITY_MAPPING;
+use crate::models::methylation::canonical_base;
+use crate::models::{
+    AdaptiveSampling, AlphabetPolicy, CutSites, DuplexModel, DuplexRole, EndReason, ErrorModel,
+    LengthModel, MethylationModel, ModificationCall, QualityModel, SamplingSpan, TooLongPolicy,
+};
+use crate::repeats::{ExpandedTract, haplotype_position};
+use crate::run::{ChannelOccupancy, ReadTiming, RunTimeline};
+use crate::transcriptome::{Transcriptome, to_uracil};
+use crate::utils::{QUALITY_MAPPING, derive_rng};
+use crate::variants::reverse_complement;
 use anyhow::{Result, anyhow, bail};
 use noodles::fastq;
-use rand::prelude::IndexedRandom;
+use rand::Rng;
 use rand::rngs::StdRng;
-use rand::{Rng, SeedableRng};
-use uuid::Uuid;
+use rayon::prelude::*;
+use uuid::Builder;
 
 const PHRED_OFFSET: u8 = 33;
 
+/// Random stream used for per-read sampling (see [`derive_rng`]).
+const READ_STREAM: u64 = 0;
+
+/// Random stream used for placing reads on the run timeline (see [`derive_rng`]).
+const TIMELINE_STREAM: u64 = 4;
+
+/// Pause between the template and complement reads of a duplex molecule, in seconds.
+const DUPLEX_GAP: f64 = 0.1;
+
+/// Default number of attempts to generate a single read before giving up.
+const MAX_ATTEMPTS_DEFAULT: usize = 1000;
+
+/// Number of reads generated at a time when replaying a run timeline up to a seek index.
+const SEEK_BATCH_SIZE: usize = 10_000;
+
+/// A simulated read together with its origin on the reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedRead {
+    pub record: fastq::Record,
+    /// ID of the reference sequence the read was sampled from
+    pub sequence_id: String,
+    /// Zero-based start of the read template in reference coordinates
+    pub start: usize,
+    /// Zero-based, exclusive end of the read template in reference coordinates
+    pub end: usize,
+    /// Labels of the variants carried by the read template
+    pub variants: Vec<String>,
+    /// 1-based number of the haplotype the read was drawn from, when there are several
+    pub haplotype: Option<usize>,
+    /// Tumour clone the read was drawn from, for tumour samples
+    pub clone: Option<String>,
+    /// Copy number of each repeat allele in the read template, by locus ID
+    pub repeats: Vec<(String, usize)>,
+    /// Base modification calls on the read, sorted by position
+    pub modifications: Vec<ModificationCall>,
+    /// Whether the read is the reverse complement of its reference sequence
+    pub reverse: bool,
+    /// Length of the poly-A tail of transcript reads
+    pub polya_length: Option<usize>,
+    /// Why sequencing of the read stopped, under adaptive sampling
+    pub end_reason: Option<EndReason>,
+    /// Role of the read in duplex basecalling, when duplex reads are simulated
+    pub duplex: Option<DuplexRole>,
+    /// ID of the template read a complement or duplex read belongs to
+    pub parent_id: Option<String>,
+    /// Complement and duplex reads of the same molecule, which follow this read
+    pub partners: Vec<SimulatedRead>,
+    /// Channel and time the read was sequenced at, when the run timeline is simulated
+    pub timing: Option<ReadTiming>,
+}
+
 /// Generator for synthetic sequencing reads with realistic error profiles.
 ///
 /// Produces FASTQ records by sampling subsequences from reference genomes and applying
@@ -45,18 +106,69 @@ const PHRED_OFFSET: u8 = 33;
 /// let read = generator.generate_read().unwrap();
 /// ```
 pub struct ReadGenerator {
-    reference_sequences: Vec<FastaRecord>,
+    haplotypes: Vec<Haplotype>,
+    cumulative_weights: Vec<f64>,
     length_model: LengthModel,
     quality_model: QualityModel,
     error_model: ErrorModel,
-    rng: StdRng,
+    alphabet_policy: AlphabetPolicy,
+    too_long_policy: TooLongPolicy,
+    methylation: Option<MethylationModel>,
+    transcriptome: Option<Transcriptome>,
+    primer_scheme: Option<PrimerScheme>,
+    cut_sites: Option<CutSites>,
+    adaptive_sampling: Option<AdaptiveSampling>,
+    duplex: Option<DuplexModel>,
+    timeline: Option<RunTimeline>,
+    occupancy: ChannelOccupancy,
+    max_attempts: usize,
+    longest_span: usize,
+    seed: u64,
+    next_index: u64,
+    skipped_reads: usize,
+}
+
+/// Reasons why attempts to generate a read were rejected, for error reporting.
+#[derive(Default)]
+struct RejectedAttempts {
+    too_long: usize,
+    longest_sampled: usize,
+    too_many_ns: usize,
+    no_quality: usize,
+}
+
+/// Outcome of drawing a read template.
+enum Sampled {
+    /// A template to turn into a read
+    Template(Template),
+    /// The attempt was rejected and another one should be made
+    Retry,
+    /// The read is skipped under [`TooLongPolicy::Skip`]
+    Skip,
+}
+
+/// A read template and where it lies on its haplotype's sequences.
+struct Template {
+    sequence: Vec<u8>,
+    haplotype: usize,
+    sequence_index: usize,
+    /// Zero-based start of the template
+    start: usize,
+    /// Zero-based, exclusive end of the template (excluding any poly-A tail)
+    end: usize,
+    repeats: Vec<(String, usize)>,
+    /// Expanded repeat tracts of the template
+    tracts: Vec<ExpandedTract>,
+    reverse: bool,
+    polya_length: Option<usize>,
 }
 
 impl ReadGenerator {
     /// Creates a new read generator with specified models and random seed.
     ///
     /// # Arguments
-    /// * `reference_sequences` - Reference genomes to sample subsequences from (must not be empty)
+    /// * `reference` - Reference genomes to sample subsequences from (must not be empty),
+    ///   either loaded records or an indexed FASTA
     /// * `length_model` - Empirical model of read lengths
     /// * `quality_model` - Empirical model of quality scores by read length
     /// * `seed` - Optional random seed for reproducibility (uses system entropy if None)
@@ -65,73 +177,948 @@ impl ReadGenerator {
     /// A configured `ReadGenerator` ready to produce reads
     ///
     /// # Errors
-    /// Returns an error if `reference_sequences` is empty
+    /// Returns an error if `reference` is empty
     pub fn new(
-        reference_sequences: Vec<FastaRecord>,
+        reference: impl Into<Reference>,
         length_model: LengthModel,
         quality_model: QualityModel,
         error_model: ErrorModel,
         seed: Option<u64>,
     ) -> Result<Self> {
-        if reference_sequences.is_empty() {
+        Self::from_haplotypes(
+            vec![Haplotype::new(reference)],
+            length_model,
+            quality_model,
+            error_model,
+            seed,
+        )
+    }
+
+    /// Creates a read generator drawing reads from one or more haplotypes.
+    ///
+    /// Each read comes from one haplotype, chosen with probability proportional to the
+    /// haplotype weights. Reads are tagged with the number and clone of their haplotype, when set.
+    ///
+    /// # Arguments
+    /// * `haplotypes` - Haplotypes to sample subsequences from (must not be empty)
+    /// * `length_model` - Empirical model of read lengths
+    /// * `quality_model` - Empirical model of quality scores by read length
+    /// * `seed` - Optional random seed for reproducibility (uses system entropy if None)
+    ///
+    /// # Errors
+    /// Returns an error if there are no haplotypes, a haplotype has no sequences, or the
+    /// weights do not add up to a positive number
+    pub fn from_haplotypes(
+        haplotypes: Vec<Haplotype>,
+        length_model: LengthModel,
+        quality_model: QualityModel,
+        error_model: ErrorModel,
+        seed: Option<u64>,
+    ) -> Result<Self> {
+        if haplotypes.is_empty() || haplotypes.iter().any(|h| h.reference().is_empty()) {
             bail!("Reference sequences cannot be empty");
         }
-
-        let rng = match seed {
-            Some(s) => StdRng::seed_from_u64(s),
-            None => StdRng::from_rng(&mut rand::rng()),
-        };
+        let cumulative_weights: Vec<f64> = haplotypes
+            .iter()
+            .scan(0.0, |total, haplotype| {
+                *total += haplotype.weight();
+                Some(*total)
+            })
+            .collect();
+        if cumulative_weights.last().is_none_or(|&total| total <= 0.0) {
+            bail!("Haplotype weights must have a positive total");
+        }
+        let longest_span = haplotypes
+            .iter()
+            .map(Haplotype::longest_span)
+            .max()
+            .unwrap_or(0);
 
         Ok(Self {
-            reference_sequences,
+            haplotypes,
+            cumulative_weights,
             length_model,
             quality_model,
             error_model,
-            rng,
+            alphabet_policy: AlphabetPolicy::default(),
+            too_long_policy: TooLongPolicy::default(),
+            methylation: None,
+            transcriptome: None,
+            primer_scheme: None,
+            cut_sites: None,
+            adaptive_sampling: None,
+            duplex: None,
+            timeline: None,
+            occupancy: ChannelOccupancy::default(),
+            max_attempts: MAX_ATTEMPTS_DEFAULT,
+            longest_span,
+            seed: seed.unwrap_or_else(rand::random),
+            next_index: 0,
+            skipped_reads: 0,
         })
     }
 
-    /// Generates a single synthetic read with realistic sequencing errors.
+    /// Sets the policy for soft-masked, ambiguous and `N` reference bases.
+    ///
+    /// # Arguments
+    /// * `alphabet_policy` - Policy applied to every sampled reference window
+    pub fn with_alphabet_policy(mut self, alphabet_policy: AlphabetPolicy) -> Self {
+        self.alphabet_policy = alphabet_policy;
+        self
+    }
+
+    /// Sets what happens when a sampled length exceeds the chosen reference sequence.
+    ///
+    /// # Arguments
+    /// * `too_long_policy` - Policy for reads longer than their reference sequence
+    pub fn with_too_long_policy(mut self, too_long_policy: TooLongPolicy) -> Self {
+        self.too_long_policy = too_long_policy;
+        self
+    }
+
+    /// Sets the model of base modifications called on every read.
+    ///
+    /// # Arguments
+    /// * `methylation` - Modification rates and call model
+    pub fn with_methylation(mut self, methylation: MethylationModel) -> Self {
+        self.methylation = Some(methylation);
+        self
+    }
+
+    /// Draws reads from transcript molecules (cDNA or direct RNA) instead of from genome windows.
+    ///
+    /// The transcript sequences are those of the first haplotype, and the length model only
+    /// sizes 5'-truncated molecules. Base modifications are not called on transcript reads.
+    ///
+    /// # Arguments
+    /// * `transcriptome` - Transcript expression, truncation and poly-A model
+    pub fn with_transcriptome(mut self, transcriptome: Transcriptome) -> Self {
+        self.transcriptome = Some(transcriptome);
+        self
+    }
+
+    /// Draws every read as a full amplicon of a primer scheme, from either strand, instead of
+    /// a window sized by the length model.
+    ///
+    /// Amplicons are placed on each haplotype through its variant layout, so all haplotypes
+    /// must share the sequences of the reference the scheme was built from. Base
+    /// modifications are not called on amplicon reads.
+    ///
+    /// # Arguments
+    /// * `primer_scheme` - Amplicons and their depths
+    pub fn with_primer_scheme(mut self, primer_scheme: PrimerScheme) -> Self {
+        self.primer_scheme = Some(primer_scheme);
+        self
+    }
+
+    /// Starts reads at Cas9 cut sites, except for a fraction of background reads placed as
+    /// usual.
+    ///
+    /// Read lengths come from the length model, cut short at the end of the sequence. Cut
+    /// positions are placed on each haplotype through its variant layout, so all haplotypes
+    /// must share the sequences of the reference the sites were built from.
+    ///
+    /// # Arguments
+    /// * `cut_sites` - Cut positions, orientations and background fraction
+    pub fn with_cut_sites(mut self, cut_sites: CutSites) -> Self {
+        self.cut_sites = Some(cut_sites);
+        self
+    }
+
+    /// Ejects molecules that do not start in a target region after their first bases, as
+    /// adaptive sampling does.
+    ///
+    /// Reads are placed as usual, and those whose first bases (the decision length) miss
+    /// every target are cut down to the decision length before qualities and errors are
+    /// sampled. Every read is flagged with its [`EndReason`]. Targets are matched through each
+    /// haplotype's variant layout, so all haplotypes must share the sequences of the reference
+    /// the targets were built from.
+    ///
+    /// # Arguments
+    /// * `adaptive_sampling` - Target regions and decision lengths
+    pub fn with_adaptive_sampling(mut self, adaptive_sampling: AdaptiveSampling) -> Self {
+        self.adaptive_sampling = Some(adaptive_sampling);
+        self
+    }
+
+    /// Reads the complement strand of a fraction of the molecules right after their template
+    /// strand, optionally with a duplex consensus read of the pair.
+    ///
+    /// Complement and duplex reads get their own qualities and errors, and are returned as
+    /// [`partners`](SimulatedRead::partners) of the template read. Every read is flagged with
+    /// its [`DuplexRole`]. Reads ejected by adaptive sampling are never paired.
+    ///
+    /// # Arguments
+    /// * `duplex` - Pairing fraction and duplex quality model
+    pub fn with_duplex(mut self, duplex: DuplexModel) -> Self {
+        self.duplex = Some(duplex);
+        self
+    }
+
+    /// Places every read on the timeline of a run, giving it a channel, start time and duration.
+    ///
+    /// Timings are drawn from a random stream of their own, so reads are otherwise unchanged
+    /// unless the timeline makes read lengths or qualities drift over the run. Complement reads
+    /// follow their template read on the same channel. As a read waits for its channel to
+    /// finish the reads before it, reads are timed in index order by
+    /// [`generate_read`](Self::generate_read) and [`generate_batch`](Self::generate_batch).
+    ///
+    /// # Arguments
+    /// * `timeline` - Channels, read rate and drift of the run
+    pub fn with_run_timeline(mut self, timeline: RunTimeline) -> Self {
+        self.timeline = Some(timeline);
+        self
+    }
+
+    /// Sets the number of attempts allowed to generate a single read before giving up.
     ///
-    /// Samples a read length from the model, chooses a random reference sequence,
-    /// extracts a random subsequence, applies quality-based errors, and returns a FASTQ record.
-    /// Automatically retries if the sampled length exceeds the reference sequence length.
+    /// # Arguments
+    /// * `max_attempts` - Maximum attempts per read (at least 1)
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Returns the number of reads skipped so far under [`TooLongPolicy::Skip`].
+    pub fn skipped_reads(&self) -> usize {
+        self.skipped_reads
+    }
+
+    /// Moves the generator to the given read index.
+    ///
+    /// The next call to [`generate_read`](Self::generate_read) or
+    /// [`generate_batch`](Self::generate_batch) produces read `index` of the run, exactly as
+    /// it would have been produced after generating all preceding reads. With a run timeline,
+    /// the preceding reads are generated again to find when their channels are free.
+    ///
+    /// # Arguments
+    /// * `index` - Zero-based index of the next read to generate
+    ///
+    /// # Errors
+    /// Returns an error if a preceding read cannot be generated
+    pub fn seek(&mut self, index: u64) -> Result<()> {
+        self.next_index = 0;
+        self.occupancy = ChannelOccupancy::default();
+        if self.timeline.is_some() {
+            let skipped_reads = self.skipped_reads;
+            while self.next_index < index {
+                let count = (index - self.next_index).min(SEEK_BATCH_SIZE as u64);
+                self.generate_batch(count as usize)?;
+            }
+            self.skipped_reads = skipped_reads;
+        }
+        self.next_index = index;
+        Ok(())
+    }
+
+    /// Generates the next synthetic read with realistic sequencing errors.
+    ///
+    /// Equivalent to calling [`generate_read_at`](Self::generate_read_at) with an internal
+    /// counter that advances by one on every call. Skipped read indices are passed over.
     ///
     /// # Returns
-    /// A `fastq::Record` with simulated sequencing errors based on quality scores
+    /// A [`SimulatedRead`] with simulated sequencing errors based on quality scores
     ///
     /// # Errors
-    /// Returns an error if the length or quality models are empty
-    pub fn generate_read(&mut self) -> Result<fastq::Record> {
-        loop {
-            let length = self
-                .length_model
-                .sample(&mut self.rng)
-                .ok_or_else(|| anyhow!("Length model is empty"))?;
-            let reference_sequence = self.reference_sequences.choose(&mut self.rng).unwrap();
+    /// Returns an error if the length or quality models are empty, the retry budget is
+    /// exhausted, or every read within the retry budget is skipped
+    pub fn generate_read(&mut self) -> Result<SimulatedRead> {
+        for _ in 0..self.max_attempts {
+            let index = self.next_index;
+            let read = self.generate_read_at(index)?;
+            self.next_index += 1;
+            match read {
+                Some(mut read) => {
+                    self.time_reads(index, &mut read);
+                    return Ok(read);
+                }
+                None => self.skipped_reads += 1,
+            }
+        }
+        bail!(
+            "Skipped {} consecutive reads longer than their reference sequence",
+            self.max_attempts
+        )
+    }
 
-            // Skip if sampled length is longer than reference sequence
-            if length > reference_sequence.sequence.len() {
-                continue;
+    /// Generates the next `count` reads in parallel using the current rayon thread pool.
+    ///
+    /// Reads are returned in index order, and each one is identical to what
+    /// [`generate_read_at`](Self::generate_read_at) produces for its index, placed on the run
+    /// timeline, regardless of the number of worker threads. Skipped reads are left out and counted, and the duplex
+    /// partners of a read follow it.
+    ///
+    /// # Arguments
+    /// * `count` - Number of read indices to generate
+    ///
+    /// # Errors
+    /// Returns an error if the length or quality models are empty or the retry budget is exhausted
+    pub fn generate_batch(&mut self, count: usize) -> Result<Vec<SimulatedRead>> {
+        let start = self.next_index;
+        let end = start + count as u64;
+        let reads = (start..end)
+            .into_par_iter()
+            .map(|index| self.generate_read_at(index))
+            .collect::<Result<Vec<_>>>()?;
+        self.next_index = end;
+
+        let mut generated = Vec::with_capacity(count);
+        for (index, read) in (start..end).zip(reads) {
+            if let Some(mut read) = read {
+                self.time_reads(index, &mut read);
+                generated.push(read);
             }
+        }
+        self.skipped_reads += count - generated.len();
+        Ok(generated
+            .into_iter()
+            .flat_map(|mut read| {
+                let partners = std::mem::take(&mut read.partners);
+                std::iter::once(read).chain(partners)
+            })
+            .collect())
+    }
+
+    /// Places a read and its duplex partners on the run timeline, if there is one.
+    ///
+    /// # Arguments
+    /// * `index` - Zero-based index of the read in the run
+    /// * `read` - Read to time, with its partners
+    fn time_reads(&mut self, index: u64, read: &mut SimulatedRead) {
+        let Some(timeline) = &self.timeline else {
+            return;
+        };
+        let mut rng = derive_rng(self.seed, TIMELINE_STREAM, index);
+        let timing = timeline.time_read(
+            index,
+            read.record.sequence().len(),
+            &mut self.occupancy,
+            &mut rng,
+        );
+        read.timing = Some(timing);
+        for partner in &mut read.partners {
+            partner.timing = Some(match partner.duplex {
+                // Duplex reads are reported at the start of their template read
+                Some(DuplexRole::Duplex) => timing,
+                _ => {
+                    let complement = ReadTiming {
+                        read_number: timing.read_number + 1,
+                        start_time: timing.start_time + timing.duration + DUPLEX_GAP,
+                        duration: timeline.duration(partner.record.sequence().len()),
+                        ..timing
+                    };
+                    self.occupancy.occupy(
+                        complement.channel,
+                        complement.start_time + complement.duration,
+                    );
+                    complement
+                }
+            });
+        }
+    }
+
+    /// Generates the read with the given index.
+    ///
+    /// Samples a read length from the model, chooses a random reference sequence (or target
+    /// region), extracts a random subsequence, applies quality-based errors, and returns a FASTQ record.
+    /// Retries (up to the configured number of attempts) if the subsequence has too many `N`
+    /// bases or no quality string is available for its length. Lengths exceeding the
+    /// reference sequence are handled according to the [`TooLongPolicy`].
+    ///
+    /// Every read index draws from its own random stream derived from the generator seed,
+    /// so the result depends only on the seed and the index. The read is not yet placed on
+    /// the run timeline, which depends on the reads before it.
+    ///
+    /// # Arguments
+    /// * `index` - Zero-based position of the read in the simulated output
+    ///
+    /// # Returns
+    /// A [`SimulatedRead`] with simulated sequencing errors based on quality scores, or None
+    /// if the read was skipped
+    ///
+    /// # Errors
+    /// Returns an error if the length or quality models are empty, or if no read could be
+    /// generated within the retry budget
+    pub fn generate_read_at(&self, index: u64) -> Result<Option<SimulatedRead>> {
+        let mut rng = derive_rng(self.seed, READ_STREAM, index);
+        let mut rejected = RejectedAttempts::default();
+        // Drifting reads depend on when they start, which is known from their index alone
+        let start_time = self
+            .timeline
+            .as_ref()
+            .filter(|timeline| timeline.has_drift())
+            .map(|timeline| {
+                timeline.start_time(index, &mut derive_rng(self.seed, TIMELINE_STREAM, index))
+            });
 
-            let max_start = reference_sequence.sequence.len() - length;
-            let start_position = self.rng.random_range(0..=max_start);
-            let sequence =
-                reference_sequence.sequence[start_position..start_position + length].to_vec();
+        for _ in 0..self.max_attempts {
+            let mut template = match self.sample_template(&mut rng, &mut rejected, start_time)? {
+                Sampled::Template(template) => template,
+                Sampled::Retry => continue,
+                Sampled::Skip => return Ok(None),
+            };
+            let end_reason = self
+                .adaptive_sampling
+                .as_ref()
+                .map(|adaptive| self.decide(adaptive, &mut template, &mut rng));
+            let Template {
+                mut sequence,
+                haplotype,
+                sequence_index,
+                start: start_position,
+                end: end_position,
+                repeats,
+                tracts,
+                reverse,
+                polya_length,
+            } = template;
+            // Repeat expansions, contractions and poly-A tails change the template length
+            let length = sequence.len();
+
+            // Skip windows dominated by unknown bases
+            if !self.alphabet_policy.accepts(&sequence) {
+                rejected.too_many_ns += 1;
+                continue;
+            }
+            let soft_masked = self.alphabet_policy.normalize(&mut sequence, &mut rng);
+            // Both strands of a duplex molecule are read from the same template
+            let molecule = self
+                .duplex
+                .as_ref()
+                .map(|_| (sequence.clone(), soft_masked.clone()));
 
-            let Some(qualities) = self.quality_model.sample(length, &mut self.rng) else {
+            let Some(mut qualities) = self.quality_model.sample(length, &mut rng) else {
+                rejected.no_quality += 1;
                 continue; // Skip if no quality string available
             };
+            self.drift_qualities(&mut qualities, start_time);
+
+            let mut modifications = match &self.methylation {
+                // PCR amplicons lose the modifications of the original molecules
+                Some(model) if self.transcriptome.is_none() && self.primer_scheme.is_none() => {
+                    let layout = self.haplotypes[haplotype].variants();
+                    let reference_position = |position: usize| {
+                        // Reverse-complemented templates run from the end of their span
+                        let position = if reverse {
+                            end_position - 1 - position
+                        } else {
+                            haplotype_position(&tracts, start_position, position)?
+                        };
+                        Some(layout.map_or(position, |layout| {
+                            layout.to_reference(sequence_index, position)
+                        }))
+                    };
+                    model.call(
+                        &sequence,
+                        sequence_index,
+                        reverse,
+                        reference_position,
+                        &mut rng,
+                    )
+                }
+                _ => Vec::new(),
+            };
 
-            let (final_sequence, final_qualities) = self.apply_errors(sequence, qualities);
+            let mut read_positions = Vec::new();
+            let (mut final_sequence, final_qualities) = self.apply_errors(
+                sequence,
+                qualities,
+                &soft_masked,
+                (!modifications.is_empty()).then_some(&mut read_positions),
+                &mut rng,
+            );
+            // Calls follow their base into the read, unless it was deleted or substituted
+            modifications.retain_mut(|call| {
+                let Some(position) = read_positions[call.position] else {
+                    return false;
+                };
+                call.position = position;
+                canonical_base(call.code) == Some(final_sequence[position].to_ascii_uppercase())
+            });
+            if self
+                .transcriptome
+                .as_ref()
+                .is_some_and(Transcriptome::uracil)
+            {
+                to_uracil(&mut final_sequence);
+            }
 
-            let id = format!("{}", Uuid::new_v4());
-            return Ok(fastq::Record::new(
-                fastq::record::Definition::new(id, ""),
+            let id = Builder::from_random_bytes(rng.random()).into_uuid();
+            let record = fastq::Record::new(
+                fastq::record::Definition::new(id.to_string(), ""),
                 final_sequence,
                 final_qualities,
+            );
+            let mut read = SimulatedRead {
+                repeats,
+                modifications,
+                reverse,
+                polya_length,
+                end_reason,
+                ..self.locate_read(
+                    record,
+                    haplotype,
+                    sequence_index,
+                    start_position,
+                    end_position,
+                )
+            };
+            if let Some(duplex) = &self.duplex
+                && let Some((sequence, soft_masked)) = molecule
+            {
+                read.duplex = Some(DuplexRole::Simplex);
+                if end_reason != Some(EndReason::DataServiceUnblockMuxChange)
+                    && duplex.is_paired(&mut rng)
+                {
+                    self.pair_read(
+                        duplex,
+                        &mut read,
+                        sequence,
+                        soft_masked,
+                        start_time,
+                        &mut rng,
+                    );
+                }
+            }
+            return Ok(Some(read));
+        }
+
+        let mut reasons = Vec::new();
+        if rejected.too_long > 0 {
+            let longest = if rejected.longest_sampled > 0 {
+                format!(" (up to {} bp)", rejected.longest_sampled)
+            } else {
+                String::new()
+            };
+            reasons.push(format!(
+                "{} sampled lengths{} did not fit the {} (longest is {} bp)",
+                rejected.too_long,
+                longest,
+                if self.haplotypes.iter().any(|h| h.targets().is_some()) {
+                    "target regions"
+                } else {
+                    "reference sequences"
+                },
+                self.longest_span
+            ));
+        }
+        if rejected.too_many_ns > 0 {
+            reasons.push(format!(
+                "{} windows exceeded the maximum N fraction of {}",
+                rejected.too_many_ns, self.alphabet_policy.max_n_fraction
+            ));
+        }
+        if rejected.no_quality > 0 {
+            reasons.push(format!(
+                "{} lengths had no quality string long enough",
+                rejected.no_quality
+            ));
+        }
+        bail!(
+            "Failed to generate read {} after {} attempts: {}",
+            index,
+            self.max_attempts,
+            reasons.join("; ")
+        )
+    }
+
+    /// Draws the template of a read: a transcript molecule in transcriptome mode, an amplicon
+    /// with a primer scheme, a molecule starting at a cut site, or a window of a haplotype sized
+    /// by the length model otherwise.
+    ///
+    /// Lengths drawn from the length model drift with the start time of the read, if given.
+    fn sample_template(
+        &self,
+        rng: &mut StdRng,
+        rejected: &mut RejectedAttempts,
+        start_time: Option<f64>,
+    ) -> Result<Sampled> {
+        if let Some(transcriptome) = &self.transcriptome {
+            let template =
+                transcriptome.sample(self.haplotypes[0].reference(), &self.length_model, rng)?;
+            return Ok(Sampled::Template(Template {
+                sequence: template.sequence,
+                haplotype: 0,
+                sequence_index: template.sequence_index,
+                start: template.start,
+                end: template.end,
+                repeats: Vec::new(),
+                tracts: Vec::new(),
+                reverse: template.reverse,
+                polya_length: Some(template.polya_length),
+            }));
+        }
+
+        if let Some(scheme) = &self.primer_scheme {
+            let haplotype = self.choose_haplotype(rng);
+            let amplicon = scheme.choose(rng);
+            // Primer positions refer to the reference, so follow them onto the haplotype
+            let (start, end) = match self.haplotypes[haplotype].variants() {
+                Some(layout) => (
+                    layout.to_haplotype(amplicon.sequence_index, amplicon.start),
+                    layout.to_haplotype(amplicon.sequence_index, amplicon.end),
+                ),
+                None => (amplicon.start, amplicon.end),
+            };
+            let mut sequence = self.haplotypes[haplotype].reference().fetch(
+                amplicon.sequence_index,
+                start,
+                end - start,
+            )?;
+            let reverse = rng.random_bool(0.5);
+            if reverse {
+                sequence = reverse_complement(&sequence);
+            }
+            return Ok(Sampled::Template(Template {
+                sequence,
+                haplotype,
+                sequence_index: amplicon.sequence_index,
+                start,
+                end,
+                repeats: Vec::new(),
+                tracts: Vec::new(),
+                reverse,
+                polya_length: None,
+            }));
+        }
+
+        if let Some(cut_sites) = &self.cut_sites
+            && let Some(site) = cut_sites.choose(rng)
+        {
+            let haplotype = self.choose_haplotype(rng);
+            let length = self
+                .length_model
+                .sample(rng)
+                .ok_or_else(|| anyhow!("Length model is empty"))?;
+            let length = self.drift_length(length, start_time);
+            let position = match self.haplotypes[haplotype].variants() {
+                Some(layout) => layout.to_haplotype(site.sequence_index, site.position),
+                None => site.position,
+            };
+            let reference = self.haplotypes[haplotype].reference();
+            // Molecules cannot extend past the end of their sequence
+            let (start, end) = if site.reverse {
+                (position.saturating_sub(length), position)
+            } else {
+                let sequence_length = reference.sequence_length(site.sequence_index);
+                (position, (position + length).min(sequence_length))
+            };
+            if start == end {
+                rejected.too_long += 1;
+                return Ok(Sampled::Retry);
+            }
+            let mut sequence = reference.fetch(site.sequence_index, start, end - start)?;
+            if site.reverse {
+                sequence = reverse_complement(&sequence);
+            }
+            return Ok(Sampled::Template(Template {
+                sequence,
+                haplotype,
+                sequence_index: site.sequence_index,
+                start,
+                end,
+                repeats: Vec::new(),
+                tracts: Vec::new(),
+                reverse: site.reverse,
+                polya_length: None,
+            }));
+        }
+
+        let (haplotype, span, length) = if self.too_long_policy == TooLongPolicy::Conditional {
+            let (haplotype, span) = self.choose_span(rng);
+            let Some(length) = self.length_model.sample_at_most(span.max_length(), rng) else {
+                if self.length_model.is_empty() {
+                    bail!("Length model is empty");
+                }
+                rejected.too_long += 1;
+                return Ok(Sampled::Retry);
+            };
+            let length = self.drift_length(length, start_time).min(span.max_length());
+            (haplotype, span, length)
+        } else {
+            let length = self
+                .length_model
+                .sample(rng)
+                .ok_or_else(|| anyhow!("Length model is empty"))?;
+            let length = self.drift_length(length, start_time);
+            let (haplotype, span) = self.choose_span(rng);
+            (haplotype, span, length)
+        };
+
+        let (window_start, window_end) = span.window(length);
+        let (start, length) = if length <= window_end - window_start {
+            let max_start = window_end - length;
+            (rng.random_range(window_start..=max_start), length)
+        } else {
+            rejected.too_long += 1;
+            rejected.longest_sampled = rejected.longest_sampled.max(length);
+            match self.too_long_policy {
+                TooLongPolicy::Truncate => {
+                    // Reads truncated at the sequence end must still start inside the span
+                    let start = rng.random_range(window_start..span.end);
+                    (start, window_end - start)
+                }
+                TooLongPolicy::Skip => return Ok(Sampled::Skip),
+                TooLongPolicy::Resample | TooLongPolicy::Conditional => {
+                    return Ok(Sampled::Retry);
+                }
+            }
+        };
+
+        let reference = self.haplotypes[haplotype].reference();
+        let (sequence, end, repeats, tracts) = match self.haplotypes[haplotype].repeats() {
+            Some(repeats) => {
+                let template = repeats.fetch(reference, span.sequence_index, start, length, rng)?;
+                (
+                    template.sequence,
+                    template.end,
+                    template.repeats,
+                    template.tracts,
+                )
+            }
+            None => (
+                reference.fetch(span.sequence_index, start, length)?,
+                start + length,
+                Vec::new(),
+                Vec::new(),
+            ),
+        };
+
+        Ok(Sampled::Template(Template {
+            sequence,
+            haplotype,
+            sequence_index: span.sequence_index,
+            start,
+            end,
+            repeats,
+            tracts,
+            reverse: false,
+            polya_length: None,
+        }))
+    }
+
+    /// Makes the adaptive sampling decision on a read template, cutting off-target templates
+    /// down to the bases read before they were ejected.
+    ///
+    /// Templates no longer than the decision length are read to the end before a decision
+    /// is made. The decision looks at the bases read, mapped back to the reference.
+    fn decide(
+        &self,
+        adaptive: &AdaptiveSampling,
+        template: &mut Template,
+        rng: &mut StdRng,
+    ) -> EndReason {
+        let decision_length = adaptive.decision_length(rng);
+        if template.sequence.len() <= decision_length {
+            return EndReason::SignalPositive;
+        }
+
+        // Reverse-complemented templates are read from the end of their span
+        let (start, end) = if template.reverse {
+            (template.end - decision_length, template.end)
+        } else {
+            (template.start, template.start + decision_length)
+        };
+        let (start, end) = match self.haplotypes[template.haplotype].variants() {
+            Some(layout) => (
+                layout.to_reference(template.sequence_index, start),
+                layout.to_reference(template.sequence_index, end),
+            ),
+            None => (start, end),
+        };
+        if adaptive.is_on_target(template.sequence_index, start, end) {
+            return EndReason::SignalPositive;
+        }
+
+        template.sequence.truncate(decision_length);
+        if template.reverse {
+            template.start = template.end - decision_length;
+        } else {
+            template.end = template.start + decision_length;
+        }
+        EndReason::DataServiceUnblockMuxChange
+    }
+
+    /// Reads the complement strand of a read's molecule and, if enabled, the duplex consensus
+    /// of both strands, and attaches them as partners of the read.
+    ///
+    /// The pair is left out if no quality string is available for the molecule length.
+    fn pair_read(
+        &self,
+        duplex: &DuplexModel,
+        read: &mut SimulatedRead,
+        sequence: Vec<u8>,
+        soft_masked: Vec<bool>,
+        start_time: Option<f64>,
+        rng: &mut StdRng,
+    ) {
+        let length = sequence.len();
+        let Some(mut qualities) = self.quality_model.sample(length, rng) else {
+            return;
+        };
+        self.drift_qualities(&mut qualities, start_time);
+        let complement_mask: Vec<bool> = soft_masked.iter().rev().copied().collect();
+        let (complement_sequence, complement_qualities) = self.apply_errors(
+            reverse_complement(&sequence),
+            qualities,
+            &complement_mask,
+            None,
+            rng,
+        );
+        let template_id = String::from_utf8_lossy(read.record.name()).into_owned();
+        let complement_id = Builder::from_random_bytes(rng.random())
+            .into_uuid()
+            .to_string();
+        let partner =
+            |id: String, sequence: Vec<u8>, qualities: Vec<u8>, reverse, role| SimulatedRead {
+                record: fastq::Record::new(
+                    fastq::record::Definition::new(id, ""),
+                    sequence,
+                    qualities,
+                ),
+                reverse,
+                modifications: Vec::new(),
+                duplex: Some(role),
+                parent_id: Some(template_id.clone()),
+                partners: Vec::new(),
+                ..read.clone()
+            };
+
+        let mut partners = vec![partner(
+            complement_id.clone(),
+            complement_sequence,
+            complement_qualities,
+            !read.reverse,
+            DuplexRole::Simplex,
+        )];
+        if duplex.consensus()
+            && let Some(mut qualities) = self.quality_model.sample(length, rng)
+        {
+            self.drift_qualities(&mut qualities, start_time);
+            duplex.boost_qualities(&mut qualities);
+            let (sequence, qualities) =
+                self.apply_errors(sequence, qualities, &soft_masked, None, rng);
+            partners.push(partner(
+                format!("{};{}", template_id, complement_id),
+                sequence,
+                qualities,
+                read.reverse,
+                DuplexRole::Duplex,
             ));
+            read.duplex = Some(DuplexRole::Parent);
+            partners[0].duplex = Some(DuplexRole::Parent);
+        }
+        read.partners = partners;
+    }
+
+    /// Scales a sampled length to the run time the read starts at, if lengths drift.
+    fn drift_length(&self, length: usize, start_time: Option<f64>) -> usize {
+        match (&self.timeline, start_time) {
+            (Some(timeline), Some(time)) => timeline.drift_length(length, time),
+            _ => length,
+        }
+    }
+
+    /// Shifts sampled qualities to the run time the read starts at, if qualities drift.
+    fn drift_qualities(&self, qualities: &mut [u8], start_time: Option<f64>) {
+        if let (Some(timeline), Some(time)) = (&self.timeline, start_time) {
+            timeline.drift_qualities(qualities, time);
+        }
+    }
+
+    /// Attaches the origin of a read, mapped back to the reference when variants are applied.
+    fn locate_read(
+        &self,
+        record: fastq::Record,
+        haplotype_index: usize,
+        sequence_index: usize,
+        start: usize,
+        end: usize,
+    ) -> SimulatedRead {
+        let haplotype = &self.haplotypes[haplotype_index];
+        let sequence_id = haplotype
+            .reference()
+            .sequence_id(sequence_index)
+            .to_string();
+        let phase = haplotype.phase();
+        let clone = haplotype.clone_name().map(str::to_string);
+        match haplotype.variants() {
+            Some(layout) => SimulatedRead {
+                record,
+                sequence_id,
+                start: layout.to_reference(sequence_index, start),
+                end: layout.to_reference(sequence_index, end),
+                variants: layout
+                    .variants_in(sequence_index, start, end)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+                haplotype: phase,
+                clone,
+                repeats: Vec::new(),
+                modifications: Vec::new(),
+                reverse: false,
+                polya_length: None,
+                end_reason: None,
+                duplex: None,
+                parent_id: None,
+                partners: Vec::new(),
+                timing: None,
+            },
+            None => SimulatedRead {
+                record,
+                sequence_id,
+                start,
+                end,
+                variants: Vec::new(),
+                haplotype: phase,
+                clone,
+                repeats: Vec::new(),
+                modifications: Vec::new(),
+                reverse: false,
+                polya_length: None,
+                end_reason: None,
+                duplex: None,
+                parent_id: None,
+                partners: Vec::new(),
+                timing: None,
+            },
+        }
+    }
+
+    /// Chooses the haplotype and the part of it the next read is placed in: a target region
+    /// if targets are configured, or a whole sequence otherwise.
+    ///
+    /// # Returns
+    /// Tuple of (haplotype index, sampling span)
+    fn choose_span(&self, rng: &mut StdRng) -> (usize, SamplingSpan) {
+        let haplotype_index = self.choose_haplotype(rng);
+        let haplotype = &self.haplotypes[haplotype_index];
+        let span = match haplotype.targets() {
+            Some(targets) => targets.choose(rng),
+            None => {
+                let reference = haplotype.reference();
+                let sequence_index = rng.random_range(0..reference.len());
+                SamplingSpan::whole_sequence(
+                    sequence_index,
+                    reference.sequence_length(sequence_index),
+                )
+            }
+        };
+        (haplotype_index, span)
+    }
+
+    /// Chooses the haplotype the next read is drawn from, according to the weights.
+    fn choose_haplotype(&self, rng: &mut StdRng) -> usize {
+        // Single haplotypes draw nothing here, keeping their random streams unchanged
+        if self.haplotypes.len() > 1 {
+            let total = self.cumulative_weights[self.cumulative_weights.len() - 1];
+            let point = rng.random_range(0.0..total);
+            self.cumulative_weights
+                .partition_point(|&weight| weight <= point)
+                .min(self.haplotypes.len() - 1)
+        } else {
+            0
         }
     }
 
@@ -143,10 +1130,21 @@ impl ReadGenerator {
     /// # Arguments
     /// * `sequence` - Original nucleotide sequence
     /// * `qualities` - Quality scores for each position
+    /// * `soft_masked` - Positions that were soft-masked in the reference
+    /// * `read_positions` - If given, filled with the read position of every template base
+    ///   (None for deleted bases)
+    /// * `rng` - Random stream of the read being generated
     ///
     /// # Returns
     /// Tuple of (modified sequence, modified quality scores)
-    fn apply_errors(&mut self, sequence: Vec<u8>, qualities: Vec<u8>) -> (Vec<u8>, Vec<u8>) {
+    fn apply_errors(
+        &self,
+        sequence: Vec<u8>,
+        qualities: Vec<u8>,
+        soft_masked: &[bool],
+        mut read_positions: Option<&mut Vec<Option<usize>>>,
+        rng: &mut StdRng,
+    ) -> (Vec<u8>, Vec<u8>) {
         let mut new_sequence = Vec::with_capacity(sequence.len());
         let mut new_quality = Vec::with_capacity(qualities.len());
 
@@ -154,16 +1152,23 @@ impl ReadGenerator {
         while i < sequence.len() {
             let quality_ascii = qualities[i];
             let phred = usize::from(quality_ascii.saturating_sub(PHRED_OFFSET).min(93));
-            let error_probability = QUALITY_MAPPING[phred];
-            let alteration = if self.rng.random_range(0.0..1.0) <= error_probability {
-                self.error_model.get_alteration_type(&mut self.rng)
+            let mut error_probability = QUALITY_MAPPING[phred];
+            if soft_masked[i] {
+                error_probability *= self.alphabet_policy.soft_mask_error_factor as f32;
+            }
+            let alteration = if rng.random_range(0.0..1.0) <= error_probability {
+                self.error_model.get_alteration_type(rng)
             } else {
                 None
             };
 
+            if let Some(read_positions) = read_positions.as_deref_mut() {
+                let deleted = matches!(alteration, Some(AlterationType::Deletion(_)));
+                read_positions.push((!deleted).then_some(new_sequence.len()));
+            }
             match alteration {
                 Some(AlterationType::Substitution) => {
-                    new_sequence.push(self.get_random_nucleotide(Some(sequence[i])));
+                    new_sequence.push(get_random_nucleotide(rng, Some(sequence[i])));
                     new_quality.push(quality_ascii);
                 }
                 Some(AlterationType::Insertion(count)) => {
@@ -171,12 +1176,15 @@ impl ReadGenerator {
                     new_quality.push(quality_ascii);
 
                     for _ in 0..count {
-                        new_sequence.push(self.get_random_nucleotide(None));
+                        new_sequence.push(get_random_nucleotide(rng, None));
                         new_quality.push(quality_ascii); // reuse the same quality for inserted bases
                     }
                 }
                 Some(AlterationType::Deletion(count)) => {
                     let skip = count.max(1).min(sequence.len() - i);
+                    if let Some(read_positions) = read_positions.as_deref_mut() {
+                        read_positions.extend(std::iter::repeat_n(None, skip - 1));
+                    }
                     i += skip;
                     continue;
                 }
@@ -191,39 +1199,52 @@ impl ReadGenerator {
 
         (new_sequence, new_quality)
     }
+}
 
-    /// Returns a random nucleotide, optionally excluding a specific one.
-    ///
-    /// # Arguments
-    /// * `exclude` - Optional nucleotide to exclude (as ASCII byte: b'A', b'C', b'G', or b'T')
-    ///   - `Some(nucleotide)` returns a different nucleotide
-    ///   - `None` returns any random nucleotide
-    ///
-    /// # Returns
-    /// A random nucleotide byte from {A, C, G, T}
-    fn get_random_nucleotide(&mut self, exclude: Option<u8>) -> u8 {
-        const NUCLEOTIDES: [u8; 4] = [b'A', b'C', b'G', b'T'];
+/// Returns a random nucleotide, optionally excluding a specific one.
+///
+/// # Arguments
+/// * `rng` - Random number generator
+/// * `exclude` - Optional nucleotide to exclude (as ASCII byte: b'A', b'C', b'G', or b'T')
+///   - `Some(nucleotide)` returns a different nucleotide, in the same case as the excluded one
+///     (any nucleotide if it is not a canonical base)
+///   - `None` returns any random nucleotide
+///
+/// # Returns
+/// A random nucleotide byte from {A, C, G, T}
+fn get_random_nucleotide(rng: &mut impl Rng, exclude: Option<u8>) -> u8 {
+    const NUCLEOTIDES: [u8; 4] = [b'A', b'C', b'G', b'T'];
 
-        match exclude {
-            Some(nucleotide) => {
-                let idx = NUCLEOTIDES
-                    .iter()
-                    .position(|&n| n == nucleotide)
-                    .unwrap_or(0);
-                let offset = self.rng.random_range(1..=3);
-                NUCLEOTIDES[(idx + offset) % 4]
-            }
-            None => {
-                let idx = self.rng.random_range(0..4);
-                NUCLEOTIDES[idx]
+    match exclude {
+        Some(nucleotide) => {
+            let upper = nucleotide.to_ascii_uppercase();
+            let substitute = match NUCLEOTIDES.iter().position(|&n| n == upper) {
+                Some(idx) => NUCLEOTIDES[(idx + rng.random_range(1..=3)) % 4],
+                None => NUCLEOTIDES[rng.random_range(0..4)],
+            };
+            if nucleotide.is_ascii_lowercase() {
+                substitute.to_ascii_lowercase()
+            } else {
+                substitute
             }
         }
+        None => {
+            let idx = rng.random_range(0..4);
+            NUCLEOTIDES[idx]
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::io::fasta::FastaRecord;
+    use crate::models::TargetRegions;
+    use crate::repeats::CopyNumber;
+    use crate::transcriptome::RnaProtocol;
+    use crate::variants::{Variant, VariantKind, apply_variants};
+    use rand::SeedableRng;
+    use uuid::Uuid;
 
     fn create_test_generator(sequences: Option<Vec<FastaRecord>>) -> Result<ReadGenerator> {
         let sequences = sequences.unwrap_or_else(|| {
@@ -232,7 +1253,10 @@ mod tests {
                 sequence: b"ACGTACGTACGTACGTACGTACGTACGTACGT".to_vec(),
             }]
         });
+        create_haplotype_generator(vec![Haplotype::new(sequences)])
+    }
 
+    fn create_haplotype_generator(haplotypes: Vec<Haplotype>) -> Result<ReadGenerator> {
         let mut length_model = LengthModel::new();
         let mut quality_model = QualityModel::new(None, None, None);
         let error_model = ErrorModel::new(None, None, None, None, None).unwrap();
@@ -241,8 +1265,8 @@ mod tests {
         length_model.add_value(10);
         quality_model.add_value(10, vec![b'?'; 10], &mut rng); // Phred 30 as ASCII
 
-        ReadGenerator::new(
-            sequences,
+        ReadGenerator::from_haplotypes(
+            haplotypes,
             length_model,
             quality_model,
             error_model,
@@ -265,10 +1289,15 @@ mod tests {
         // Generate multiple reads to verify the generator can be reused
         for _ in 0..5 {
             let read = generator.generate_read().unwrap();
-            assert_eq!(read.sequence().len(), 10);
-            assert!(read.quality_scores().iter().all(|&q| q >= PHRED_OFFSET));
+            assert_eq!(read.record.sequence().len(), 10);
+            assert!(
+                read.record
+                    .quality_scores()
+                    .iter()
+                    .all(|&q| q >= PHRED_OFFSET)
+            );
             // Verify the ID is a valid UUID
-            let name_str = std::str::from_utf8(read.name()).unwrap();
+            let name_str = std::str::from_utf8(read.record.name()).unwrap();
             assert!(
                 Uuid::parse_str(name_str).is_ok(),
                 "Expected valid UUID, got: {}",
@@ -279,15 +1308,723 @@ mod tests {
 
     #[test]
     fn test_get_random_nucleotide() {
-        let mut generator = create_test_generator(None).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
 
         // Test with exclusion (substitution)
-        let result = generator.get_random_nucleotide(Some(b'A'));
+        let result = get_random_nucleotide(&mut rng, Some(b'A'));
         assert_ne!(result, b'A');
         assert!(result == b'C' || result == b'G' || result == b'T');
 
+        // Soft-masked bases are never substituted by themselves
+        for _ in 0..100 {
+            let result = get_random_nucleotide(&mut rng, Some(b'c'));
+            assert!(result == b'a' || result == b'g' || result == b't');
+        }
+
         // Test without exclusion (insertion)
-        let result = generator.get_random_nucleotide(None);
+        let result = get_random_nucleotide(&mut rng, None);
         assert!(result == b'A' || result == b'C' || result == b'G' || result == b'T');
     }
+
+    #[test]
+    fn test_batch_matches_sequential_generation() {
+        let mut sequential = create_test_generator(None).unwrap();
+        let expected: Vec<SimulatedRead> = (0..50)
+            .map(|_| sequential.generate_read().unwrap())
+            .collect();
+
+        // Output must not depend on the number of worker threads
+        for threads in [1, 4] {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let mut generator = create_test_generator(None).unwrap();
+            let mut reads = pool.install(|| generator.generate_batch(20)).unwrap();
+            reads.extend(pool.install(|| generator.generate_batch(30)).unwrap());
+            assert_eq!(reads, expected);
+        }
+    }
+
+    #[test]
+    fn test_rejects_windows_with_ns() {
+        let sequences = vec![
+            FastaRecord {
+                id: "gap".to_string(),
+                sequence: vec![b'N'; 32],
+            },
+            FastaRecord {
+                id: "seq1".to_string(),
+                sequence: b"acgtacgtacgtacgtacgtacgtacgtacgt".to_vec(),
+            },
+        ];
+        let mut generator = create_test_generator(Some(sequences)).unwrap();
+
+        for _ in 0..10 {
+            let read = generator.generate_read().unwrap();
+            assert!(!read.record.sequence().contains(&b'N'));
+            assert!(
+                read.record
+                    .sequence()
+                    .iter()
+                    .all(|b| b.is_ascii_uppercase())
+            );
+        }
+    }
+
+    fn create_amplicon_generator(too_long_policy: TooLongPolicy) -> ReadGenerator {
+        let sequences = vec![FastaRecord {
+            id: "amplicon".to_string(),
+            sequence: b"ACGTACGTACGTACGTACGT".to_vec(),
+        }];
+        let mut length_model = LengthModel::new();
+        let mut quality_model = QualityModel::new(None, None, None);
+        let error_model = ErrorModel::new(None, None, None, None, None).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        length_model.add_value(10);
+        length_model.add_value(50);
+        quality_model.add_value(50, vec![b'?'; 50], &mut rng);
+
+        ReadGenerator::new(
+            sequences,
+            length_model,
+            quality_model,
+            error_model,
+            Some(42),
+        )
+        .unwrap()
+        .with_too_long_policy(too_long_policy)
+        .with_max_attempts(50)
+    }
+
+    #[test]
+    fn test_retry_budget_reports_mismatch() {
+        let sequences = vec![FastaRecord {
+            id: "amplicon".to_string(),
+            sequence: b"ACGTACGTAC".to_vec(),
+        }];
+        let mut length_model = LengthModel::new();
+        length_model.add_value(50);
+        let generator = ReadGenerator::new(
+            sequences,
+            length_model,
+            QualityModel::new(None, None, None),
+            ErrorModel::new(None, None, None, None, None).unwrap(),
+            Some(42),
+        )
+        .unwrap()
+        .with_max_attempts(20);
+
+        let err = generator.generate_read_at(0).unwrap_err().to_string();
+        assert!(err.contains("after 20 attempts"), "{}", err);
+        assert!(err.contains("up to 50 bp"), "{}", err);
+        assert!(err.contains("longest is 10 bp"), "{}", err);
+    }
+
+    #[test]
+    fn test_too_long_policies() {
+        let mut truncate = create_amplicon_generator(TooLongPolicy::Truncate);
+        let reads = truncate.generate_batch(20).unwrap();
+        assert_eq!(reads.len(), 20);
+        assert!(reads.iter().all(|r| r.record.sequence().len() <= 25));
+
+        let mut conditional = create_amplicon_generator(TooLongPolicy::Conditional);
+        let reads = conditional.generate_batch(20).unwrap();
+        assert_eq!(reads.len(), 20);
+        assert!(reads.iter().all(|r| r.record.sequence().len() <= 15));
+
+        let mut skip = create_amplicon_generator(TooLongPolicy::Skip);
+        let reads = skip.generate_batch(20).unwrap();
+        assert!(reads.len() < 20);
+        assert_eq!(skip.skipped_reads(), 20 - reads.len());
+    }
+
+    #[test]
+    fn test_reads_from_target_regions() {
+        use crate::io::bed::BedRecord;
+        use crate::models::TargetMode;
+
+        let mut sequence = vec![b'A'; 100];
+        sequence[40..60].fill(b'G');
+        let sequences = vec![FastaRecord {
+            id: "seq1".to_string(),
+            sequence,
+        }];
+        let target = BedRecord {
+            chrom: "seq1".to_string(),
+            start: 40,
+            end: 60,
+            fields: Vec::new(),
+        };
+        let error_model = ErrorModel::new(Some(0.0), Some(0.0), Some(0.0), None, None).unwrap();
+        let mut length_model = LengthModel::new();
+        let mut quality_model = QualityModel::new(None, None, None);
+        let mut rng = StdRng::seed_from_u64(42);
+        length_model.add_value(10);
+        quality_model.add_value(10, vec![b'?'; 10], &mut rng);
+
+        for mode in [TargetMode::Contained, TargetMode::Overlap] {
+            let reference = Reference::from(sequences.clone());
+            let targets =
+                TargetRegions::new(std::slice::from_ref(&target), &reference, mode, false).unwrap();
+            let mut generator = ReadGenerator::from_haplotypes(
+                vec![Haplotype::new(reference).with_targets(targets)],
+                length_model.clone(),
+                quality_model.clone(),
+                error_model.clone(),
+                Some(42),
+            )
+            .unwrap();
+
+            for read in generator.generate_batch(50).unwrap() {
+                let in_target = match mode {
+                    TargetMode::Contained => read.record.sequence().iter().all(|&b| b == b'G'),
+                    TargetMode::Overlap => read.record.sequence().contains(&b'G'),
+                };
+                assert!(in_target, "{:?}", read.record.sequence());
+            }
+        }
+    }
+
+    #[test]
+    fn test_truncated_reads_overlap_targets() {
+        use crate::io::bed::BedRecord;
+        use crate::models::TargetMode;
+
+        // Reads are longer than the sequence, so every one of them is truncated
+        let mut sequence = vec![b'A'; 30];
+        sequence[5..10].fill(b'G');
+        let reference = Reference::from(vec![FastaRecord {
+            id: "seq1".to_string(),
+            sequence,
+        }]);
+        let target = BedRecord {
+            chrom: "seq1".to_string(),
+            start: 5,
+            end: 10,
+            fields: Vec::new(),
+        };
+        let targets =
+            TargetRegions::new(&[target], &reference, TargetMode::Overlap, false).unwrap();
+        let error_model = ErrorModel::new(Some(0.0), Some(0.0), Some(0.0), None, None).unwrap();
+        let mut length_model = LengthModel::new();
+        let mut quality_model = QualityModel::new(None, None, None);
+        let mut rng = StdRng::seed_from_u64(42);
+        length_model.add_value(50);
+        quality_model.add_value(50, vec![b'?'; 50], &mut rng);
+        let mut generator = ReadGenerator::from_haplotypes(
+            vec![Haplotype::new(reference).with_targets(targets)],
+            length_model,
+            quality_model,
+            error_model,
+            Some(42),
+        )
+        .unwrap()
+        .with_too_long_policy(TooLongPolicy::Truncate);
+
+        for read in generator.generate_batch(50).unwrap() {
+            assert!(
+                read.start < 10 && read.end == 30,
+                "{}-{}",
+                read.start,
+                read.end
+            );
+            assert!(read.record.sequence().contains(&b'G'));
+        }
+    }
+
+    #[test]
+    fn test_reads_report_origin_and_variants() {
+        let sequences = vec![FastaRecord {
+            id: "seq1".to_string(),
+            sequence: b"ACGT".repeat(25),
+        }];
+        // 10 bp deletion after position 50, so modified positions >= 50 are shifted by 10
+        let variant = Variant {
+            label: "del1".to_string(),
+            kind: VariantKind::Deletion,
+            sequence_index: 0,
+            start: 50,
+            end: 60,
+            replacement: Vec::new(),
+        };
+        let (modified, layout, _) = apply_variants(&sequences, vec![variant]);
+
+        let generator =
+            create_haplotype_generator(vec![Haplotype::new(modified).with_variants(layout)])
+                .unwrap();
+
+        for index in 0..50 {
+            let read = generator.generate_read_at(index).unwrap().unwrap();
+            assert_eq!(read.sequence_id, "seq1");
+            let carries_deletion = read.start < 50 && read.end > 60;
+            assert_eq!(read.variants == vec!["del1"], carries_deletion);
+            let expected_length = if carries_deletion { 20 } else { 10 };
+            assert_eq!(read.end - read.start, expected_length);
+            assert_eq!(read.haplotype, None);
+        }
+    }
+
+    #[test]
+    fn test_reads_span_amplicons() {
+        use crate::amplicons::PrimerScheme;
+        use crate::io::bed::BedRecord;
+
+        let sequences = vec![FastaRecord {
+            id: "seq1".to_string(),
+            sequence: [vec![b'A'; 20], b"GGCCTTGACC".to_vec(), vec![b'A'; 20]].concat(),
+        }];
+        // 5 bp insertion before the amplicon shifts it on the haplotype
+        let variant = Variant {
+            label: "ins1".to_string(),
+            kind: VariantKind::Insertion,
+            sequence_index: 0,
+            start: 5,
+            end: 5,
+            replacement: b"TTTTT".to_vec(),
+        };
+        let (modified, layout, _) = apply_variants(&sequences, vec![variant]);
+        let primers =
+            [(20, 23, "scheme_1_LEFT"), (27, 30, "scheme_1_RIGHT")].map(|(start, end, name)| {
+                BedRecord {
+                    chrom: "seq1".to_string(),
+                    start,
+                    end,
+                    fields: vec![name.to_string()],
+                }
+            });
+        let scheme = PrimerScheme::from_bed(&primers, &Reference::from(sequences)).unwrap();
+
+        let generator =
+            create_haplotype_generator(vec![Haplotype::new(modified).with_variants(layout)])
+                .unwrap()
+                .with_primer_scheme(scheme);
+        let mut reverse = 0;
+        for index in 0..50 {
+            let read = generator.generate_read_at(index).unwrap().unwrap();
+            assert_eq!((read.start, read.end), (20, 30));
+            assert!(read.variants.is_empty());
+            if read.reverse {
+                reverse += 1;
+                assert_eq!(read.record.sequence(), b"GGTCAAGGCC");
+            } else {
+                assert_eq!(read.record.sequence(), b"GGCCTTGACC");
+            }
+        }
+        assert!(reverse > 0 && reverse < 50);
+    }
+
+    #[test]
+    fn test_reads_start_at_cut_sites() {
+        use crate::io::bed::BedRecord;
+
+        let sequences = vec![FastaRecord {
+            id: "seq1".to_string(),
+            sequence: [vec![b'A'; 50], vec![b'G'; 50]].concat(),
+        }];
+        let cut = |strand: &str| BedRecord {
+            chrom: "seq1".to_string(),
+            start: 50,
+            end: 51,
+            fields: vec!["cut".to_string(), "1".to_string(), strand.to_string()],
+        };
+        let reference = Reference::from(sequences);
+        let cut_sites = CutSites::new(&[cut("+"), cut("-")], &reference, 0.0, false).unwrap();
+        let error_model = ErrorModel::new(Some(0.0), Some(0.0), Some(0.0), None, None).unwrap();
+        let mut length_model = LengthModel::new();
+        let mut quality_model = QualityModel::new(None, None, None);
+        let mut rng = StdRng::seed_from_u64(42);
+        length_model.add_value(10);
+        quality_model.add_value(10, vec![b'?'; 10], &mut rng);
+        let generator = ReadGenerator::new(
+            reference,
+            length_model,
+            quality_model,
+            error_model,
+            Some(42),
+        )
+        .unwrap()
+        .with_cut_sites(cut_sites);
+
+        let mut reverse = 0;
+        for index in 0..50 {
+            let read = generator.generate_read_at(index).unwrap().unwrap();
+            if read.reverse {
+                reverse += 1;
+                assert_eq!((read.start, read.end), (40, 50));
+                assert_eq!(read.record.sequence(), b"TTTTTTTTTT");
+            } else {
+                assert_eq!((read.start, read.end), (50, 60));
+                assert_eq!(read.record.sequence(), b"GGGGGGGGGG");
+            }
+        }
+        assert!(reverse > 0 && reverse < 50);
+    }
+
+    #[test]
+    fn test_adaptive_sampling_rejects_off_target_reads() {
+        use crate::io::bed::BedRecord;
+        use crate::repeats::CopyNumber;
+
+        let reference = Reference::from(vec![FastaRecord {
+            id: "seq1".to_string(),
+            sequence: vec![b'A'; 1000],
+        }]);
+        let target = BedRecord {
+            chrom: "seq1".to_string(),
+            start: 0,
+            end: 300,
+            fields: Vec::new(),
+        };
+        let adaptive = AdaptiveSampling::new(&[target], &reference, CopyNumber::Fixed(20)).unwrap();
+        let error_model = ErrorModel::new(Some(0.0), Some(0.0), Some(0.0), None, None).unwrap();
+        let mut length_model = LengthModel::new();
+        let mut quality_model = QualityModel::new(None, None, None);
+        let mut rng = StdRng::seed_from_u64(42);
+        length_model.add_value(200);
+        quality_model.add_value(200, vec![b'?'; 200], &mut rng);
+        quality_model.add_value(20, vec![b'5'; 20], &mut rng);
+        let generator = ReadGenerator::new(
+            reference,
+            length_model,
+            quality_model,
+            error_model,
+            Some(42),
+        )
+        .unwrap()
+        .with_adaptive_sampling(adaptive);
+
+        let mut rejected = 0;
+        for index in 0..100 {
+            let read = generator.generate_read_at(index).unwrap().unwrap();
+            if read.start < 300 {
+                assert_eq!(read.end_reason, Some(EndReason::SignalPositive));
+                assert_eq!(read.end - read.start, 200);
+            } else {
+                rejected += 1;
+                assert_eq!(
+                    read.end_reason,
+                    Some(EndReason::DataServiceUnblockMuxChange)
+                );
+                assert_eq!(read.end - read.start, 20);
+                assert_eq!(read.record.quality_scores(), &[b'5'; 20]);
+            }
+        }
+        assert!(rejected > 0 && rejected < 100);
+    }
+
+    #[test]
+    fn test_duplex_pairs() {
+        let error_model = ErrorModel::new(Some(0.0), Some(0.0), Some(0.0), None, None).unwrap();
+        let mut length_model = LengthModel::new();
+        let mut quality_model = QualityModel::new(None, None, None);
+        let mut rng = StdRng::seed_from_u64(42);
+        length_model.add_value(10);
+        quality_model.add_value(10, vec![b'+'; 10], &mut rng);
+        let mut generator = ReadGenerator::new(
+            vec![FastaRecord {
+                id: "seq1".to_string(),
+                sequence: b"AAAAACCCCCGGGGGTTTTT".to_vec(),
+            }],
+            length_model,
+            quality_model,
+            error_model,
+            Some(42),
+        )
+        .unwrap()
+        .with_duplex(DuplexModel::new(0.5, 10).unwrap().with_consensus());
+
+        let reads = generator.generate_batch(40).unwrap();
+        let mut pairs = 0;
+        let mut i = 0;
+        while i < reads.len() {
+            let read = &reads[i];
+            if read.duplex == Some(DuplexRole::Simplex) {
+                assert_eq!(read.parent_id, None);
+                i += 1;
+                continue;
+            }
+            pairs += 1;
+            let (complement, consensus) = (&reads[i + 1], &reads[i + 2]);
+            let template_id = String::from_utf8_lossy(read.record.name()).into_owned();
+            assert_eq!(read.duplex, Some(DuplexRole::Parent));
+            assert_eq!(complement.duplex, Some(DuplexRole::Parent));
+            assert_eq!(complement.parent_id.as_ref(), Some(&template_id));
+            assert!(complement.reverse && !read.reverse);
+            assert_eq!(
+                complement.record.sequence(),
+                reverse_complement(read.record.sequence())
+            );
+            assert_eq!(consensus.duplex, Some(DuplexRole::Duplex));
+            assert_eq!(
+                String::from_utf8_lossy(consensus.record.name()),
+                format!(
+                    "{};{}",
+                    template_id,
+                    String::from_utf8_lossy(complement.record.name())
+                )
+            );
+            assert_eq!(consensus.record.sequence(), read.record.sequence());
+            assert_eq!(consensus.record.quality_scores(), &[b'5'; 10]);
+            assert_eq!((consensus.start, consensus.end), (read.start, read.end));
+            i += 3;
+        }
+        assert!(pairs > 0 && pairs < 40);
+        assert_eq!(reads.len(), 40 + 2 * pairs);
+    }
+
+    #[test]
+    fn test_haplotype_weights() {
+        let haplotype = |base: u8| {
+            Haplotype::new(vec![FastaRecord {
+                id: "seq1".to_string(),
+                sequence: vec![base; 100],
+            }])
+        };
+        let generator = create_haplotype_generator(vec![
+            haplotype(b'A').with_weight(3.0).unwrap().with_phase(1),
+            haplotype(b'C').with_weight(1.0).unwrap().with_phase(2),
+            haplotype(b'G').with_weight(0.0).unwrap().with_phase(3),
+        ])
+        .unwrap();
+
+        let mut counts = [0; 3];
+        for index in 0..400 {
+            let read = generator.generate_read_at(index).unwrap().unwrap();
+            let haplotype = read.haplotype.unwrap();
+            counts[haplotype - 1] += 1;
+            // Reads come entirely from the sequence of their haplotype
+            let base = [b'A', b'C', b'G'][haplotype - 1];
+            let matching = read
+                .record
+                .sequence()
+                .iter()
+                .filter(|&&b| b == base)
+                .count();
+            assert!(matching > read.record.sequence().len() / 2);
+        }
+        assert_eq!(counts[2], 0);
+        assert!(counts[0] > 2 * counts[1], "{:?}", counts);
+
+        assert!(haplotype(b'A').with_weight(-1.0).is_err());
+        assert!(
+            create_haplotype_generator(vec![haplotype(b'A').with_weight(0.0).unwrap()]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_reads_expand_repeats() {
+        use crate::repeats::{CopyNumber, RepeatAlleles, RepeatLocus};
+
+        let sequences = vec![FastaRecord {
+            id: "seq1".to_string(),
+            sequence: [vec![b'A'; 40], b"CAG".repeat(3), vec![b'T'; 40]].concat(),
+        }];
+        // The haplotype has lost the repeat tract entirely
+        let locus = RepeatLocus {
+            id: "rep1".to_string(),
+            sequence_index: 0,
+            start: 40,
+            end: 49,
+            motif: b"CAG".to_vec(),
+            alleles: vec![CopyNumber::Fixed(0)],
+        };
+        let error_model = ErrorModel::new(Some(0.0), Some(0.0), Some(0.0), None, None).unwrap();
+        let mut length_model = LengthModel::new();
+        let mut quality_model = QualityModel::new(None, None, None);
+        let mut rng = StdRng::seed_from_u64(42);
+        length_model.add_value(10);
+        quality_model.add_value(10, vec![b'?'; 10], &mut rng);
+        let generator = ReadGenerator::from_haplotypes(
+            vec![Haplotype::new(sequences).with_repeats(RepeatAlleles::new(&[locus], 0, None))],
+            length_model,
+            quality_model,
+            error_model,
+            Some(42),
+        )
+        .unwrap();
+
+        let mut spanning = 0;
+        for index in 0..100 {
+            let read = generator.generate_read_at(index).unwrap().unwrap();
+            let sequence = read.record.sequence();
+            assert_eq!(sequence.len(), 10);
+            assert!(sequence.iter().all(|&b| b == b'A' || b == b'T'));
+            if sequence.contains(&b'A') && sequence.contains(&b'T') {
+                spanning += 1;
+                assert_eq!(read.repeats, vec![("rep1".to_string(), 0)]);
+            }
+        }
+        assert!(spanning > 0);
+    }
+
+    #[test]
+    fn test_site_rates_past_expanded_repeats() {
+        use crate::io::bed::BedRecord;
+        use crate::repeats::{CopyNumber, RepeatAlleles, RepeatLocus};
+
+        let sequences = vec![FastaRecord {
+            id: "seq1".to_string(),
+            sequence: [
+                vec![b'A'; 20],
+                b"CAG".repeat(3),
+                b"TTTTCT".to_vec(),
+                vec![b'T'; 20],
+            ]
+            .concat(),
+        }];
+        // The C past the repeat tract is always methylated
+        let site = BedRecord {
+            chrom: "seq1".to_string(),
+            start: 33,
+            end: 34,
+            fields: ["m", "0", "+", "0", "0", "0", "10", "100"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        };
+        let methylation = MethylationModel::new(Vec::new())
+            .with_sites(&[site], &Reference::from(sequences.clone()))
+            .unwrap();
+        let locus = RepeatLocus {
+            id: "rep1".to_string(),
+            sequence_index: 0,
+            start: 20,
+            end: 29,
+            motif: b"CAG".to_vec(),
+            alleles: vec![CopyNumber::Fixed(5)],
+        };
+        let error_model = ErrorModel::new(Some(0.0), Some(0.0), Some(0.0), None, None).unwrap();
+        let mut length_model = LengthModel::new();
+        let mut quality_model = QualityModel::new(None, None, None);
+        let mut rng = StdRng::seed_from_u64(42);
+        length_model.add_value(30);
+        quality_model.add_value(30, vec![b'?'; 30], &mut rng);
+        let generator = ReadGenerator::from_haplotypes(
+            vec![Haplotype::new(sequences).with_repeats(RepeatAlleles::new(&[locus], 0, None))],
+            length_model,
+            quality_model,
+            error_model,
+            Some(42),
+        )
+        .unwrap()
+        .with_methylation(methylation);
+
+        let mut past_repeat = 0;
+        for index in 0..100 {
+            let read = generator.generate_read_at(index).unwrap().unwrap();
+            if read.repeats.is_empty() {
+                continue;
+            }
+            // Only the site is called, at its base after the expanded tract
+            for call in &read.modifications {
+                assert_eq!(call.reference_position, Some(33));
+                assert_eq!(read.record.sequence()[call.position], b'C');
+                assert!(call.modified);
+                past_repeat += 1;
+            }
+        }
+        assert!(past_repeat > 0);
+    }
+
+    fn create_transcript_generator(
+        transcriptome: impl Fn(&Reference) -> Transcriptome,
+    ) -> ReadGenerator {
+        let transcripts = Reference::from(vec![FastaRecord {
+            id: "tx1".to_string(),
+            sequence: b"GATTACAGGC".to_vec(),
+        }]);
+        let transcriptome = transcriptome(&transcripts);
+        let error_model = ErrorModel::new(Some(0.0), Some(0.0), Some(0.0), None, None).unwrap();
+        let mut length_model = LengthModel::new();
+        let mut quality_model = QualityModel::new(None, None, None);
+        let mut rng = StdRng::seed_from_u64(42);
+        length_model.add_value(4);
+        quality_model.add_value(20, vec![b'?'; 20], &mut rng);
+        ReadGenerator::new(
+            transcripts,
+            length_model,
+            quality_model,
+            error_model,
+            Some(42),
+        )
+        .unwrap()
+        .with_transcriptome(transcriptome)
+    }
+
+    #[test]
+    fn test_reads_from_transcripts() {
+        let generator = create_transcript_generator(|transcripts| {
+            Transcriptome::new(transcripts, None, 0.0, CopyNumber::Fixed(5)).unwrap()
+        });
+        let molecule = b"GATTACAGGCAAAAA";
+        let mut reverse = 0;
+        for index in 0..50 {
+            let read = generator.generate_read_at(index).unwrap().unwrap();
+            assert_eq!(read.sequence_id, "tx1");
+            assert_eq!((read.start, read.end), (0, 10));
+            assert_eq!(read.polya_length, Some(5));
+            if read.reverse {
+                reverse += 1;
+                assert_eq!(read.record.sequence(), reverse_complement(molecule));
+            } else {
+                assert_eq!(read.record.sequence(), molecule);
+            }
+        }
+        assert!(reverse > 0 && reverse < 50);
+
+        // Direct RNA reads are sense-strand, reported 5'→3' with the poly-A tail last
+        let generator = create_transcript_generator(|transcripts| {
+            Transcriptome::new(transcripts, None, 0.0, CopyNumber::Fixed(5))
+                .unwrap()
+                .with_protocol(RnaProtocol::DirectRna)
+                .with_uracil()
+        });
+        for index in 0..10 {
+            let read = generator.generate_read_at(index).unwrap().unwrap();
+            assert!(!read.reverse);
+            assert_eq!(read.record.sequence(), b"GAUUACAGGCAAAAA");
+        }
+    }
+
+    #[test]
+    fn test_seek_reproduces_reads() {
+        let mut generator = create_test_generator(None).unwrap();
+        let reads = generator.generate_batch(10).unwrap();
+
+        let mut shard = create_test_generator(None).unwrap();
+        shard.seek(6).unwrap();
+        assert_eq!(shard.generate_batch(4).unwrap(), reads[6..]);
+    }
+
+    #[test]
+    fn test_reads_on_a_channel_do_not_overlap() {
+        let create_generator = || {
+            create_test_generator(None)
+                .unwrap()
+                .with_duplex(DuplexModel::new(0.3, 10).unwrap())
+                .with_run_timeline(RunTimeline::new(3, 4.0, 10.0).unwrap())
+        };
+        let reads = create_generator().generate_batch(60).unwrap();
+        assert!(reads.len() > 60);
+
+        // Complement reads keep their channel busy after the template read
+        let mut free_at = [0.0; 3];
+        for read in reads
+            .iter()
+            .filter(|read| read.duplex != Some(DuplexRole::Duplex))
+        {
+            let timing = read.timing.unwrap();
+            let channel = timing.channel as usize - 1;
+            assert!(timing.start_time > free_at[channel]);
+            free_at[channel] = timing.start_time + timing.duration;
+        }
+
+        // Seeking finds when the channels are free after the preceding reads
+        let mut shard = create_generator();
+        shard.seek(45).unwrap();
+        let rest = shard.generate_batch(15).unwrap();
+        assert_eq!(rest, reads[reads.len() - rest.len()..]);
+    }
 }
@@ -0,0 +1,749 @@
+//! Genomes to simulate, derived from the reference by applying germline, structural and
+//! somatic variants.
+
+use crate::haplotype::Haplotype;
+use crate::io::fasta::{FastaRecord, Reference};
+use crate::io::{VcfRecord, VcfWriter};
+use crate::models::clones::{self, CloneSpec, CloneTree, vaf_clones};
+use crate::sv::{self, StructuralVariant, truth_records};
+use crate::variants::{Variant, place_variants, variants_from_vcf};
+use anyhow::{Result, anyhow, bail};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Builder of the haplotypes carrying germline variants, structural variants and (for tumour
+/// samples) the somatic variants of each clone.
+///
+/// Germline variants go on the haplotypes given by the genotype of the chosen sample; with
+/// a single haplotype, the first ALT allele of every record is applied (or, if a sample was
+/// chosen, the first one it carries). Structural variants
+/// are shared by all haplotypes. A tumour sample adds, for every clone, the haplotypes
+/// carrying the somatic variants of the clone and its ancestors, weighted by their share of
+/// the sample cells.
+///
+/// All haplotypes read their sequences through their variants from one shared copy of the
+/// reference, so memory grows with the number of variants rather than of genomes.
+pub struct GenomeBuilder {
+    sequences: Vec<FastaRecord>,
+    ploidy: usize,
+    sample: Option<String>,
+    somatic_sample: Option<String>,
+    germline: Option<(Vec<String>, Vec<VcfRecord>)>,
+    svs: Vec<StructuralVariant>,
+    tumour: Option<Tumour>,
+    phase_weights: Option<Vec<f64>>,
+}
+
+/// Somatic variants of a tumour sample and its clonal structure.
+struct Tumour {
+    samples: Vec<String>,
+    /// Somatic records with the name of the clone each arose in
+    records: Vec<(VcfRecord, String)>,
+    tree: CloneTree,
+}
+
+/// A genome to simulate: the variants applied to the reference and how reads are drawn from it.
+struct GenomePlan {
+    variants: Vec<Variant>,
+    phase: Option<usize>,
+    clone: Option<String>,
+    weight: f64,
+}
+
+/// Haplotypes built by a [`GenomeBuilder`], with what it takes to report their variants.
+pub struct Genomes {
+    pub haplotypes: Vec<Haplotype>,
+    /// Number of VCF records skipped as unsupported
+    pub unsupported: usize,
+    /// Number of variants skipped as overlapping a previous variant
+    pub overlapping: usize,
+    /// Number of unphased heterozygous genotypes, whose alleles were assigned to haplotypes
+    /// in the order listed
+    pub unphased: usize,
+    /// Reference sequences shared by the haplotypes
+    reference: Arc<Reference>,
+    svs: Vec<StructuralVariant>,
+    somatic: Vec<(VcfRecord, String)>,
+}
+
+impl GenomeBuilder {
+    /// Creates a builder of genomes with the given number of haplotypes.
+    ///
+    /// # Arguments
+    /// * `sequences` - Reference sequences the variants are applied to
+    /// * `ploidy` - Number of haplotypes of every genome
+    pub fn new(sequences: Vec<FastaRecord>, ploidy: usize) -> Self {
+        Self {
+            sequences,
+            ploidy,
+            sample: None,
+            somatic_sample: None,
+            germline: None,
+            svs: Vec::new(),
+            tumour: None,
+            phase_weights: None,
+        }
+    }
+
+    /// Sets the sample whose genotypes place the germline variants (the first of the VCF by
+    /// default).
+    pub fn with_sample(mut self, sample: impl Into<String>) -> Self {
+        self.sample = Some(sample.into());
+        self
+    }
+
+    /// Sets the tumour sample whose genotypes place the somatic variants (the only sample of
+    /// the somatic VCF by default).
+    pub fn with_somatic_sample(mut self, sample: impl Into<String>) -> Self {
+        self.somatic_sample = Some(sample.into());
+        self
+    }
+
+    /// Sets the germline variants, as read from a VCF.
+    ///
+    /// # Arguments
+    /// * `vcf` - Tuple of (sample names, records)
+    pub fn with_germline(mut self, vcf: (Vec<String>, Vec<VcfRecord>)) -> Self {
+        self.germline = Some(vcf);
+        self
+    }
+
+    /// Sets the structural variants, shared by all haplotypes.
+    pub fn with_svs(mut self, svs: Vec<StructuralVariant>) -> Self {
+        self.svs = svs;
+        self
+    }
+
+    /// Makes the genomes those of a tumour sample with the given somatic variants.
+    ///
+    /// Somatic records are assigned to the clone of their `CLONE` INFO field, to clones
+    /// reproducing their `VAF` INFO field, or to the founding clone.
+    ///
+    /// # Arguments
+    /// * `vcf` - Tuple of (sample names, records) of the somatic VCF
+    /// * `subclones` - Subclone definitions
+    /// * `purity` - Fraction of tumour cells in the sample
+    ///
+    /// # Errors
+    /// Returns an error if the clonal structure is invalid, an allele fraction cannot be
+    /// parsed or a record refers to an unknown clone
+    pub fn with_somatic(
+        mut self,
+        vcf: (Vec<String>, Vec<VcfRecord>),
+        subclones: &[CloneSpec],
+        purity: f64,
+    ) -> Result<Self> {
+        let (samples, records) = vcf;
+
+        // Variants given by allele fraction are assigned to clones reproducing it
+        let vafs = records
+            .iter()
+            .filter(|record| record.info_value("CLONE").is_none())
+            .filter_map(|record| record.info_value("VAF"))
+            .map(|vaf| {
+                vaf.parse::<f64>()
+                    .map_err(|_| anyhow!("Invalid VAF '{}' in the somatic VCF", vaf))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let (vaf_clone_names, vaf_specs) = vaf_clones(&vafs, self.ploidy, purity)?;
+        let specs: Vec<CloneSpec> = subclones.iter().cloned().chain(vaf_specs).collect();
+        let tree = CloneTree::new(&specs, purity)?;
+
+        let mut vaf_clone_names = vaf_clone_names.into_iter();
+        let records = records
+            .into_iter()
+            .map(|record| {
+                let name = match record.info_value("CLONE") {
+                    Some(name) => name.to_string(),
+                    None if record.info_value("VAF").is_some() => vaf_clone_names
+                        .next()
+                        .expect("one clone per allele fraction"),
+                    None => clones::FOUNDING_CLONE.to_string(),
+                };
+                if tree.index(&name).is_none() {
+                    bail!(
+                        "Somatic variant at {}:{} refers to unknown clone '{}'",
+                        record.chrom,
+                        record.position,
+                        name
+                    );
+                }
+                Ok((record, name))
+            })
+            .collect::<Result<_>>()?;
+
+        self.tumour = Some(Tumour {
+            samples,
+            records,
+            tree,
+        });
+        Ok(self)
+    }
+
+    /// Scales the weight of the haplotypes of every phase.
+    pub fn with_phase_weights(mut self, weights: Vec<f64>) -> Self {
+        self.phase_weights = Some(weights);
+        self
+    }
+
+    /// Applies the variants to the reference and builds the haplotypes.
+    ///
+    /// # Errors
+    /// Returns an error if a chosen sample is not in its VCF, genotypes are needed but
+    /// missing, the somatic VCF has several samples and none was chosen, or a variant does
+    /// not match the reference
+    pub fn build(self) -> Result<Genomes> {
+        let has_germline = self.germline.is_some();
+        let (samples, records) = self.germline.unwrap_or_default();
+
+        let mut unphased = 0;
+        let sample_index = if has_germline && (self.ploidy > 1 || self.sample.is_some()) {
+            let sample_index = find_sample(&samples, self.sample.as_deref(), "germline")?
+                .ok_or_else(|| {
+                    anyhow!("A ploidy above 1 requires a germline VCF with sample genotypes")
+                })?;
+            if self.ploidy > 1 {
+                unphased = records
+                    .iter()
+                    .filter_map(|record| record.genotype(sample_index))
+                    .filter(|(alleles, phased)| !phased && alleles.windows(2).any(|w| w[0] != w[1]))
+                    .count();
+            }
+            Some(sample_index)
+        } else {
+            None
+        };
+        let somatic_sample_index = match &self.tumour {
+            Some(tumour)
+                if (self.ploidy > 1 || self.somatic_sample.is_some())
+                    && !tumour.samples.is_empty() =>
+            {
+                // The first of several samples is usually the matched normal
+                if self.somatic_sample.is_none() && tumour.samples.len() > 1 {
+                    bail!(
+                        "The somatic VCF has several samples ({}); the tumour sample must be chosen",
+                        tumour.samples.join(", ")
+                    );
+                }
+                find_sample(&tumour.samples, self.somatic_sample.as_deref(), "somatic")?
+            }
+            _ => None,
+        };
+
+        let sequences = self.sequences;
+        let sv_variants: Vec<Variant> = self
+            .svs
+            .iter()
+            .flat_map(|sv| sv.variants(&sequences))
+            .collect();
+
+        let mut unsupported = 0;
+        let mut germline = Vec::with_capacity(self.ploidy);
+        for haplotype in 0..self.ploidy {
+            let (mut variants, skipped) = variants_from_vcf(&records, &sequences, |record| {
+                carried_allele(record, sample_index, haplotype, self.ploidy)
+            })?;
+            variants.extend(sv_variants.iter().cloned());
+            unsupported += skipped;
+            germline.push(variants);
+        }
+
+        let ploidy = self.ploidy;
+        let phase = |haplotype: usize| (ploidy > 1).then_some(haplotype + 1);
+        let (plans, somatic) = match self.tumour {
+            Some(tumour) => {
+                let (plans, skipped) =
+                    tumour.plans(&sequences, &germline, ploidy, somatic_sample_index)?;
+                unsupported += skipped;
+                (plans, tumour.records)
+            }
+            None => {
+                let plans = germline
+                    .into_iter()
+                    .enumerate()
+                    .map(|(haplotype, variants)| GenomePlan {
+                        variants,
+                        phase: phase(haplotype),
+                        clone: None,
+                        weight: 1.0,
+                    })
+                    .collect();
+                (plans, Vec::new())
+            }
+        };
+
+        let lengths: Vec<usize> = sequences
+            .iter()
+            .map(|record| record.sequence.len())
+            .collect();
+        let reference = Arc::new(Reference::from(sequences));
+        let mut haplotypes = Vec::with_capacity(plans.len());
+        let mut overlapping = 0;
+        for plan in plans {
+            let (layout, skipped_overlapping) = place_variants(&lengths, plan.variants);
+            overlapping += skipped_overlapping;
+            let mut haplotype =
+                Haplotype::edited(reference.clone(), layout).with_weight(plan.weight)?;
+            if let Some(phase) = plan.phase {
+                haplotype = haplotype.with_phase(phase);
+            }
+            if let Some(clone) = plan.clone {
+                haplotype = haplotype.with_clone(clone);
+            }
+            haplotypes.push(haplotype);
+        }
+        let haplotypes = apply_phase_weights(haplotypes, self.phase_weights.as_deref())?;
+
+        Ok(Genomes {
+            haplotypes,
+            unsupported,
+            overlapping,
+            unphased,
+            reference,
+            svs: self.svs,
+            somatic,
+        })
+    }
+}
+
+impl Tumour {
+    /// Plans the genomes of the sample: the normal haplotypes plus the haplotypes of every
+    /// clone.
+    ///
+    /// Somatic variants go on the haplotypes given by the genotype of the sample, or on the
+    /// first haplotype if the VCF has no genotypes. A single haplotype gets the first ALT
+    /// allele, as for germline variants.
+    ///
+    /// # Arguments
+    /// * `sequences` - Reference sequences
+    /// * `germline` - Germline variants of each haplotype
+    /// * `ploidy` - Number of haplotypes
+    /// * `sample_index` - Sample whose genotypes place the variants, if any
+    ///
+    /// # Returns
+    /// Tuple of (genome plans, number of records skipped as unsupported)
+    fn plans(
+        &self,
+        sequences: &[FastaRecord],
+        germline: &[Vec<Variant>],
+        ploidy: usize,
+        sample_index: Option<usize>,
+    ) -> Result<(Vec<GenomePlan>, usize)> {
+        let tree = &self.tree;
+        let mut clone_records: Vec<Vec<VcfRecord>> = vec![Vec::new(); tree.len()];
+        for (record, name) in &self.records {
+            let clone = tree.index(name).expect("clones were checked");
+            clone_records[clone].push(record.clone());
+        }
+
+        let mut unsupported = 0;
+        let mut clone_variants = Vec::with_capacity(tree.len());
+        for records in &clone_records {
+            let mut variants = Vec::with_capacity(ploidy);
+            for haplotype in 0..ploidy {
+                let (haplotype_variants, skipped) =
+                    variants_from_vcf(records, sequences, |record| {
+                        carried_allele(record, sample_index, haplotype, ploidy)
+                    })?;
+                unsupported += skipped;
+                variants.push(haplotype_variants);
+            }
+            clone_variants.push(variants);
+        }
+
+        let phase = |haplotype: usize| (ploidy > 1).then_some(haplotype + 1);
+        let mut plans = Vec::new();
+        if tree.purity() < 1.0 {
+            for (haplotype, variants) in germline.iter().enumerate() {
+                plans.push(GenomePlan {
+                    variants: variants.clone(),
+                    phase: phase(haplotype),
+                    clone: Some(clones::NORMAL.to_string()),
+                    weight: 1.0 - tree.purity(),
+                });
+            }
+        }
+        for clone in 0..tree.len() {
+            let weight = tree.sample_fraction(clone);
+            if weight <= 0.0 {
+                continue;
+            }
+            for (haplotype, germline) in germline.iter().enumerate() {
+                let mut variants = germline.clone();
+                for ancestor in (0..tree.len()).filter(|&a| tree.descends_from(clone, a)) {
+                    variants.extend(clone_variants[ancestor][haplotype].iter().cloned());
+                }
+                plans.push(GenomePlan {
+                    variants,
+                    phase: phase(haplotype),
+                    clone: Some(tree.name(clone).to_string()),
+                    weight,
+                });
+            }
+        }
+
+        Ok((plans, unsupported))
+    }
+}
+
+impl Genomes {
+    /// Returns the reference sequences the variants were applied to.
+    fn sequences(&self) -> &[FastaRecord] {
+        self.reference
+            .records()
+            .expect("genome sequences are in memory")
+    }
+
+    /// Returns the truth records of the structural and somatic variants, in reference order.
+    ///
+    /// Only structural variants applied in full on every haplotype are reported; somatic
+    /// records get their clone and the expected fraction of reads carrying them.
+    pub fn truth_records(&self) -> Result<Vec<VcfRecord>> {
+        // Number of edits applied for every variant label, per haplotype
+        let edits: Vec<HashMap<&str, usize>> = self
+            .haplotypes
+            .iter()
+            .map(|haplotype| {
+                let layout = haplotype.variants().expect("variants were applied");
+                let mut edits = HashMap::new();
+                for label in layout.labels() {
+                    *edits.entry(label).or_default() += 1;
+                }
+                edits
+            })
+            .collect();
+        let applied: Vec<StructuralVariant> = self
+            .svs
+            .iter()
+            .filter(|sv| {
+                let count = sv.variants(self.sequences()).len();
+                edits
+                    .iter()
+                    .all(|edits| edits.get(sv.id.as_str()) == Some(&count))
+            })
+            .cloned()
+            .collect();
+        let mut truth = truth_records(&applied, self.sequences());
+        let carried: Vec<HashSet<&str>> = edits
+            .iter()
+            .map(|edits| edits.keys().copied().collect())
+            .collect();
+        for (record, clone) in &self.somatic {
+            let vaf = expected_vaf(record, self.sequences(), &self.haplotypes, &carried)?;
+            let mut record = record.clone();
+            record.info = format!("SOMATIC;CLONE={};VAF={:.4}", clone, vaf);
+            truth.push(record);
+        }
+
+        let sequence_indices: HashMap<&str, usize> = self
+            .sequences()
+            .iter()
+            .enumerate()
+            .map(|(i, record)| (record.id.as_str(), i))
+            .collect();
+        truth.sort_by_key(|record| (sequence_indices[record.chrom.as_str()], record.position));
+        Ok(truth)
+    }
+
+    /// Writes the truth VCF of the structural and somatic variants.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the VCF file
+    pub fn write_truth_vcf(&self, path: &Path) -> Result<()> {
+        let contigs: Vec<(&str, usize)> = self
+            .sequences()
+            .iter()
+            .map(|record| (record.id.as_str(), record.sequence.len()))
+            .collect();
+        let meta_lines: Vec<&str> = sv::VCF_META_LINES
+            .iter()
+            .chain(clones::VCF_META_LINES)
+            .copied()
+            .collect();
+        let mut writer = VcfWriter::new(path, &contigs, &meta_lines)?;
+        for record in &self.truth_records()? {
+            writer.write_record(record)?;
+        }
+        writer.finish()
+    }
+}
+
+/// Returns the index of the chosen sample in a VCF, or of its first sample if none was
+/// chosen (None if the VCF has no samples).
+///
+/// # Arguments
+/// * `samples` - Sample names of the VCF
+/// * `sample` - Name of the chosen sample, if any
+/// * `vcf` - Which VCF the samples are from, for error messages
+fn find_sample(samples: &[String], sample: Option<&str>, vcf: &str) -> Result<Option<usize>> {
+    match sample {
+        Some(name) => samples
+            .iter()
+            .position(|sample| sample == name)
+            .map(Some)
+            .ok_or_else(|| anyhow!("Sample '{}' not found in the {} VCF", name, vcf)),
+        None => Ok((!samples.is_empty()).then_some(0)),
+    }
+}
+
+/// Returns the allele of a record carried by a haplotype.
+///
+/// Haplotype `k` carries the `k`-th allele of the genotype of the sample. A single haplotype
+/// carries the first ALT allele the sample has, or the first ALT allele of the record if no
+/// sample was chosen; without a sample, only the first of several haplotypes carries it.
+///
+/// # Arguments
+/// * `record` - VCF record
+/// * `sample_index` - Sample whose genotype places the alleles, if any
+/// * `haplotype` - Index of the haplotype
+/// * `ploidy` - Number of haplotypes
+fn carried_allele(
+    record: &VcfRecord,
+    sample_index: Option<usize>,
+    haplotype: usize,
+    ploidy: usize,
+) -> Option<usize> {
+    match sample_index {
+        Some(sample_index) => {
+            let (alleles, _) = record.genotype(sample_index)?;
+            if ploidy == 1 {
+                alleles.into_iter().flatten().find(|&allele| allele > 0)
+            } else {
+                alleles.get(haplotype).copied().flatten()
+            }
+        }
+        None => (haplotype == 0).then_some(1),
+    }
+}
+
+/// Scales the weight of every haplotype by the weight of its phase.
+///
+/// # Arguments
+/// * `haplotypes` - Haplotypes, with their phase if phased
+/// * `weights` - Weight of every phase, if any
+pub fn apply_phase_weights(
+    haplotypes: Vec<Haplotype>,
+    weights: Option<&[f64]>,
+) -> Result<Vec<Haplotype>> {
+    let Some(weights) = weights else {
+        return Ok(haplotypes);
+    };
+    haplotypes
+        .into_iter()
+        .map(|haplotype| {
+            let weight = weights[haplotype.phase().map_or(0, |phase| phase - 1)];
+            let weight = haplotype.weight() * weight;
+            haplotype.with_weight(weight)
+        })
+        .collect()
+}
+
+/// Returns the expected fraction of reads carrying an ALT allele of a somatic record, from
+/// the weights of the haplotypes it was applied to.
+///
+/// # Arguments
+/// * `record` - Somatic VCF record
+/// * `sequences` - Reference sequences
+/// * `haplotypes` - Haplotypes reads are drawn from
+/// * `carried` - Labels of the variants applied to each haplotype
+fn expected_vaf(
+    record: &VcfRecord,
+    sequences: &[FastaRecord],
+    haplotypes: &[Haplotype],
+    carried: &[HashSet<&str>],
+) -> Result<f64> {
+    let mut labels = Vec::new();
+    for allele in 1..=record.alternates.len() {
+        let (variants, _) =
+            variants_from_vcf(std::slice::from_ref(record), sequences, |_| Some(allele))?;
+        labels.extend(variants.into_iter().map(|variant| variant.label));
+    }
+
+    let total: f64 = haplotypes.iter().map(Haplotype::weight).sum();
+    // Folded from +0, as a float sum of no weights is -0
+    let carrying = haplotypes
+        .iter()
+        .zip(carried)
+        .filter(|(_, carried)| labels.iter().any(|label| carried.contains(label.as_str())))
+        .fold(0.0, |carrying, (haplotype, _)| {
+            carrying + haplotype.weight()
+        });
+
+    Ok(carrying / total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference() -> Vec<FastaRecord> {
+        vec![FastaRecord {
+            id: "chr1".to_string(),
+            sequence: b"AAAACCCCGGGGTTTT".to_vec(),
+        }]
+    }
+
+    fn vcf(position: usize, reference: &str, info: &str, genotypes: &[&str]) -> VcfRecord {
+        VcfRecord {
+            chrom: "chr1".to_string(),
+            position,
+            id: Some(format!("v{}", position)),
+            reference: reference.to_string(),
+            alternates: vec!["T".to_string()],
+            info: info.to_string(),
+            format: (!genotypes.is_empty()).then(|| "GT".to_string()),
+            samples: genotypes.iter().map(|gt| gt.to_string()).collect(),
+        }
+    }
+
+    fn labels(haplotype: &Haplotype) -> Vec<&str> {
+        haplotype.variants().unwrap().labels().collect()
+    }
+
+    #[test]
+    fn test_germline_genotypes() {
+        let samples = vec!["S1".to_string(), "S2".to_string()];
+        let records = vec![
+            vcf(2, "A", ".", &["0|1", "1|1"]),
+            vcf(6, "C", ".", &["1|1", "0/0"]),
+        ];
+        let genomes = GenomeBuilder::new(reference(), 2)
+            .with_sample("S1")
+            .with_germline((samples.clone(), records.clone()))
+            .with_phase_weights(vec![1.0, 3.0])
+            .build()
+            .unwrap();
+        assert_eq!(genomes.haplotypes.len(), 2);
+        assert_eq!(labels(&genomes.haplotypes[0]), vec!["v6"]);
+        assert_eq!(labels(&genomes.haplotypes[1]), vec!["v2", "v6"]);
+        assert_eq!(genomes.haplotypes[1].phase(), Some(2));
+        assert_eq!(genomes.haplotypes[1].weight(), 3.0);
+
+        // Haploid genomes carry the first ALT allele of every record
+        let genomes = GenomeBuilder::new(reference(), 1)
+            .with_germline((samples.clone(), records.clone()))
+            .build()
+            .unwrap();
+        assert_eq!(labels(&genomes.haplotypes[0]), vec!["v2", "v6"]);
+
+        // Or, for a chosen sample, the ALT alleles it carries
+        let genomes = GenomeBuilder::new(reference(), 1)
+            .with_sample("S2")
+            .with_germline((samples.clone(), records.clone()))
+            .build()
+            .unwrap();
+        assert_eq!(labels(&genomes.haplotypes[0]), vec!["v2"]);
+        assert!(
+            GenomeBuilder::new(reference(), 1)
+                .with_sample("S3")
+                .with_germline((samples, records))
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_truth_svs() {
+        let mut sequences = reference();
+        sequences.push(FastaRecord {
+            id: "chr2".to_string(),
+            sequence: b"ACGTACGTACGT".to_vec(),
+        });
+        let sv = |id: &str, sv_type, sequence_index, start, end, destination| StructuralVariant {
+            id: id.to_string(),
+            sv_type,
+            sequence_index,
+            start,
+            end,
+            inserted: Vec::new(),
+            destination,
+        };
+        let svs = vec![
+            sv("sv1", sv::SvType::Translocation, 0, 4, 8, Some((1, 2))),
+            sv("sv2", sv::SvType::Deletion, 1, 8, 10, None),
+        ];
+        // The second haplotype has a SNV where the translocated segment starts, so only the
+        // destination of the translocation is applied on it
+        let genomes = GenomeBuilder::new(sequences, 2)
+            .with_germline((vec!["S1".to_string()], vec![vcf(5, "C", ".", &["0|1"])]))
+            .with_svs(svs)
+            .build()
+            .unwrap();
+        assert_eq!(labels(&genomes.haplotypes[1]), vec!["v5", "sv1", "sv2"]);
+
+        let truth = genomes.truth_records().unwrap();
+        assert_eq!(truth.len(), 1);
+        assert_eq!(truth[0].info, "SVTYPE=DEL;SVLEN=-2;END=10");
+    }
+
+    #[test]
+    fn test_tumour_genomes() {
+        let records = vec![vcf(2, "A", "CLONE=A", &[]), vcf(10, "G", ".", &[])];
+        let subclones = vec!["A:0.5".parse::<CloneSpec>().unwrap()];
+        let genomes = GenomeBuilder::new(reference(), 1)
+            .with_somatic((Vec::new(), records), &subclones, 0.8)
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let clones: Vec<(Option<&str>, Vec<&str>)> = genomes
+            .haplotypes
+            .iter()
+            .map(|haplotype| (haplotype.clone_name(), labels(haplotype)))
+            .collect();
+        assert_eq!(
+            clones,
+            vec![
+                (Some(clones::NORMAL), vec![]),
+                (Some(clones::FOUNDING_CLONE), vec!["v10"]),
+                (Some("A"), vec!["v2", "v10"]),
+            ]
+        );
+
+        let truth = genomes.truth_records().unwrap();
+        assert_eq!(truth[0].info, "SOMATIC;CLONE=A;VAF=0.4000");
+        assert_eq!(truth[1].info, "SOMATIC;CLONE=founder;VAF=0.8000");
+    }
+
+    #[test]
+    fn test_somatic_genotypes() {
+        let samples = vec!["NORMAL".to_string(), "TUMOR".to_string()];
+        let records = vec![vcf(2, "A", ".", &["0/0", "0/1"])];
+
+        // A single haplotype gets the first ALT allele, whatever the genotype
+        let genomes = GenomeBuilder::new(reference(), 1)
+            .with_somatic((samples.clone(), records.clone()), &[], 1.0)
+            .unwrap()
+            .build()
+            .unwrap();
+        let haplotype = &genomes.haplotypes[0];
+        assert_eq!(labels(haplotype), vec!["v2"]);
+        assert_eq!(haplotype.reference().fetch(0, 0, 4).unwrap(), b"ATAA");
+        assert_eq!(
+            genomes.truth_records().unwrap()[0].info,
+            "SOMATIC;CLONE=founder;VAF=1.0000"
+        );
+
+        // Genotypes are those of the chosen sample
+        let genomes = GenomeBuilder::new(reference(), 2)
+            .with_somatic_sample("TUMOR")
+            .with_somatic((samples.clone(), records.clone()), &[], 1.0)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert!(labels(&genomes.haplotypes[0]).is_empty());
+        assert_eq!(labels(&genomes.haplotypes[1]), vec!["v2"]);
+        assert_eq!(
+            genomes.truth_records().unwrap()[0].info,
+            "SOMATIC;CLONE=founder;VAF=0.5000"
+        );
+
+        // The tumour sample must be chosen among several
+        let result = GenomeBuilder::new(reference(), 2)
+            .with_somatic((samples, records), &[], 1.0)
+            .unwrap()
+            .build();
+        assert!(result.is_err());
+    }
+}
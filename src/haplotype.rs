@@ -0,0 +1,176 @@
+//! Haplotypes, the genome copies that reads are drawn from.
+
+use crate::io::fasta::Reference;
+use crate::models::TargetRegions;
+use crate::repeats::RepeatAlleles;
+use crate::variants::{EditedSequences, VariantLayout};
+use anyhow::{Result, bail};
+use std::sync::Arc;
+
+/// One copy of the simulated genome.
+///
+/// A haplotype is a set of sequences to sample reads from, either read directly from a FASTA
+/// file or derived from the reference by applying variants. Haploid simulations use a
+/// single haplotype; diploid and polyploid ones draw each read from one of several
+/// haplotypes according to their weights. Tumour samples add one haplotype per clone and
+/// haplotype number, each carrying the somatic variants of its clone.
+pub struct Haplotype {
+    reference: Reference,
+    variants: Option<Arc<VariantLayout>>,
+    targets: Option<TargetRegions>,
+    repeats: Option<RepeatAlleles>,
+    weight: f64,
+    phase: Option<usize>,
+    clone: Option<String>,
+}
+
+impl Haplotype {
+    /// Creates a haplotype with weight 1 from its sequences.
+    ///
+    /// # Arguments
+    /// * `reference` - Sequences of the haplotype, either loaded records or an indexed FASTA
+    pub fn new(reference: impl Into<Reference>) -> Self {
+        Self {
+            reference: reference.into(),
+            variants: None,
+            targets: None,
+            repeats: None,
+            weight: 1.0,
+            phase: None,
+            clone: None,
+        }
+    }
+
+    /// Creates a haplotype with weight 1 carrying variants on shared reference sequences.
+    ///
+    /// The sequences of the haplotype are read through the variants from the reference, so
+    /// that haplotypes do not each hold a copy of the genome.
+    ///
+    /// # Arguments
+    /// * `reference` - Reference sequences the variants were placed on
+    /// * `variants` - Layout of the variants placed on the reference
+    pub fn edited(reference: Arc<Reference>, variants: VariantLayout) -> Self {
+        let variants = Arc::new(variants);
+        let mut haplotype = Self::new(Reference::Edited(EditedSequences::new(
+            reference,
+            variants.clone(),
+        )));
+        haplotype.variants = Some(variants);
+        haplotype
+    }
+
+    /// Records the variants applied to the reference to obtain this haplotype.
+    ///
+    /// Read origins are then reported in coordinates of the original reference, along with
+    /// the variants each read carries.
+    ///
+    /// # Arguments
+    /// * `variants` - Layout of the variants applied to the haplotype's sequences
+    pub fn with_variants(mut self, variants: VariantLayout) -> Self {
+        self.variants = Some(Arc::new(variants));
+        self
+    }
+
+    /// Restricts read positions to the given target regions.
+    ///
+    /// # Arguments
+    /// * `targets` - Weighted regions of the haplotype's sequences to draw reads from
+    pub fn with_targets(mut self, targets: TargetRegions) -> Self {
+        self.targets = Some(targets);
+        self
+    }
+
+    /// Sets the repeat alleles expanded into the templates of reads drawn from this haplotype.
+    ///
+    /// # Arguments
+    /// * `repeats` - Repeat alleles placed on the haplotype's sequences
+    pub fn with_repeats(mut self, repeats: RepeatAlleles) -> Self {
+        self.repeats = Some(repeats);
+        self
+    }
+
+    /// Sets the relative share of reads drawn from this haplotype.
+    ///
+    /// # Errors
+    /// Returns an error if `weight` is negative or not finite
+    pub fn with_weight(mut self, weight: f64) -> Result<Self> {
+        if !weight.is_finite() || weight < 0.0 {
+            bail!(
+                "Haplotype weight must be a non-negative number, got {}",
+                weight
+            );
+        }
+        self.weight = weight;
+        Ok(self)
+    }
+
+    /// Sets the haplotype number reported for reads drawn from this haplotype.
+    ///
+    /// # Arguments
+    /// * `phase` - 1-based haplotype number, as in `HP` tags
+    pub fn with_phase(mut self, phase: usize) -> Self {
+        self.phase = Some(phase);
+        self
+    }
+
+    /// Sets the clone of a tumour sample this haplotype belongs to.
+    ///
+    /// # Arguments
+    /// * `clone` - Clone name, reported in the read truth
+    pub fn with_clone(mut self, clone: impl Into<String>) -> Self {
+        self.clone = Some(clone.into());
+        self
+    }
+
+    /// Returns the sequences of the haplotype.
+    pub fn reference(&self) -> &Reference {
+        &self.reference
+    }
+
+    /// Returns the layout of the variants applied to the haplotype, if any.
+    pub fn variants(&self) -> Option<&VariantLayout> {
+        self.variants.as_deref()
+    }
+
+    /// Returns the target regions of the haplotype, if any.
+    pub fn targets(&self) -> Option<&TargetRegions> {
+        self.targets.as_ref()
+    }
+
+    /// Returns the repeat alleles of the haplotype, if any.
+    pub fn repeats(&self) -> Option<&RepeatAlleles> {
+        self.repeats.as_ref()
+    }
+
+    /// Returns the relative share of reads drawn from this haplotype.
+    pub fn weight(&self) -> f64 {
+        self.weight
+    }
+
+    /// Returns the 1-based haplotype number, if set.
+    pub fn phase(&self) -> Option<usize> {
+        self.phase
+    }
+
+    /// Returns the clone the haplotype belongs to, if set.
+    pub fn clone_name(&self) -> Option<&str> {
+        self.clone.as_deref()
+    }
+
+    /// Returns the longest span a read can be placed in.
+    pub fn longest_span(&self) -> usize {
+        match &self.targets {
+            Some(targets) => targets.max_length(),
+            None => (0..self.reference.len())
+                .map(|i| self.reference.sequence_length(i))
+                .max()
+                .unwrap_or(0),
+        }
+    }
+}
+
+impl From<Reference> for Haplotype {
+    fn from(reference: Reference) -> Self {
+        Self::new(reference)
+    }
+}
@@ -1,10 +1,14 @@
 //! BAM file reading and writing.
 
+use super::sam::unaligned_record;
+use crate::transcriptome::to_thymine;
 use anyhow::{Context, Result};
 use noodles::bam;
 use noodles::bgzf;
 use noodles::sam;
 use noodles::sam::alignment::RecordBuf;
+use noodles::sam::alignment::record::data::field::Tag;
+use noodles::sam::alignment::record_buf::data::field::Value;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 
@@ -29,7 +33,7 @@ impl BamReader {
     /// # Ok::<(), anyhow::Error>(())
     /// ```
     pub fn from_path(path: &Path) -> Result<BamReaderIterator> {
-        let mut reader = bam::io::reader::Builder::default()
+        let mut reader = bam::io::reader::Builder
             .build_from_path(path)
             .with_context(|| format!("Failed to open BAM file: {}", path.display()))?;
 
@@ -45,6 +49,13 @@ pub struct BamReaderIterator {
     header: sam::Header,
 }
 
+impl BamReaderIterator {
+    /// Returns the header of the BAM file.
+    pub fn header(&self) -> &sam::Header {
+        &self.header
+    }
+}
+
 impl Iterator for BamReaderIterator {
     type Item = Result<RecordBuf>;
 
@@ -79,7 +90,20 @@ impl BamWriter {
         let header = sam::Header::builder()
             .add_comment("Generated by ReadFaker")
             .build();
+        Self::with_header(path, compression_threads, header)
+    }
 
+    /// Creates a new BAM writer with the given header.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the output BAM file
+    /// * `compression_threads` - Number of compression threads (0 = auto-detect)
+    /// * `header` - Header to write, e.g. with the read groups of the run
+    pub fn with_header(
+        path: &PathBuf,
+        compression_threads: usize,
+        header: sam::Header,
+    ) -> Result<Self> {
         let file = File::create(path)
             .with_context(|| format!("Failed to create BAM file: {}", path.display()))?;
 
@@ -112,27 +136,20 @@ impl BamWriter {
     /// * `name` - Read name/identifier
     /// * `sequence` - Nucleotide sequence
     /// * `quality_ascii` - Quality scores in Phred+33 ASCII encoding
+    /// * `tags` - Auxiliary data fields to attach to the record
     pub fn write_record(
         &mut self,
         name: &str,
         sequence: &[u8],
         quality_ascii: &[u8],
+        tags: Vec<(Tag, Value)>,
     ) -> Result<()> {
         use noodles::sam::alignment::io::Write as AlignmentWrite;
 
-        // Convert Phred+33 ASCII to raw Phred scores (0-93) for BAM format
-        let quality_phred: Vec<u8> = quality_ascii
-            .iter()
-            .map(|&q| q.saturating_sub(33))
-            .collect();
-
-        // Create an unaligned SAM record
-        let record = RecordBuf::builder()
-            .set_name(name.as_bytes())
-            .set_flags(sam::alignment::record::Flags::UNMAPPED)
-            .set_sequence(sequence.to_vec().into())
-            .set_quality_scores(quality_phred.into())
-            .build();
+        // BAM has no code for U, so RNA reads are stored in the DNA alphabet
+        let mut sequence = sequence.to_vec();
+        to_thymine(&mut sequence);
+        let record = unaligned_record(name, &sequence, quality_ascii, tags);
 
         self.writer
             .write_alignment_record(&self.header, &record)
@@ -176,8 +193,12 @@ mod tests {
         // 'J' = 74 (ASCII) = Phred 41
         {
             let mut writer = BamWriter::new(&temp_file, 4).unwrap();
-            writer.write_record("read1", b"ACGT", b"IIII").unwrap();
-            writer.write_record("read2", b"TGCA", b"JJJJ").unwrap();
+            writer
+                .write_record("read1", b"ACGT", b"IIII", Vec::new())
+                .unwrap();
+            writer
+                .write_record("read2", b"TGCA", b"JJJJ", Vec::new())
+                .unwrap();
             writer.finish().unwrap();
         }
 
@@ -219,6 +240,24 @@ mod tests {
         std::fs::remove_file(temp_file).ok();
     }
 
+    #[test]
+    fn test_bam_rna_sequence() {
+        let temp_file = std::env::temp_dir().join("readfaker_rna_test.bam");
+        let mut writer = BamWriter::new(&temp_file, 1).unwrap();
+        writer
+            .write_record("read1", b"ACGUNu", b"IIIIII", Vec::new())
+            .unwrap();
+        writer.finish().unwrap();
+
+        let records: Vec<RecordBuf> = BamReader::from_path(&temp_file)
+            .unwrap()
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        std::fs::remove_file(&temp_file).ok();
+        // U is written as T rather than decoded as N
+        assert_eq!(records[0].sequence().as_ref(), b"ACGTNT");
+    }
+
     #[test]
     fn test_bam_eof_marker() {
         use std::io::{Read, Seek};
@@ -229,7 +268,9 @@ mod tests {
         // Write a BAM file
         {
             let mut writer = BamWriter::new(&temp_file, 4).unwrap();
-            writer.write_record("read1", b"ACGT", b"IIII").unwrap();
+            writer
+                .write_record("read1", b"ACGT", b"IIII", Vec::new())
+                .unwrap();
             writer.finish().unwrap();
         }
 
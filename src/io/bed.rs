@@ -0,0 +1,184 @@
+//! BED file reading.
+
+use super::is_gzip_compressed;
+use anyhow::{Context, Result, bail};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// A single BED interval (0-based, half-open) with its optional columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BedRecord {
+    pub chrom: String,
+    pub start: usize,
+    pub end: usize,
+    /// Columns after `chromEnd`, in file order (name, score, strand, ...)
+    pub fields: Vec<String>,
+}
+
+impl BedRecord {
+    /// Returns the name column, if present.
+    pub fn name(&self) -> Option<&str> {
+        self.field(0)
+    }
+
+    /// Returns the score column, if present and numeric.
+    pub fn score(&self) -> Option<f64> {
+        self.field(1).and_then(|s| s.parse().ok())
+    }
+
+    /// Returns the strand column (`+` or `-`), if present.
+    pub fn strand(&self) -> Option<char> {
+        self.field(2)
+            .and_then(|s| s.chars().next())
+            .filter(|c| matches!(c, '+' | '-'))
+    }
+
+    /// Returns the length of the interval.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns true if the interval is empty.
+    pub fn is_empty(&self) -> bool {
+        self.end == self.start
+    }
+
+    /// Returns an optional column by position after `chromEnd`, treating `.` as missing.
+    pub fn field(&self, index: usize) -> Option<&str> {
+        self.fields
+            .get(index)
+            .map(String::as_str)
+            .filter(|s| !s.is_empty() && *s != ".")
+    }
+}
+
+/// Reader for BED files.
+pub struct BedReader;
+
+impl BedReader {
+    /// Reads all intervals from a BED file.
+    ///
+    /// Automatically detects and handles both compressed (gzip/bgzf) and uncompressed files.
+    /// Comment, `track` and `browser` lines are ignored.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the BED file
+    ///
+    /// # Returns
+    /// Vector of all intervals in the file
+    pub fn read(path: &Path) -> Result<Vec<BedRecord>> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open BED file: {}", path.display()))?;
+
+        // Check if file is gzip-compressed by reading magic bytes
+        let mut buffered = BufReader::new(file);
+        let is_compressed = is_gzip_compressed(&mut buffered)?;
+
+        let reader: Box<dyn BufRead> = if is_compressed {
+            Box::new(BufReader::new(MultiGzDecoder::new(buffered)))
+        } else {
+            Box::new(buffered)
+        };
+
+        let mut records = Vec::new();
+        for (line_number, line) in reader.lines().enumerate() {
+            let line =
+                line.with_context(|| format!("Failed to read BED file: {}", path.display()))?;
+            let line = line.trim_end();
+            if line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("track")
+                || line.starts_with("browser")
+            {
+                continue;
+            }
+
+            records.push(parse_line(line).with_context(|| {
+                format!(
+                    "Failed to parse BED line {} in {}",
+                    line_number + 1,
+                    path.display()
+                )
+            })?);
+        }
+
+        if records.is_empty() {
+            bail!("No intervals found in BED file: {}", path.display());
+        }
+
+        Ok(records)
+    }
+}
+
+/// Parses a single BED line (tab-separated, falling back to whitespace).
+fn parse_line(line: &str) -> Result<BedRecord> {
+    let columns: Vec<&str> = if line.contains('\t') {
+        line.split('\t').collect()
+    } else {
+        line.split_whitespace().collect()
+    };
+
+    if columns.len() < 3 {
+        bail!("Expected at least 3 columns, got {}", columns.len());
+    }
+
+    let start: usize = columns[1]
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid start position '{}'", columns[1]))?;
+    let end: usize = columns[2]
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid end position '{}'", columns[2]))?;
+
+    if end < start {
+        bail!("End position {} is before start position {}", end, start);
+    }
+
+    Ok(BedRecord {
+        chrom: columns[0].to_string(),
+        start,
+        end,
+        fields: columns[3..].iter().map(|s| s.trim().to_string()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let record = parse_line("chr1\t100\t200\tgene1\t2.5\t-").unwrap();
+        assert_eq!(record.chrom, "chr1");
+        assert_eq!(record.len(), 100);
+        assert_eq!(record.name(), Some("gene1"));
+        assert_eq!(record.score(), Some(2.5));
+        assert_eq!(record.strand(), Some('-'));
+
+        let record = parse_line("chr2 5 10").unwrap();
+        assert_eq!(record.name(), None);
+        assert_eq!(record.score(), None);
+
+        assert!(parse_line("chr1\t200\t100").is_err());
+        assert!(parse_line("chr1\t200").is_err());
+    }
+
+    #[test]
+    fn test_read_skips_headers() {
+        let temp_file = std::env::temp_dir().join("readfaker_test_targets.bed");
+        std::fs::write(
+            &temp_file,
+            "track name=targets\n# comment\nchr1\t0\t10\nchr2\t5\t15\ttarget\t3\n",
+        )
+        .unwrap();
+
+        let records = BedReader::read(&temp_file).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].score(), Some(3.0));
+
+        std::fs::remove_file(temp_file).ok();
+    }
+}
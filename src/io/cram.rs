@@ -0,0 +1,117 @@
+//! CRAM file writing.
+
+use super::sam::unaligned_record;
+use crate::transcriptome::to_thymine;
+use anyhow::{Context, Result, anyhow};
+use noodles::cram;
+use noodles::sam;
+use noodles::sam::alignment::record::data::field::Tag;
+use noodles::sam::alignment::record_buf::data::field::Value;
+use std::fs::File;
+use std::path::Path;
+
+/// Writer for unaligned CRAM files.
+///
+/// Unaligned records are not compressed against a reference, so files are written without
+/// one and list no reference sequences.
+pub struct CramWriter {
+    writer: cram::io::Writer<File>,
+    header: sam::Header,
+}
+
+impl CramWriter {
+    /// Creates a new CRAM writer with the given header.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the output CRAM file
+    /// * `header` - Header to write, e.g. with the read groups of the run
+    pub fn with_header(path: &Path, header: sam::Header) -> Result<Self> {
+        let mut writer = cram::io::writer::Builder::default()
+            .build_from_path(path)
+            .with_context(|| format!("Failed to create CRAM file: {}", path.display()))?;
+        writer
+            .write_header(&header)
+            .context("Failed to write CRAM header")?;
+        Ok(Self { writer, header })
+    }
+
+    /// Writes a single unaligned CRAM record.
+    ///
+    /// # Arguments
+    /// * `name` - Read name/identifier
+    /// * `sequence` - Nucleotide sequence
+    /// * `quality_ascii` - Quality scores in Phred+33 ASCII encoding
+    /// * `tags` - Auxiliary data fields to attach to the record
+    pub fn write_record(
+        &mut self,
+        name: &str,
+        sequence: &[u8],
+        quality_ascii: &[u8],
+        tags: Vec<(Tag, Value)>,
+    ) -> Result<()> {
+        use noodles::sam::alignment::io::Write as AlignmentWrite;
+
+        // CRAM has no code for U, so RNA reads are stored in the DNA alphabet
+        let mut sequence = sequence.to_vec();
+        to_thymine(&mut sequence);
+        let record = unaligned_record(name, &sequence, quality_ascii, tags);
+        self.writer
+            .write_alignment_record(&self.header, &record)
+            .context("Failed to write CRAM record")
+    }
+
+    /// Finishes the writer, writing the last container and the EOF container.
+    pub fn finish(mut self) -> Result<()> {
+        self.writer
+            .try_finish(&self.header)
+            .map_err(|e| anyhow!("Failed to finish CRAM writer: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cram_write_and_read() {
+        let path = std::env::temp_dir().join("readfaker_cram_test.cram");
+        let header = sam::Header::builder()
+            .add_comment("Generated by ReadFaker")
+            .build();
+        let mut writer = CramWriter::with_header(&path, header).unwrap();
+        writer
+            .write_record(
+                "read1",
+                b"ACGTN",
+                b"+5?I#",
+                vec![(Tag::new(b'H', b'P'), Value::Int32(1))],
+            )
+            .unwrap();
+        writer.finish().unwrap();
+
+        let mut reader = cram::io::reader::Builder::default()
+            .build_from_path(&path)
+            .unwrap();
+        let header = reader.read_header().unwrap();
+        let records: Vec<_> = reader
+            .records(&header)
+            .collect::<std::io::Result<_>>()
+            .unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(header.reference_sequences().is_empty());
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(
+            record.name().map(|name| name.to_vec()),
+            Some(b"read1".to_vec())
+        );
+        assert!(record.flags().is_unmapped());
+        assert_eq!(record.sequence().as_ref(), b"ACGTN");
+        assert_eq!(record.quality_scores().as_ref(), [10, 20, 30, 40, 2]);
+        assert_eq!(
+            record.data().get(&Tag::new(b'H', b'P')),
+            Some(&Value::Int32(1))
+        );
+    }
+}
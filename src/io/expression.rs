@@ -0,0 +1,183 @@
+//! Transcript expression table reading.
+
+use super::is_gzip_compressed;
+use anyhow::{Context, Result, bail};
+use clap::ValueEnum;
+use flate2::read::MultiGzDecoder;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Quantity given by an expression table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ExpressionUnit {
+    /// Transcripts per million: molecule abundance, independent of transcript length
+    #[default]
+    Tpm,
+    /// Read counts: one read per molecule, as in long-read quantifications
+    Counts,
+}
+
+impl ExpressionUnit {
+    /// Header names of the column holding this quantity in common quantification outputs
+    /// (salmon, kallisto, featureCounts-like tables).
+    fn column_names(self) -> &'static [&'static str] {
+        match self {
+            ExpressionUnit::Tpm => &["tpm"],
+            ExpressionUnit::Counts => &["numreads", "est_counts", "count", "counts", "reads"],
+        }
+    }
+}
+
+/// Reader for transcript expression tables.
+pub struct ExpressionReader;
+
+impl ExpressionReader {
+    /// Reads the expression of each transcript from a tab- or whitespace-separated table.
+    ///
+    /// The first column holds transcript IDs. Tables with a header take the value from the
+    /// column named after `unit` (e.g. `TPM` or `NumReads`); headerless tables take it from
+    /// the second column. Both units are read as relative molecule abundances.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the table (optionally gzip-compressed)
+    /// * `unit` - Quantity to read
+    ///
+    /// # Returns
+    /// Expression value per transcript ID
+    ///
+    /// # Errors
+    /// Returns an error if the value column cannot be found or a value is not a non-negative
+    /// number
+    pub fn read(path: &Path, unit: ExpressionUnit) -> Result<HashMap<String, f64>> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open expression table: {}", path.display()))?;
+
+        let mut buffered = BufReader::new(file);
+        let is_compressed = is_gzip_compressed(&mut buffered)?;
+
+        let reader: Box<dyn BufRead> = if is_compressed {
+            Box::new(BufReader::new(MultiGzDecoder::new(buffered)))
+        } else {
+            Box::new(buffered)
+        };
+
+        let mut column = None;
+        let mut expression = HashMap::new();
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line
+                .with_context(|| format!("Failed to read expression table: {}", path.display()))?;
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let columns: Vec<&str> = if line.contains('\t') {
+                line.split('\t').collect()
+            } else {
+                line.split_whitespace().collect()
+            };
+
+            let index = match column {
+                Some(index) => index,
+                None => {
+                    let index = value_column(&columns, unit).with_context(|| {
+                        format!("Invalid header in expression table: {}", path.display())
+                    })?;
+                    column = Some(index);
+                    // A header line holds no values
+                    if columns
+                        .get(1)
+                        .is_some_and(|s| s.trim().parse::<f64>().is_err())
+                    {
+                        continue;
+                    }
+                    index
+                }
+            };
+
+            let value = columns
+                .get(index)
+                .with_context(|| {
+                    format!(
+                        "Missing expression value on line {} of {}",
+                        line_number + 1,
+                        path.display()
+                    )
+                })?
+                .trim();
+            let value: f64 = value
+                .parse()
+                .ok()
+                .filter(|v: &f64| *v >= 0.0)
+                .with_context(|| {
+                    format!(
+                        "Invalid expression value '{}' on line {} of {}",
+                        value,
+                        line_number + 1,
+                        path.display()
+                    )
+                })?;
+            expression.insert(columns[0].trim().to_string(), value);
+        }
+
+        if expression.is_empty() {
+            bail!(
+                "No transcripts found in expression table: {}",
+                path.display()
+            );
+        }
+
+        Ok(expression)
+    }
+}
+
+/// Finds the value column from the first line of a table.
+fn value_column(columns: &[&str], unit: ExpressionUnit) -> Result<usize> {
+    if columns.len() < 2 {
+        bail!("Expected at least 2 columns, got {}", columns.len());
+    }
+    if columns[1].trim().parse::<f64>().is_ok() {
+        return Ok(1);
+    }
+
+    let names = unit.column_names();
+    columns
+        .iter()
+        .position(|column| names.contains(&column.trim().to_lowercase().as_str()))
+        .with_context(|| format!("No column named {} in header", names.join(" or ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_expression_tables() {
+        let temp_file = std::env::temp_dir().join("readfaker_test_expression.tsv");
+
+        // salmon quant.sf
+        std::fs::write(
+            &temp_file,
+            "Name\tLength\tEffectiveLength\tTPM\tNumReads\nT1\t1000\t800\t25.5\t10\nT2\t500\t300\t0\t0\n",
+        )
+        .unwrap();
+        let tpm = ExpressionReader::read(&temp_file, ExpressionUnit::Tpm).unwrap();
+        assert_eq!(tpm.len(), 2);
+        assert_eq!(tpm["T1"], 25.5);
+        let counts = ExpressionReader::read(&temp_file, ExpressionUnit::Counts).unwrap();
+        assert_eq!(counts["T1"], 10.0);
+
+        // Headerless
+        std::fs::write(&temp_file, "T1 3\nT2 1.5\n").unwrap();
+        let counts = ExpressionReader::read(&temp_file, ExpressionUnit::Counts).unwrap();
+        assert_eq!(counts["T2"], 1.5);
+
+        std::fs::write(&temp_file, "id\tvalue\nT1\t3\n").unwrap();
+        assert!(ExpressionReader::read(&temp_file, ExpressionUnit::Tpm).is_err());
+        std::fs::write(&temp_file, "T1\t-3\n").unwrap();
+        assert!(ExpressionReader::read(&temp_file, ExpressionUnit::Tpm).is_err());
+
+        std::fs::remove_file(temp_file).ok();
+    }
+}
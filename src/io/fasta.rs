@@ -1,10 +1,16 @@
-//! FASTA file reading.
+//! FASTA file reading and writing.
 
-use anyhow::{Context, Result, bail};
+use super::format::OutputStream;
+use super::is_gzip_compressed;
+use crate::variants::EditedSequences;
+use anyhow::{Context, Result, anyhow, bail};
+use flate2::read::MultiGzDecoder;
+use noodles::core::{Position, Region};
 use noodles::fasta;
 use std::fs::File;
-use std::io::BufReader;
-use std::path::Path;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 /// Represents a FASTA sequence with its ID and nucleotide sequence.
 #[derive(Debug, Clone)]
@@ -19,6 +25,8 @@ pub struct FastaReader;
 impl FastaReader {
     /// Reads all sequences from a FASTA file.
     ///
+    /// Automatically detects and handles both compressed (gzip/bgzf) and uncompressed FASTA files.
+    ///
     /// # Arguments
     /// * `path` - Path to the FASTA file
     ///
@@ -29,7 +37,18 @@ impl FastaReader {
 
         let file = File::open(path)
             .with_context(|| format!("Failed to open FASTA file: {}", path.display()))?;
-        let mut reader = fasta::io::Reader::new(BufReader::new(file));
+
+        // Check if file is gzip-compressed by reading magic bytes
+        let mut buffered = BufReader::new(file);
+        let is_compressed = is_gzip_compressed(&mut buffered)?;
+
+        let inner: Box<dyn BufRead> = if is_compressed {
+            // Use MultiGzDecoder which handles both regular gzip and BGZF
+            Box::new(BufReader::new(MultiGzDecoder::new(buffered)))
+        } else {
+            Box::new(buffered)
+        };
+        let mut reader = fasta::io::Reader::new(inner);
 
         for result in reader.records() {
             let record = result
@@ -47,6 +66,287 @@ impl FastaReader {
 
         Ok(records)
     }
+
+    /// Reads all sequences from several FASTA files, in the order given.
+    ///
+    /// # Arguments
+    /// * `paths` - Paths to the FASTA files
+    ///
+    /// # Returns
+    /// Vector of all FASTA records across all files
+    pub fn read_all(paths: &[PathBuf]) -> Result<Vec<FastaRecord>> {
+        let mut records = Vec::new();
+        for path in paths {
+            records.extend(Self::read(path)?);
+        }
+        Ok(records)
+    }
+}
+
+type IndexedReaderInner = fasta::io::IndexedReader<fasta::io::BufReader<File>>;
+
+/// Reader for indexed FASTA files that fetches sequence windows on demand.
+///
+/// Requires a FASTA index (`.fai`) next to the file and, for bgzipped FASTA (`.gz`/`.bgz`),
+/// a BGZF index (`.gzi`), as produced by `samtools faidx`. Only the index is kept in memory.
+///
+/// Underlying file handles are pooled, so windows can be fetched concurrently from
+/// multiple threads.
+pub struct IndexedFastaReader {
+    path: PathBuf,
+    index: fasta::fai::Index,
+    readers: Mutex<Vec<IndexedReaderInner>>,
+}
+
+impl IndexedFastaReader {
+    /// Opens an indexed FASTA file.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the FASTA file (plain or bgzipped)
+    ///
+    /// # Errors
+    /// Returns an error if the file or its index cannot be opened, or the index is empty
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let reader = fasta::io::indexed_reader::Builder::default()
+            .build_from_path(path)
+            .with_context(|| {
+                format!(
+                    "Failed to open indexed FASTA file: {} (index it with `samtools faidx`)",
+                    path.display()
+                )
+            })?;
+        let index = reader.index().clone();
+
+        if index.as_ref().is_empty() {
+            bail!("No sequences found in FASTA index: {}", path.display());
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            index,
+            readers: Mutex::new(vec![reader]),
+        })
+    }
+
+    /// Returns the number of sequences in the index.
+    pub fn len(&self) -> usize {
+        self.index.as_ref().len()
+    }
+
+    /// Returns true if the index has no sequences.
+    pub fn is_empty(&self) -> bool {
+        self.index.as_ref().is_empty()
+    }
+
+    /// Returns the name of the sequence at the given position in the index.
+    pub fn sequence_id(&self, sequence_index: usize) -> &str {
+        std::str::from_utf8(self.index.as_ref()[sequence_index].name()).unwrap_or_default()
+    }
+
+    /// Returns the length of the sequence at the given position in the index.
+    pub fn sequence_length(&self, sequence_index: usize) -> usize {
+        self.index.as_ref()[sequence_index].length() as usize
+    }
+
+    /// Fetches a window of a sequence from disk.
+    ///
+    /// # Arguments
+    /// * `sequence_index` - Position of the sequence in the index
+    /// * `start` - Zero-based start of the window
+    /// * `length` - Length of the window (must be at least 1)
+    pub fn fetch(&self, sequence_index: usize, start: usize, length: usize) -> Result<Vec<u8>> {
+        let name = self.sequence_id(sequence_index);
+        let region_start =
+            Position::try_from(start + 1).map_err(|e| anyhow!("Invalid window start: {}", e))?;
+        let region_end =
+            Position::try_from(start + length).map_err(|e| anyhow!("Invalid window end: {}", e))?;
+        let region = Region::new(name, region_start..=region_end);
+
+        // Reuse an idle reader if available, otherwise open a new handle for this thread
+        let pooled = self.readers.lock().unwrap().pop();
+        let mut reader = match pooled {
+            Some(reader) => reader,
+            None => fasta::io::indexed_reader::Builder::default()
+                .set_index(self.index.clone())
+                .build_from_path(&self.path)
+                .with_context(|| {
+                    format!("Failed to open indexed FASTA file: {}", self.path.display())
+                })?,
+        };
+
+        let record = reader
+            .query(&region)
+            .with_context(|| format!("Failed to fetch {} from {}", region, self.path.display()))?;
+        self.readers.lock().unwrap().push(reader);
+
+        Ok(record.sequence().as_ref().to_vec())
+    }
+}
+
+/// Reference sequences available for read sampling.
+pub enum Reference {
+    /// All sequences loaded in memory
+    InMemory(Vec<FastaRecord>),
+    /// Sequences fetched on demand from one or more indexed FASTA files
+    Indexed(Vec<IndexedFastaReader>),
+    /// Sequences with variants, read through their layout from shared reference sequences
+    Edited(EditedSequences),
+}
+
+impl Reference {
+    /// Opens reference sequences from one or more FASTA files.
+    ///
+    /// # Arguments
+    /// * `paths` - Paths to the FASTA files (sequences are numbered in file order)
+    /// * `indexed` - Fetch windows on demand from the FASTA indexes instead of loading
+    ///   the sequences into memory
+    pub fn from_paths(paths: &[PathBuf], indexed: bool) -> Result<Self> {
+        if indexed {
+            let readers = paths
+                .iter()
+                .map(|path| IndexedFastaReader::from_path(path))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Reference::Indexed(readers))
+        } else {
+            Ok(Reference::InMemory(FastaReader::read_all(paths)?))
+        }
+    }
+
+    /// Returns the number of reference sequences.
+    pub fn len(&self) -> usize {
+        match self {
+            Reference::InMemory(records) => records.len(),
+            Reference::Indexed(readers) => readers.iter().map(|r| r.len()).sum(),
+            Reference::Edited(edited) => edited.reference().len(),
+        }
+    }
+
+    /// Returns true if there are no reference sequences.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the ID of the reference sequence at the given position.
+    pub fn sequence_id(&self, sequence_index: usize) -> &str {
+        match self {
+            Reference::InMemory(records) => &records[sequence_index].id,
+            Reference::Indexed(readers) => {
+                let (reader, local_index) = locate(readers, sequence_index);
+                reader.sequence_id(local_index)
+            }
+            Reference::Edited(edited) => edited.reference().sequence_id(sequence_index),
+        }
+    }
+
+    /// Returns the length of the reference sequence at the given position.
+    pub fn sequence_length(&self, sequence_index: usize) -> usize {
+        match self {
+            Reference::InMemory(records) => records[sequence_index].sequence.len(),
+            Reference::Indexed(readers) => {
+                let (reader, local_index) = locate(readers, sequence_index);
+                reader.sequence_length(local_index)
+            }
+            Reference::Edited(edited) => edited.sequence_length(sequence_index),
+        }
+    }
+
+    /// Returns a copy of a window of a reference sequence.
+    ///
+    /// # Arguments
+    /// * `sequence_index` - Position of the reference sequence
+    /// * `start` - Zero-based start of the window
+    /// * `length` - Length of the window
+    pub fn fetch(&self, sequence_index: usize, start: usize, length: usize) -> Result<Vec<u8>> {
+        match self {
+            Reference::InMemory(records) => {
+                Ok(records[sequence_index].sequence[start..start + length].to_vec())
+            }
+            Reference::Indexed(_) if length == 0 => Ok(Vec::new()),
+            Reference::Indexed(readers) => {
+                let (reader, local_index) = locate(readers, sequence_index);
+                reader.fetch(local_index, start, length)
+            }
+            Reference::Edited(edited) => edited.fetch(sequence_index, start, length),
+        }
+    }
+
+    /// Returns the sequences in memory, or None if they are read from disk or edited.
+    pub fn records(&self) -> Option<&[FastaRecord]> {
+        match self {
+            Reference::InMemory(records) => Some(records),
+            _ => None,
+        }
+    }
+
+    /// Returns the reference sequences coordinates refer to: those the variants of edited
+    /// sequences were placed on, or the sequences themselves otherwise.
+    pub fn unedited(&self) -> &Reference {
+        match self {
+            Reference::Edited(edited) => edited.reference(),
+            _ => self,
+        }
+    }
+}
+
+/// Maps a global sequence index to the indexed reader holding it and the local index within it.
+fn locate(
+    readers: &[IndexedFastaReader],
+    mut sequence_index: usize,
+) -> (&IndexedFastaReader, usize) {
+    for reader in readers {
+        if sequence_index < reader.len() {
+            return (reader, sequence_index);
+        }
+        sequence_index -= reader.len();
+    }
+    panic!("Reference sequence index out of bounds");
+}
+
+impl From<Vec<FastaRecord>> for Reference {
+    fn from(records: Vec<FastaRecord>) -> Self {
+        Reference::InMemory(records)
+    }
+}
+
+/// Writer for FASTA reads, one sequence line per read, BGZF-compressed for `.gz`/`.bgz` paths.
+pub struct FastaWriter {
+    writer: fasta::io::Writer<OutputStream>,
+}
+
+impl FastaWriter {
+    /// Creates a new FASTA writer for the specified file path.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the output FASTA file
+    /// * `compression_threads` - Number of compression threads (0 = auto-detect)
+    pub fn new(path: &Path, compression_threads: usize) -> Result<Self> {
+        let writer = fasta::io::writer::Builder::default()
+            .set_line_base_count(std::num::NonZero::<usize>::MAX)
+            .build_from_writer(OutputStream::create(path, compression_threads)?);
+        Ok(Self { writer })
+    }
+
+    /// Writes a single read.
+    ///
+    /// # Arguments
+    /// * `name` - Read name/identifier
+    /// * `description` - Description after the name, if any
+    /// * `sequence` - Nucleotide sequence
+    pub fn write_record(&mut self, name: &[u8], description: &[u8], sequence: &[u8]) -> Result<()> {
+        let definition = fasta::record::Definition::new(
+            name,
+            (!description.is_empty()).then(|| description.into()),
+        );
+        let record = fasta::Record::new(definition, sequence.to_vec().into());
+        self.writer
+            .write_record(&record)
+            .context("Failed to write FASTA record")
+    }
+
+    /// Finishes the writer, flushing the file and finalizing compressed output.
+    pub fn finish(self) -> Result<()> {
+        self.writer.into_inner().finish()
+    }
 }
 
 #[cfg(test)]
@@ -62,4 +362,62 @@ mod tests {
         assert_eq!(record.id, "seq1");
         assert_eq!(record.sequence, b"ACGT");
     }
+
+    #[test]
+    fn test_indexed_fetch_matches_in_memory() {
+        let temp_file = std::env::temp_dir().join("readfaker_test_indexed.fa");
+        std::fs::write(
+            &temp_file,
+            ">chr1\nACGTACGTAC\nGGGGCCCCTT\nAA\n>chr2\nTTTTGGGGCC\n",
+        )
+        .unwrap();
+        let index = fasta::fs::index(&temp_file).unwrap();
+        let index_file = temp_file.with_extension("fa.fai");
+        fasta::fai::fs::write(&index_file, &index).unwrap();
+
+        let in_memory = Reference::from(FastaReader::read(&temp_file).unwrap());
+        let indexed = Reference::from_paths(std::slice::from_ref(&temp_file), true).unwrap();
+
+        assert_eq!(indexed.len(), 2);
+        assert_eq!(indexed.sequence_id(1), "chr2");
+        assert_eq!(indexed.sequence_length(0), 22);
+        // Windows spanning line breaks must be joined transparently
+        for (sequence_index, start, length) in [(0, 0, 22), (0, 8, 5), (0, 19, 3), (1, 2, 6)] {
+            assert_eq!(
+                indexed.fetch(sequence_index, start, length).unwrap(),
+                in_memory.fetch(sequence_index, start, length).unwrap()
+            );
+        }
+
+        std::fs::remove_file(temp_file).ok();
+        std::fs::remove_file(index_file).ok();
+    }
+
+    #[test]
+    fn test_read_compressed_and_multiple_files() {
+        use flate2::Compression;
+        use flate2::write::GzEncoder;
+        use std::io::Write;
+
+        let plain_file = std::env::temp_dir().join("readfaker_test_multi.fa");
+        let gz_file = std::env::temp_dir().join("readfaker_test_multi.fa.gz");
+        std::fs::write(&plain_file, ">chr1\nACGT\n").unwrap();
+        let mut encoder = GzEncoder::new(File::create(&gz_file).unwrap(), Compression::default());
+        encoder.write_all(b">plasmid\nGGCC\nTTAA\n").unwrap();
+        encoder.finish().unwrap();
+
+        let records = FastaReader::read(&gz_file).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "plasmid");
+        assert_eq!(records[0].sequence, b"GGCCTTAA");
+
+        let reference =
+            Reference::from_paths(&[plain_file.clone(), gz_file.clone()], false).unwrap();
+        assert_eq!(reference.len(), 2);
+        assert_eq!(reference.sequence_id(0), "chr1");
+        assert_eq!(reference.sequence_id(1), "plasmid");
+
+        std::fs::remove_file(plain_file).ok();
+        std::fs::remove_file(gz_file).ok();
+    }
 }
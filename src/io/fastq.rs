@@ -1,5 +1,7 @@
 //! FASTQ file reading and writing.
 
+use super::format::should_compress;
+use super::is_gzip_compressed;
 use anyhow::{Context, Result};
 use flate2::read::MultiGzDecoder;
 use noodles::bgzf;
@@ -216,21 +218,6 @@ impl FastqWriter {
     }
 }
 
-/// Helper function to check if a file is gzip-compressed
-fn is_gzip_compressed<R: std::io::Read>(reader: &mut BufReader<R>) -> Result<bool> {
-    let buffer = reader.fill_buf().context("Failed to read file header")?;
-
-    // Check for gzip magic bytes (0x1f 0x8b)
-    Ok(buffer.len() >= 2 && buffer[0] == 0x1f && buffer[1] == 0x8b)
-}
-
-/// Helper function to check if a file should be compressed based on its extension
-fn should_compress(path: &Path) -> bool {
-    path.extension()
-        .and_then(|ext| ext.to_str())
-        .is_some_and(|ext| ["gz", "bgz"].iter().any(|s| ext.eq_ignore_ascii_case(s)))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,180 @@
+//! Registry of the output formats of simulated reads.
+
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use noodles::bgzf;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Format simulated reads are written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// FASTQ, with read annotations in the comment
+    Fastq,
+    /// FASTA, with read annotations in the description and qualities dropped
+    Fasta,
+    /// Unaligned SAM text
+    Sam,
+    /// Unaligned BAM
+    Bam,
+    /// Unaligned CRAM, written against the simulation reference
+    Cram,
+}
+
+/// Output formats with the file extensions they are detected by.
+const REGISTRY: &[(OutputFormat, &[&str])] = &[
+    (OutputFormat::Fastq, &["fastq", "fq"]),
+    (OutputFormat::Fasta, &["fasta", "fa", "fna", "fas"]),
+    (OutputFormat::Sam, &["sam"]),
+    (OutputFormat::Bam, &["bam"]),
+    (OutputFormat::Cram, &["cram"]),
+];
+
+/// Extensions of BGZF-compressed text output.
+const COMPRESSION_EXTENSIONS: &[&str] = &["gz", "bgz", "bgzf"];
+
+impl OutputFormat {
+    /// Detects the format of an output file from its extension, looking past a compression
+    /// extension (`reads.fa.gz` is FASTA).
+    ///
+    /// Unknown extensions default to FASTQ.
+    pub fn from_path(path: &Path) -> Self {
+        let file_name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let base_name = COMPRESSION_EXTENSIONS
+            .iter()
+            .find_map(|extension| file_name.strip_suffix(&format!(".{}", extension)))
+            .unwrap_or(&file_name);
+        let extension = base_name
+            .rsplit_once('.')
+            .map_or("", |(_, extension)| extension);
+
+        REGISTRY
+            .iter()
+            .find(|(_, extensions)| extensions.contains(&extension))
+            .map_or(OutputFormat::Fastq, |(format, _)| *format)
+    }
+
+    /// Returns the explicitly requested format, or the one detected from the path.
+    ///
+    /// # Arguments
+    /// * `format` - Format given by the user, if any
+    /// * `path` - Path to the output file
+    pub fn resolve(format: Option<Self>, path: &Path) -> Self {
+        format.unwrap_or_else(|| Self::from_path(path))
+    }
+
+    /// Returns the main file extension of the format.
+    pub fn extension(self) -> &'static str {
+        REGISTRY
+            .iter()
+            .find(|(format, _)| *format == self)
+            .map(|(_, extensions)| extensions[0])
+            .expect("every output format is registered")
+    }
+}
+
+/// Returns true if text output to the path is BGZF-compressed, by its extension.
+pub(crate) fn should_compress(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            COMPRESSION_EXTENSIONS
+                .iter()
+                .any(|s| ext.eq_ignore_ascii_case(s))
+        })
+}
+
+/// File stream of text output, BGZF-compressed for compressed extensions.
+pub(crate) enum OutputStream {
+    Uncompressed(BufWriter<File>),
+    Compressed(bgzf::io::MultithreadedWriter<File>),
+}
+
+impl OutputStream {
+    /// Creates the output file.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the output file
+    /// * `compression_threads` - Number of compression threads (0 = auto-detect)
+    pub(crate) fn create(path: &Path, compression_threads: usize) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create output file: {}", path.display()))?;
+        if !should_compress(path) {
+            return Ok(OutputStream::Uncompressed(BufWriter::new(file)));
+        }
+
+        // Use specified threads or auto-detect CPU cores
+        let worker_count = if compression_threads == 0 {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(4) // Fallback to 4 threads
+        } else {
+            compression_threads
+        };
+        Ok(OutputStream::Compressed(
+            bgzf::io::MultithreadedWriter::with_worker_count(
+                std::num::NonZero::new(worker_count).unwrap(),
+                file,
+            ),
+        ))
+    }
+
+    /// Flushes the stream, writing the BGZF EOF marker of compressed files.
+    pub(crate) fn finish(self) -> Result<()> {
+        match self {
+            OutputStream::Uncompressed(mut writer) => {
+                writer.flush().context("Failed to flush output file")
+            }
+            OutputStream::Compressed(mut writer) => writer
+                .finish()
+                .map(|_| ())
+                .map_err(|e| anyhow!("Failed to finish BGZF writer: {}", e)),
+        }
+    }
+}
+
+impl Write for OutputStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            OutputStream::Uncompressed(writer) => writer.write(buf),
+            OutputStream::Compressed(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            OutputStream::Uncompressed(writer) => writer.flush(),
+            OutputStream::Compressed(writer) => writer.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_detection() {
+        let detect = |name: &str| OutputFormat::from_path(Path::new(name));
+        assert_eq!(detect("reads.fastq.gz"), OutputFormat::Fastq);
+        assert_eq!(detect("reads.fq"), OutputFormat::Fastq);
+        assert_eq!(detect("reads.FA.bgz"), OutputFormat::Fasta);
+        assert_eq!(detect("reads.fasta"), OutputFormat::Fasta);
+        assert_eq!(detect("reads.sam"), OutputFormat::Sam);
+        assert_eq!(detect("reads.bam"), OutputFormat::Bam);
+        assert_eq!(detect("reads.txt"), OutputFormat::Fastq);
+        assert_eq!(detect("reads"), OutputFormat::Fastq);
+        assert_eq!(detect("reads.cram"), OutputFormat::Cram);
+
+        assert_eq!(
+            OutputFormat::resolve(Some(OutputFormat::Sam), Path::new("reads.txt")),
+            OutputFormat::Sam
+        );
+        assert_eq!(OutputFormat::Fasta.extension(), "fasta");
+    }
+}
@@ -0,0 +1,140 @@
+//! GTF annotation reading.
+
+use super::is_gzip_compressed;
+use anyhow::{Context, Result, bail};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// A single GTF feature (1-based, closed coordinates as in the file).
+#[derive(Debug, Clone, PartialEq)]
+pub struct GtfRecord {
+    pub seqname: String,
+    pub feature: String,
+    pub start: usize,
+    pub end: usize,
+    pub strand: char,
+    /// Raw attribute column (`key "value"; key "value"; ...`)
+    pub attributes: String,
+}
+
+impl GtfRecord {
+    /// Returns the value of an attribute, without its quotes.
+    ///
+    /// # Arguments
+    /// * `key` - Attribute name, e.g. `transcript_id`
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .split(';')
+            .filter_map(|entry| entry.trim().split_once(char::is_whitespace))
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| value.trim().trim_matches('"'))
+    }
+}
+
+/// Reader for GTF files.
+pub struct GtfReader;
+
+impl GtfReader {
+    /// Reads all features from a GTF file.
+    ///
+    /// Automatically detects and handles both compressed (gzip/bgzf) and uncompressed files.
+    /// Comment lines are ignored.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the GTF file
+    ///
+    /// # Returns
+    /// Vector of all features in the file
+    pub fn read(path: &Path) -> Result<Vec<GtfRecord>> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open GTF file: {}", path.display()))?;
+
+        let mut buffered = BufReader::new(file);
+        let is_compressed = is_gzip_compressed(&mut buffered)?;
+
+        let reader: Box<dyn BufRead> = if is_compressed {
+            Box::new(BufReader::new(MultiGzDecoder::new(buffered)))
+        } else {
+            Box::new(buffered)
+        };
+
+        let mut records = Vec::new();
+        for (line_number, line) in reader.lines().enumerate() {
+            let line =
+                line.with_context(|| format!("Failed to read GTF file: {}", path.display()))?;
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            records.push(parse_line(line).with_context(|| {
+                format!(
+                    "Failed to parse GTF line {} in {}",
+                    line_number + 1,
+                    path.display()
+                )
+            })?);
+        }
+
+        if records.is_empty() {
+            bail!("No features found in GTF file: {}", path.display());
+        }
+
+        Ok(records)
+    }
+}
+
+/// Parses a single tab-separated GTF line.
+fn parse_line(line: &str) -> Result<GtfRecord> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() < 9 {
+        bail!("Expected 9 tab-separated columns, got {}", columns.len());
+    }
+
+    let start: usize = columns[3]
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid start position '{}'", columns[3]))?;
+    let end: usize = columns[4]
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid end position '{}'", columns[4]))?;
+
+    if start == 0 || end < start {
+        bail!("Invalid feature coordinates {}-{}", start, end);
+    }
+
+    Ok(GtfRecord {
+        seqname: columns[0].to_string(),
+        feature: columns[2].to_string(),
+        start,
+        end,
+        strand: columns[6].chars().next().unwrap_or('.'),
+        attributes: columns[8].to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let record = parse_line(
+            "chr1\tHAVANA\texon\t11869\t12227\t.\t+\t.\tgene_id \"G1\"; transcript_id \"T1.1\"; exon_number 1;",
+        )
+        .unwrap();
+        assert_eq!(record.seqname, "chr1");
+        assert_eq!(record.feature, "exon");
+        assert_eq!((record.start, record.end), (11869, 12227));
+        assert_eq!(record.strand, '+');
+        assert_eq!(record.attribute("transcript_id"), Some("T1.1"));
+        assert_eq!(record.attribute("exon_number"), Some("1"));
+        assert_eq!(record.attribute("gene_name"), None);
+
+        assert!(parse_line("chr1\tsrc\texon\t0\t10\t.\t+\t.\t").is_err());
+        assert!(parse_line("chr1 src exon 1 10 . + . x").is_err());
+    }
+}
@@ -0,0 +1,351 @@
+//! Live run output, written the way MinKNOW fills its output directory during a run.
+
+use crate::generator::SimulatedRead;
+use crate::io::{OutputFormat, ReadWriter};
+use crate::run::RunMetadata;
+use crate::utils::{MIN_QSCORE, mean_qscore};
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Writer of a live run directory.
+///
+/// Reads go into `fastq_pass/` or `fastq_fail/` (`bam_pass/`, ... for other formats) by their
+/// mean quality, which they carry in a dorado `qs` tag, in rolling chunks named as by MinKNOW:
+/// `<flow cell>_<pass|fail>_<run ID prefix>_<chunk>.fastq.gz`. A chunk is closed after a
+/// number of reads, or when the run time of any read reaches the next chunk interval. Chunks
+/// are written under a hidden name and renamed once complete, so that a watching tool never
+/// sees a partial file.
+///
+/// Writing can be paced to the run time of the reads, so that each read appears when it would
+/// have finished sequencing in a real run (or a sped-up one).
+pub struct LiveWriter {
+    files: ChunkFiles,
+    min_qscore: f32,
+    chunk_reads: usize,
+    chunk_seconds: Option<f64>,
+    speed: Option<f64>,
+    started: Instant,
+    pass: ChunkSeries,
+    fail: ChunkSeries,
+}
+
+/// Where and how chunk files are written.
+struct ChunkFiles {
+    directory: PathBuf,
+    format: OutputFormat,
+    run_metadata: RunMetadata,
+    /// Whether reads carry the run metadata in their headers
+    write_metadata: bool,
+    compression_threads: usize,
+}
+
+/// Rolling chunks of one output directory (such as `fastq_pass/` or `fastq_fail/`).
+struct ChunkSeries {
+    name: &'static str,
+    next_index: usize,
+    current: Option<Chunk>,
+}
+
+/// An open chunk file.
+struct Chunk {
+    writer: ReadWriter,
+    /// Hidden path the chunk is written to
+    partial_path: PathBuf,
+    path: PathBuf,
+    reads: usize,
+    /// Chunk interval the chunk belongs to, when chunks are also cut by time
+    interval: u64,
+}
+
+impl LiveWriter {
+    /// Creates the pass and fail directories of a live run, such as `fastq_pass/` and
+    /// `fastq_fail/`.
+    ///
+    /// # Arguments
+    /// * `directory` - Output directory of the run
+    /// * `format` - Format of the chunks; FASTQ, FASTA and SAM chunks are BGZF-compressed
+    /// * `run_metadata` - Metadata of the run, naming the chunks
+    /// * `write_metadata` - Whether reads carry the run metadata in their headers
+    /// * `compression_threads` - Number of compression threads (0 = auto-detect)
+    ///
+    /// # Errors
+    /// Returns an error if the directories cannot be created
+    pub fn new(
+        directory: &Path,
+        format: OutputFormat,
+        run_metadata: RunMetadata,
+        write_metadata: bool,
+        compression_threads: usize,
+    ) -> Result<Self> {
+        for name in ["pass", "fail"] {
+            let path = directory.join(format!("{}_{}", format.extension(), name));
+            std::fs::create_dir_all(&path)
+                .with_context(|| format!("Failed to create directory: {}", path.display()))?;
+        }
+        Ok(Self {
+            files: ChunkFiles {
+                directory: directory.to_path_buf(),
+                format,
+                run_metadata,
+                write_metadata,
+                compression_threads,
+            },
+            min_qscore: MIN_QSCORE,
+            chunk_reads: 4000,
+            chunk_seconds: None,
+            speed: None,
+            started: Instant::now(),
+            pass: ChunkSeries::new("pass"),
+            fail: ChunkSeries::new("fail"),
+        })
+    }
+
+    /// Sets the mean quality reads need to go into the pass directory (Q10 by default).
+    pub fn with_min_qscore(mut self, min_qscore: f32) -> Self {
+        self.min_qscore = min_qscore;
+        self
+    }
+
+    /// Sets the number of reads after which a chunk is closed (4000 by default, as MinKNOW).
+    ///
+    /// # Errors
+    /// Returns an error if the number of reads is zero
+    pub fn with_chunk_reads(mut self, chunk_reads: usize) -> Result<Self> {
+        if chunk_reads == 0 {
+            bail!("Chunks need at least one read");
+        }
+        self.chunk_reads = chunk_reads;
+        Ok(self)
+    }
+
+    /// Also closes chunks every given number of seconds of run time.
+    ///
+    /// # Errors
+    /// Returns an error if the interval is not positive
+    pub fn with_chunk_seconds(mut self, chunk_seconds: f64) -> Result<Self> {
+        if !(chunk_seconds > 0.0 && chunk_seconds.is_finite()) {
+            bail!(
+                "Chunk interval must be a positive number of seconds, got {}",
+                chunk_seconds
+            );
+        }
+        self.chunk_seconds = Some(chunk_seconds);
+        Ok(self)
+    }
+
+    /// Paces writing to the run time of the reads, sped up by a factor (1 for real time).
+    ///
+    /// # Errors
+    /// Returns an error if the speed is not positive
+    pub fn with_speed(mut self, speed: f64) -> Result<Self> {
+        if !(speed > 0.0 && speed.is_finite()) {
+            bail!("Live speed must be a positive number, got {}", speed);
+        }
+        self.speed = Some(speed);
+        Ok(self)
+    }
+
+    /// Writes a read into the pass or fail chunk it belongs to, waiting for its run time to
+    /// come if writing is paced.
+    ///
+    /// Reads without a timing are written straight away, in the first chunk interval.
+    ///
+    /// # Returns
+    /// The path of the complete chunk the read ends up in
+    pub fn write_read(&mut self, read: &SimulatedRead) -> Result<PathBuf> {
+        // Reads are written out once they finish sequencing
+        let end_time = read
+            .timing
+            .map_or(0.0, |timing| timing.start_time + timing.duration);
+        if let Some(speed) = self.speed {
+            let due = Duration::from_secs_f64(end_time / speed);
+            if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+        let interval = self
+            .chunk_seconds
+            .map_or(0, |seconds| (end_time / seconds) as u64);
+
+        // Chunks of past intervals are closed in both series, even if this read goes elsewhere
+        for series in [&mut self.pass, &mut self.fail] {
+            if series
+                .current
+                .as_ref()
+                .is_some_and(|chunk| chunk.interval < interval)
+            {
+                series.close()?;
+            }
+        }
+
+        let passes = mean_qscore(read.record.quality_scores()) >= self.min_qscore;
+        let series = if passes {
+            &mut self.pass
+        } else {
+            &mut self.fail
+        };
+        if series
+            .current
+            .as_ref()
+            .is_some_and(|chunk| chunk.reads >= self.chunk_reads)
+        {
+            series.close()?;
+        }
+        let chunk = match &mut series.current {
+            Some(chunk) => chunk,
+            None => series.open(&self.files, interval)?,
+        };
+        chunk.writer.write_read(read)?;
+        chunk.reads += 1;
+        Ok(chunk.path.clone())
+    }
+
+    /// Closes the open chunks, making them visible under their final names.
+    pub fn finish(mut self) -> Result<()> {
+        self.pass.close()?;
+        self.fail.close()
+    }
+}
+
+impl ChunkSeries {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            next_index: 0,
+            current: None,
+        }
+    }
+
+    /// Opens the next chunk of the series.
+    fn open(&mut self, files: &ChunkFiles, interval: u64) -> Result<&mut Chunk> {
+        let (format, run) = (files.format, &files.run_metadata);
+        let run_prefix: String = run.run_id.chars().take(8).collect();
+        let extension = match format {
+            OutputFormat::Bam | OutputFormat::Cram => format.extension().to_string(),
+            _ => format!("{}.gz", format.extension()),
+        };
+        let file_name = format!(
+            "{}_{}_{}_{}.{}",
+            run.flow_cell_id, self.name, run_prefix, self.next_index, extension
+        );
+        let series_directory =
+            files
+                .directory
+                .join(format!("{}_{}", format.extension(), self.name));
+        let partial_path = series_directory.join(format!(".{}", file_name));
+        let writer = ReadWriter::with_format(
+            &partial_path,
+            format,
+            files.compression_threads,
+            files.write_metadata.then(|| run.clone()),
+        )?
+        .with_qscore();
+        self.next_index += 1;
+        Ok(self.current.insert(Chunk {
+            writer,
+            partial_path,
+            path: series_directory.join(file_name),
+            reads: 0,
+            interval,
+        }))
+    }
+
+    /// Finishes the open chunk, if any, and moves it to its final name.
+    fn close(&mut self) -> Result<()> {
+        let Some(chunk) = self.current.take() else {
+            return Ok(());
+        };
+        chunk.writer.finish()?;
+        std::fs::rename(&chunk.partial_path, &chunk.path)
+            .with_context(|| format!("Failed to move chunk into place: {}", chunk.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::ReadTiming;
+    use noodles::fastq;
+
+    fn read(id: &str, qualities: &[u8], start_time: f64) -> SimulatedRead {
+        SimulatedRead {
+            record: fastq::Record::new(
+                fastq::record::Definition::new(id, ""),
+                vec![b'A'; qualities.len()],
+                qualities.to_vec(),
+            ),
+            sequence_id: "chr1".to_string(),
+            start: 0,
+            end: qualities.len(),
+            variants: Vec::new(),
+            haplotype: None,
+            clone: None,
+            repeats: Vec::new(),
+            modifications: Vec::new(),
+            reverse: false,
+            polya_length: None,
+            end_reason: None,
+            duplex: None,
+            parent_id: None,
+            partners: Vec::new(),
+            timing: Some(ReadTiming {
+                channel: 1,
+                mux: 1,
+                read_number: 1,
+                start_time,
+                duration: 1.0,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_live_chunks() {
+        let directory = std::env::temp_dir().join("readfaker_live_test");
+        std::fs::remove_dir_all(&directory).ok();
+        let mut run = RunMetadata::simulated(1);
+        run.run_id = "0123456789abcdef".to_string();
+        run.flow_cell_id = "FAX00001".to_string();
+        let mut writer = LiveWriter::new(&directory, OutputFormat::Fastq, run, false, 1)
+            .unwrap()
+            .with_chunk_reads(2)
+            .unwrap()
+            .with_chunk_seconds(60.0)
+            .unwrap();
+
+        let good = b"????";
+        let bad = b"((((";
+        // Chunks close after 2 reads, or past the end of a minute of run time
+        let mut paths: Vec<PathBuf> = [
+            read("a", good, 0.0),
+            read("b", bad, 1.0),
+            read("c", good, 2.0),
+            read("d", good, 3.0),
+            read("e", good, 70.0),
+        ]
+        .iter()
+        .map(|read| writer.write_read(read).unwrap())
+        .collect();
+        // The fail chunk of the first minute is closed by the pass read past it
+        assert!(paths[1].exists());
+        paths.push(writer.write_read(&read("f", bad, 80.0)).unwrap());
+        writer.finish().unwrap();
+
+        let name = |path: &PathBuf| path.file_name().unwrap().to_str().unwrap().to_string();
+        assert_eq!(name(&paths[0]), "FAX00001_pass_01234567_0.fastq.gz");
+        assert_eq!(paths[0].parent().unwrap(), directory.join("fastq_pass"));
+        assert_eq!(name(&paths[1]), "FAX00001_fail_01234567_0.fastq.gz");
+        assert_eq!(name(&paths[3]), "FAX00001_pass_01234567_1.fastq.gz");
+        assert_eq!(name(&paths[4]), "FAX00001_pass_01234567_2.fastq.gz");
+        assert_eq!(name(&paths[5]), "FAX00001_fail_01234567_1.fastq.gz");
+        for path in &paths {
+            assert!(path.exists());
+        }
+        // No partial chunks are left behind
+        let files = std::fs::read_dir(directory.join("fastq_pass"))
+            .unwrap()
+            .count();
+        std::fs::remove_dir_all(&directory).ok();
+        assert_eq!(files, 3);
+    }
+}
@@ -1,12 +1,49 @@
 //! I/O module for reading and writing sequence files.
 //!
-//! Provides readers and writers for FASTA, FASTQ, and BAM file formats.
+//! Provides readers and writers for FASTA, FASTQ, SAM, BAM, CRAM, BED, GTF and VCF file formats,
+//! transcript expression tables, the per-read truth table, the sequencing summary and the
+//! chunked output of live runs.
 
 pub mod bam;
+pub mod bed;
+pub mod cram;
+pub mod expression;
 pub mod fasta;
 pub mod fastq;
+pub mod format;
+pub mod gtf;
+pub mod live;
+pub mod output;
+pub mod sam;
+pub mod summary;
+pub mod truth;
+pub mod vcf;
 
 // Re-export main types
 pub use bam::{BamReader, BamWriter};
-pub use fasta::FastaReader;
+pub use bed::{BedReader, BedRecord};
+pub use cram::CramWriter;
+pub use expression::{ExpressionReader, ExpressionUnit};
+pub use fasta::{FastaReader, FastaWriter, IndexedFastaReader, Reference};
 pub use fastq::FastqWriter;
+pub use format::OutputFormat;
+pub use gtf::{GtfReader, GtfRecord};
+pub use live::LiveWriter;
+pub use output::ReadWriter;
+pub use sam::SamWriter;
+pub use summary::SequencingSummaryWriter;
+pub use truth::{ModificationTruthWriter, TruthWriter};
+pub use vcf::{VcfReader, VcfRecord, VcfWriter};
+
+/// Helper function to check if a file is gzip-compressed (including BGZF)
+pub(crate) fn is_gzip_compressed<R: std::io::Read>(
+    reader: &mut std::io::BufReader<R>,
+) -> anyhow::Result<bool> {
+    use anyhow::Context;
+    use std::io::BufRead;
+
+    let buffer = reader.fill_buf().context("Failed to read file header")?;
+
+    // Check for gzip magic bytes (0x1f 0x8b)
+    Ok(buffer.len() >= 2 && buffer[0] == 0x1f && buffer[1] == 0x8b)
+}
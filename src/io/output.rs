@@ -0,0 +1,359 @@
+//! Output writer selection for simulated reads.
+
+use crate::generator::SimulatedRead;
+use crate::io::{BamWriter, CramWriter, FastaWriter, FastqWriter, OutputFormat, SamWriter};
+use crate::models::methylation::format_mm_ml;
+use crate::run::{HeaderStyle, RunMetadata};
+use crate::utils::mean_qscore;
+use anyhow::Result;
+use noodles::sam;
+use noodles::sam::alignment::record::data::field::Tag;
+use noodles::sam::alignment::record_buf::data::field::Value;
+use noodles::sam::alignment::record_buf::data::field::value::Array;
+use std::path::PathBuf;
+
+/// Haplotype of origin, as used by phasing tools such as WhatsHap.
+const HAPLOTYPE_TAG: Tag = Tag::new(b'H', b'P');
+
+/// Base modifications, as written by basecallers such as dorado.
+const MODIFICATIONS_TAG: Tag = Tag::new(b'M', b'M');
+
+/// Probabilities of the base modifications in the `MM` tag.
+const MODIFICATION_PROBABILITIES_TAG: Tag = Tag::new(b'M', b'L');
+
+/// Poly-A tail length of transcript reads, as estimated by dorado.
+const POLYA_LENGTH_TAG: Tag = Tag::new(b'p', b't');
+
+/// Why sequencing of the read stopped, with the end reasons of MinKNOW.
+const END_REASON_TAG: Tag = Tag::new(b'e', b'r');
+
+/// Duplex role of the read, as written by dorado: 0 for simplex reads, -1 for simplex reads
+/// with a duplex offspring and 1 for duplex reads.
+const DUPLEX_TAG: Tag = Tag::new(b'd', b'x');
+
+/// ID of the parent read, here the template read of complement and duplex reads.
+const PARENT_ID_TAG: Tag = Tag::new(b'p', b'i');
+
+/// Mean quality of the read, computed from the mean error probability.
+const QSCORE_TAG: Tag = Tag::new(b'q', b's');
+
+/// Duration of the read in the pore, in seconds.
+const DURATION_TAG: Tag = Tag::new(b'd', b'u');
+
+/// Number of signal samples of the read.
+const NUM_SAMPLES_TAG: Tag = Tag::new(b'n', b's');
+
+/// Number of signal samples trimmed from the start of the read.
+const TRIMMED_SAMPLES_TAG: Tag = Tag::new(b't', b's');
+
+/// Well (mux) of the channel the read was sequenced in.
+const MUX_TAG: Tag = Tag::new(b'm', b'x');
+
+/// Channel the read was sequenced in.
+const CHANNEL_TAG: Tag = Tag::new(b'c', b'h');
+
+/// Start time of the read.
+const START_TIME_TAG: Tag = Tag::new(b's', b't');
+
+/// Read number within the run.
+const READ_NUMBER_TAG: Tag = Tag::new(b'r', b'n');
+
+/// Read group of the run, `<run ID>_<basecall model>`.
+const READ_GROUP_TAG: Tag = Tag::new(b'R', b'G');
+
+/// Writer for simulated reads, dispatching to the format chosen from the output path.
+pub struct ReadWriter {
+    output: Output,
+    run_metadata: Option<RunMetadata>,
+    /// Whether every read carries its mean quality in a `qs` tag
+    write_qscore: bool,
+}
+
+/// Output file of a [`ReadWriter`].
+enum Output {
+    Fastq(FastqWriter),
+    Fasta(FastaWriter),
+    Sam(Box<SamWriter>),
+    Bam(Box<BamWriter>),
+    Cram(Box<CramWriter>),
+}
+
+impl ReadWriter {
+    /// Creates a writer for the specified output path in the given format.
+    ///
+    /// SAM, BAM and CRAM records are unaligned; text formats are BGZF-compressed for
+    /// `.gz`/`.bgz` paths. With run metadata, SAM, BAM and CRAM files get a dorado read group
+    /// and every read the run fields of its header style.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the output file
+    /// * `format` - Output format
+    /// * `compression_threads` - Number of compression threads (0 = auto-detect)
+    /// * `run_metadata` - Metadata of the simulated run, if written
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be created
+    pub fn with_format(
+        path: &PathBuf,
+        format: OutputFormat,
+        compression_threads: usize,
+        run_metadata: Option<RunMetadata>,
+    ) -> Result<Self> {
+        let header = || {
+            let mut header = sam::Header::builder().add_comment("Generated by ReadFaker");
+            if let Some(run) = &run_metadata {
+                header = header.add_read_group(run.read_group_id(), run.read_group());
+            }
+            header.build()
+        };
+
+        let output = match format {
+            OutputFormat::Fastq => Output::Fastq(FastqWriter::new(path, compression_threads)?),
+            OutputFormat::Fasta => Output::Fasta(FastaWriter::new(path, compression_threads)?),
+            OutputFormat::Sam => Output::Sam(Box::new(SamWriter::with_header(
+                path,
+                compression_threads,
+                header(),
+            )?)),
+            OutputFormat::Bam => Output::Bam(Box::new(BamWriter::with_header(
+                path,
+                compression_threads,
+                header(),
+            )?)),
+            OutputFormat::Cram => Output::Cram(Box::new(CramWriter::with_header(path, header())?)),
+        };
+        Ok(Self {
+            output,
+            run_metadata,
+            write_qscore: false,
+        })
+    }
+
+    /// Writes the mean quality of every read in a dorado `qs` tag, as when reads are split
+    /// into pass and fail by it.
+    pub fn with_qscore(mut self) -> Self {
+        self.write_qscore = true;
+        self
+    }
+
+    /// Writes a single simulated read.
+    ///
+    /// Read annotations are written as SAM/BAM/CRAM tags, or as SAM-style `TAG:TYPE:VALUE` fields
+    /// in the FASTQ comment or FASTA description (which `minimap2 -y` carries over to the
+    /// alignments). Run metadata is written as dorado tags in SAM/BAM/CRAM files and in FASTQ and
+    /// FASTA files of the dorado header style, and as MinKNOW `key=value` fields ahead of the
+    /// tags otherwise.
+    pub fn write_read(&mut self, read: &SimulatedRead) -> Result<()> {
+        let mut tags = Vec::new();
+        let mut fields = Vec::new();
+        if let Some(run) = &self.run_metadata {
+            match (&self.output, run.header_style) {
+                (Output::Fastq(_) | Output::Fasta(_), HeaderStyle::Minknow) => {
+                    fields = minknow_fields(run, read)
+                }
+                _ => tags = dorado_tags(run, read),
+            }
+        }
+        if self.write_qscore && !tags.iter().any(|(tag, _)| *tag == QSCORE_TAG) {
+            tags.push(qscore_tag(read));
+        }
+        tags.extend(read_tags(read));
+        let record = &read.record;
+
+        match &mut self.output {
+            Output::Fastq(writer) if tags.is_empty() && fields.is_empty() => {
+                writer.write_record(record)
+            }
+            Output::Fastq(writer) => {
+                let mut record = record.clone();
+                *record.description_mut() = description(&fields, &tags).into();
+                writer.write_record(&record)
+            }
+            Output::Fasta(writer) => writer.write_record(
+                record.name(),
+                description(&fields, &tags).as_bytes(),
+                record.sequence(),
+            ),
+            Output::Sam(writer) => {
+                let name = std::str::from_utf8(record.name()).expect("UUID should be valid UTF-8");
+                writer.write_record(name, record.sequence(), record.quality_scores(), tags)
+            }
+            Output::Bam(writer) => {
+                let name = std::str::from_utf8(record.name()).expect("UUID should be valid UTF-8");
+                writer.write_record(name, record.sequence(), record.quality_scores(), tags)
+            }
+            Output::Cram(writer) => {
+                let name = std::str::from_utf8(record.name()).expect("UUID should be valid UTF-8");
+                writer.write_record(name, record.sequence(), record.quality_scores(), tags)
+            }
+        }
+    }
+
+    /// Finishes the underlying writer, finalizing compressed output.
+    pub fn finish(self) -> Result<()> {
+        match self.output {
+            Output::Fastq(writer) => writer.finish(),
+            Output::Fasta(writer) => writer.finish(),
+            Output::Sam(writer) => writer.finish(),
+            Output::Bam(writer) => writer.finish(),
+            Output::Cram(writer) => writer.finish(),
+        }
+    }
+}
+
+/// Joins MinKNOW fields (space-separated) and tags (tab-separated) into a FASTQ comment or
+/// FASTA description.
+fn description(fields: &[String], tags: &[(Tag, Value)]) -> String {
+    let tags = tags
+        .iter()
+        .map(|(tag, value)| format_tag(*tag, value))
+        .collect::<Vec<_>>()
+        .join("\t");
+    match (fields.is_empty(), tags.is_empty()) {
+        (true, _) => tags,
+        (false, true) => fields.join(" "),
+        (false, false) => format!("{}\t{}", fields.join(" "), tags),
+    }
+}
+
+/// Returns the MinKNOW FASTQ header fields of a read.
+fn minknow_fields(run: &RunMetadata, read: &SimulatedRead) -> Vec<String> {
+    let read_id = String::from_utf8_lossy(read.record.name());
+    let mut fields = vec![format!("runid={}", run.run_id)];
+    if let Some(timing) = &read.timing {
+        fields.push(format!("read={}", timing.read_number));
+        fields.push(format!("ch={}", timing.channel));
+        fields.push(format!("start_time={}", run.timestamp(timing.start_time)));
+    }
+    fields.extend([
+        format!("flow_cell_id={}", run.flow_cell_id),
+        format!("protocol_group_id={}", run.protocol_group_id),
+        format!("sample_id={}", run.sample_id),
+        format!(
+            "parent_read_id={}",
+            read.parent_id.as_deref().unwrap_or(&read_id)
+        ),
+        format!("basecall_model_version_id={}", run.basecall_model),
+    ]);
+    fields
+}
+
+/// Returns the dorado tags of a read.
+fn dorado_tags(run: &RunMetadata, read: &SimulatedRead) -> Vec<(Tag, Value)> {
+    let mut tags = vec![qscore_tag(read)];
+    if let Some(timing) = &read.timing {
+        tags.extend([
+            (DURATION_TAG, Value::Float(timing.duration as f32)),
+            (
+                NUM_SAMPLES_TAG,
+                Value::Int32((timing.duration * f64::from(run.sample_rate)).round() as i32),
+            ),
+            (TRIMMED_SAMPLES_TAG, Value::Int32(0)),
+            (MUX_TAG, Value::Int32(i32::from(timing.mux))),
+            (CHANNEL_TAG, Value::Int32(timing.channel as i32)),
+            (
+                START_TIME_TAG,
+                Value::String(run.timestamp(timing.start_time).into()),
+            ),
+            (READ_NUMBER_TAG, Value::Int32(timing.read_number as i32)),
+        ]);
+    }
+    tags.push((READ_GROUP_TAG, Value::String(run.read_group_id().into())));
+    tags
+}
+
+/// Returns the `qs` tag of a read, its mean quality computed as by dorado.
+fn qscore_tag(read: &SimulatedRead) -> (Tag, Value) {
+    (
+        QSCORE_TAG,
+        Value::Float(mean_qscore(read.record.quality_scores())),
+    )
+}
+
+/// Returns the SAM tags annotating a simulated read.
+fn read_tags(read: &SimulatedRead) -> Vec<(Tag, Value)> {
+    let mut tags = Vec::new();
+    if let Some(haplotype) = read.haplotype {
+        tags.push((HAPLOTYPE_TAG, Value::Int32(haplotype as i32)));
+    }
+    if let Some(length) = read.polya_length {
+        tags.push((POLYA_LENGTH_TAG, Value::Int32(length as i32)));
+    }
+    if let Some(end_reason) = read.end_reason {
+        tags.push((END_REASON_TAG, Value::String(end_reason.as_str().into())));
+    }
+    if let Some(duplex) = read.duplex {
+        tags.push((DUPLEX_TAG, Value::Int32(duplex.tag_value())));
+    }
+    if let Some(parent_id) = &read.parent_id {
+        tags.push((PARENT_ID_TAG, Value::String(parent_id.as_str().into())));
+    }
+    if !read.modifications.is_empty() {
+        let (mm, ml) = format_mm_ml(read.record.sequence(), &read.modifications);
+        tags.push((MODIFICATIONS_TAG, Value::String(mm.into())));
+        tags.push((
+            MODIFICATION_PROBABILITIES_TAG,
+            Value::Array(Array::UInt8(ml)),
+        ));
+    }
+    tags
+}
+
+/// Formats a tag as in SAM text (`TAG:TYPE:VALUE`).
+fn format_tag(tag: Tag, value: &Value) -> String {
+    let tag = String::from_utf8_lossy(tag.as_ref());
+    match value {
+        Value::Character(c) => format!("{}:A:{}", tag, *c as char),
+        Value::Int8(n) => format!("{}:i:{}", tag, n),
+        Value::UInt8(n) => format!("{}:i:{}", tag, n),
+        Value::Int16(n) => format!("{}:i:{}", tag, n),
+        Value::UInt16(n) => format!("{}:i:{}", tag, n),
+        Value::Int32(n) => format!("{}:i:{}", tag, n),
+        Value::UInt32(n) => format!("{}:i:{}", tag, n),
+        Value::Float(n) => format!("{}:f:{}", tag, n),
+        Value::String(s) => format!("{}:Z:{}", tag, s),
+        Value::Hex(s) => format!("{}:H:{}", tag, s),
+        Value::Array(array) => {
+            let (subtype, values) = match array {
+                Array::Int8(v) => ('c', join_values(v)),
+                Array::UInt8(v) => ('C', join_values(v)),
+                Array::Int16(v) => ('s', join_values(v)),
+                Array::UInt16(v) => ('S', join_values(v)),
+                Array::Int32(v) => ('i', join_values(v)),
+                Array::UInt32(v) => ('I', join_values(v)),
+                Array::Float(v) => ('f', join_values(v)),
+            };
+            format!("{}:B:{}{}", tag, subtype, values)
+        }
+    }
+}
+
+/// Formats array values with a leading comma each, as in SAM `B` fields.
+fn join_values<T: std::fmt::Display>(values: &[T]) -> String {
+    values.iter().map(|v| format!(",{}", v)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_tag() {
+        assert_eq!(format_tag(HAPLOTYPE_TAG, &Value::Int32(2)), "HP:i:2");
+        assert_eq!(
+            format_tag(Tag::new(b'R', b'G'), &Value::String("run1".into())),
+            "RG:Z:run1"
+        );
+        assert_eq!(
+            format_tag(Tag::new(b'q', b's'), &Value::Float(12.5)),
+            "qs:f:12.5"
+        );
+        assert_eq!(
+            format_tag(
+                Tag::new(b'M', b'L'),
+                &Value::Array(Array::UInt8(vec![3, 250]))
+            ),
+            "ML:B:C,3,250"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+//! SAM file writing.
+
+use super::format::OutputStream;
+use anyhow::{Context, Result};
+use noodles::sam;
+use noodles::sam::alignment::RecordBuf;
+use noodles::sam::alignment::record::data::field::Tag;
+use noodles::sam::alignment::record_buf::data::field::Value;
+use std::path::Path;
+
+/// Writer for unaligned SAM files, BGZF-compressed for `.gz`/`.bgz` paths.
+pub struct SamWriter {
+    writer: sam::io::Writer<OutputStream>,
+    header: sam::Header,
+}
+
+impl SamWriter {
+    /// Creates a new SAM writer with the given header.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the output SAM file
+    /// * `compression_threads` - Number of compression threads (0 = auto-detect)
+    /// * `header` - Header to write, e.g. with the read groups of the run
+    pub fn with_header(
+        path: &Path,
+        compression_threads: usize,
+        header: sam::Header,
+    ) -> Result<Self> {
+        let mut writer = sam::io::Writer::new(OutputStream::create(path, compression_threads)?);
+        writer
+            .write_header(&header)
+            .context("Failed to write SAM header")?;
+        Ok(Self { writer, header })
+    }
+
+    /// Writes a single unaligned SAM record.
+    ///
+    /// # Arguments
+    /// * `name` - Read name/identifier
+    /// * `sequence` - Nucleotide sequence
+    /// * `quality_ascii` - Quality scores in Phred+33 ASCII encoding
+    /// * `tags` - Auxiliary data fields to attach to the record
+    pub fn write_record(
+        &mut self,
+        name: &str,
+        sequence: &[u8],
+        quality_ascii: &[u8],
+        tags: Vec<(Tag, Value)>,
+    ) -> Result<()> {
+        use noodles::sam::alignment::io::Write as AlignmentWrite;
+
+        let record = unaligned_record(name, sequence, quality_ascii, tags);
+        self.writer
+            .write_alignment_record(&self.header, &record)
+            .context("Failed to write SAM record")
+    }
+
+    /// Finishes the writer, flushing the file and finalizing compressed output.
+    pub fn finish(self) -> Result<()> {
+        self.writer.into_inner().finish()
+    }
+}
+
+/// Builds an unaligned SAM record of a read.
+///
+/// # Arguments
+/// * `name` - Read name/identifier
+/// * `sequence` - Nucleotide sequence
+/// * `quality_ascii` - Quality scores in Phred+33 ASCII encoding
+/// * `tags` - Auxiliary data fields to attach to the record
+pub(crate) fn unaligned_record(
+    name: &str,
+    sequence: &[u8],
+    quality_ascii: &[u8],
+    tags: Vec<(Tag, Value)>,
+) -> RecordBuf {
+    // Convert Phred+33 ASCII to raw Phred scores (0-93)
+    let quality_phred: Vec<u8> = quality_ascii
+        .iter()
+        .map(|&q| q.saturating_sub(33))
+        .collect();
+
+    RecordBuf::builder()
+        .set_name(name.as_bytes())
+        .set_flags(sam::alignment::record::Flags::UNMAPPED)
+        .set_sequence(sequence.to_vec().into())
+        .set_quality_scores(quality_phred.into())
+        .set_data(tags.into_iter().collect())
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sam_writer() {
+        let path = std::env::temp_dir().join("readfaker_sam_test.sam");
+        let header = sam::Header::builder()
+            .add_comment("Generated by ReadFaker")
+            .build();
+        let mut writer = SamWriter::with_header(&path, 1, header).unwrap();
+        writer
+            .write_record(
+                "read1",
+                b"ACGT",
+                b"+5?I",
+                vec![(Tag::new(b'H', b'P'), Value::Int32(1))],
+            )
+            .unwrap();
+        writer.finish().unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(
+            contents,
+            "@CO\tGenerated by ReadFaker\nread1\t4\t*\t0\t255\t*\t*\t0\t0\tACGT\t+5?I\tHP:i:1\n"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Sequencing summary output.
+
+use crate::generator::SimulatedRead;
+use crate::io::OutputFormat;
+use crate::models::EndReason;
+use crate::run::RunMetadata;
+use crate::utils::{MIN_QSCORE, mean_qscore};
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Writer for a `sequencing_summary.txt` table as written by MinKNOW, read by QC tools such as
+/// pycoQC, NanoPlot or MinIONQC.
+///
+/// Columns are `filename_fastq` (or `filename_bam`), `read_id`, `parent_read_id`, `run_id`,
+/// `channel`, `mux`, `start_time` and `duration` (in seconds since the start of the run),
+/// `passes_filtering`, `template_start`, `template_duration`, `sequence_length_template`,
+/// `mean_qscore_template`, `barcode_arrangement` and `end_reason`.
+pub struct SequencingSummaryWriter {
+    writer: BufWriter<File>,
+    run_metadata: RunMetadata,
+    filename: String,
+    min_qscore: f32,
+}
+
+impl SequencingSummaryWriter {
+    /// Creates a sequencing summary at the specified path and writes its header.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the summary file
+    /// * `run_metadata` - Metadata of the run the reads belong to
+    /// * `reads_path` - Path to the file the reads are written to
+    /// * `format` - Format of the reads, which names the file column
+    pub fn new(
+        path: &Path,
+        run_metadata: RunMetadata,
+        reads_path: &Path,
+        format: OutputFormat,
+    ) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create sequencing summary: {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        let filename_column = match format {
+            OutputFormat::Bam | OutputFormat::Cram => "filename_bam",
+            _ => "filename_fastq",
+        };
+        writeln!(
+            writer,
+            "{}\tread_id\tparent_read_id\trun_id\tchannel\tmux\tstart_time\tduration\t\
+             passes_filtering\ttemplate_start\ttemplate_duration\tsequence_length_template\t\
+             mean_qscore_template\tbarcode_arrangement\tend_reason",
+            filename_column
+        )
+        .context("Failed to write sequencing summary header")?;
+
+        Ok(Self {
+            writer,
+            run_metadata,
+            filename: reads_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            min_qscore: MIN_QSCORE,
+        })
+    }
+
+    /// Sets the mean quality reads need to pass filtering (Q10 by default).
+    pub fn with_min_qscore(mut self, min_qscore: f32) -> Self {
+        self.min_qscore = min_qscore;
+        self
+    }
+
+    /// Writes the summary row of a simulated read.
+    ///
+    /// Reads without a timing are reported at the start of the run, on channel 1.
+    pub fn write_read(&mut self, read: &SimulatedRead) -> Result<()> {
+        write_row(
+            &mut self.writer,
+            &self.filename,
+            &self.run_metadata.run_id,
+            self.min_qscore,
+            read,
+        )
+    }
+
+    /// Writes the summary row of a read written to a file other than the main output, such
+    /// as a chunk of a live run.
+    ///
+    /// # Arguments
+    /// * `read` - Simulated read
+    /// * `reads_path` - Path to the file the read was written to
+    pub fn write_read_in(&mut self, read: &SimulatedRead, reads_path: &Path) -> Result<()> {
+        let filename = reads_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        write_row(
+            &mut self.writer,
+            &filename,
+            &self.run_metadata.run_id,
+            self.min_qscore,
+            read,
+        )
+    }
+
+    /// Flushes buffered rows to the file.
+    pub fn finish(mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .context("Failed to flush sequencing summary")
+    }
+}
+
+/// Writes the summary row of a read.
+fn write_row(
+    writer: &mut impl Write,
+    filename: &str,
+    run_id: &str,
+    min_qscore: f32,
+    read: &SimulatedRead,
+) -> Result<()> {
+    let read_id = String::from_utf8_lossy(read.record.name());
+    let (channel, mux, start_time, duration) = read.timing.map_or((1, 1, 0.0, 0.0), |t| {
+        (t.channel, t.mux, t.start_time, t.duration)
+    });
+    let qscore = mean_qscore(read.record.quality_scores());
+    writeln!(
+        writer,
+        "{}\t{}\t{}\t{}\t{}\t{}\t{:.6}\t{:.6}\t{}\t{:.6}\t{:.6}\t{}\t{:.6}\tunclassified\t{}",
+        filename,
+        read_id,
+        read.parent_id.as_deref().unwrap_or(&read_id),
+        run_id,
+        channel,
+        mux,
+        start_time,
+        duration,
+        if qscore >= min_qscore {
+            "TRUE"
+        } else {
+            "FALSE"
+        },
+        start_time,
+        duration,
+        read.record.sequence().len(),
+        qscore,
+        read.end_reason.unwrap_or(EndReason::SignalPositive)
+    )
+    .context("Failed to write sequencing summary record")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::ReadTiming;
+    use noodles::fastq;
+
+    #[test]
+    fn test_write_summary() {
+        let path = std::env::temp_dir().join("readfaker_summary_test.txt");
+        let read = SimulatedRead {
+            record: fastq::Record::new(
+                fastq::record::Definition::new("read1", ""),
+                b"ACGT".to_vec(),
+                b"5555".to_vec(),
+            ),
+            sequence_id: "chr1".to_string(),
+            start: 10,
+            end: 14,
+            variants: Vec::new(),
+            haplotype: None,
+            clone: None,
+            repeats: Vec::new(),
+            modifications: Vec::new(),
+            reverse: false,
+            polya_length: None,
+            end_reason: Some(EndReason::DataServiceUnblockMuxChange),
+            duplex: None,
+            parent_id: None,
+            partners: Vec::new(),
+            timing: Some(ReadTiming {
+                channel: 12,
+                mux: 3,
+                read_number: 5,
+                start_time: 61.5,
+                duration: 0.01,
+            }),
+        };
+        let mut run = RunMetadata::simulated(1);
+        run.run_id = "run1".to_string();
+
+        let mut writer = SequencingSummaryWriter::new(
+            &path,
+            run,
+            Path::new("out/reads.fastq.gz"),
+            OutputFormat::Fastq,
+        )
+        .unwrap();
+        writer.write_read(&read).unwrap();
+        writer.finish().unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert!(lines[0].starts_with("filename_fastq\tread_id\tparent_read_id\trun_id\tchannel"));
+        assert_eq!(
+            lines[1],
+            "reads.fastq.gz\tread1\tread1\trun1\t12\t3\t61.500000\t0.010000\tTRUE\t61.500000\t\
+             0.010000\t4\t20.000000\tunclassified\tdata_service_unblock_mux_change"
+        );
+    }
+}
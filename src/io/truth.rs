@@ -0,0 +1,178 @@
+//! Per-read truth output.
+
+use crate::generator::SimulatedRead;
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Writer for a tab-separated table describing the origin of every simulated read.
+///
+/// Columns are `read_id`, `sequence_id`, `start` and `end` (0-based, half-open, in
+/// reference or transcript coordinates), `strand` (`-` for reads that are the reverse
+/// complement of their sequence), `haplotype` (1-based, `.` for single-haplotype runs), `clone`
+/// (tumour clone or `normal`, `.` outside tumour simulations), `variants` (comma-separated
+/// labels, `.` if none) and `repeats` (copy numbers of the repeat alleles in the read as
+/// comma-separated `ID=COPIES`, `.` if none).
+pub struct TruthWriter {
+    writer: BufWriter<File>,
+}
+
+impl TruthWriter {
+    /// Creates a truth table at the specified path and writes its header.
+    pub fn new(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create truth file: {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(
+            writer,
+            "read_id\tsequence_id\tstart\tend\tstrand\thaplotype\tclone\tvariants\trepeats"
+        )
+        .context("Failed to write truth header")?;
+
+        Ok(Self { writer })
+    }
+
+    /// Writes the truth row of a simulated read.
+    pub fn write_read(&mut self, read: &SimulatedRead) -> Result<()> {
+        let variants = if read.variants.is_empty() {
+            ".".to_string()
+        } else {
+            read.variants.join(",")
+        };
+        let repeats = if read.repeats.is_empty() {
+            ".".to_string()
+        } else {
+            read.repeats
+                .iter()
+                .map(|(id, copies)| format!("{}={}", id, copies))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let haplotype = read
+            .haplotype
+            .map_or_else(|| ".".to_string(), |h| h.to_string());
+        writeln!(
+            self.writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            String::from_utf8_lossy(read.record.name()),
+            read.sequence_id,
+            read.start,
+            read.end,
+            if read.reverse { '-' } else { '+' },
+            haplotype,
+            read.clone.as_deref().unwrap_or("."),
+            variants,
+            repeats
+        )
+        .context("Failed to write truth record")
+    }
+
+    /// Flushes buffered rows to the file.
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush().context("Failed to flush truth file")
+    }
+}
+
+/// Writer for a tab-separated table of the base modification sites of every simulated read.
+///
+/// Columns are `read_id`, `position` (0-based, in the read), `sequence_id`,
+/// `reference_position` (0-based, `.` if unknown), `code`, `modified` (the true state, 0 or 1)
+/// and `probability` (the called probability, 0-255 as in `ML` tags).
+pub struct ModificationTruthWriter {
+    writer: BufWriter<File>,
+}
+
+impl ModificationTruthWriter {
+    /// Creates a modification truth table at the specified path and writes its header.
+    pub fn new(path: &Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| {
+            format!(
+                "Failed to create modification truth file: {}",
+                path.display()
+            )
+        })?;
+        let mut writer = BufWriter::new(file);
+        writeln!(
+            writer,
+            "read_id\tposition\tsequence_id\treference_position\tcode\tmodified\tprobability"
+        )
+        .context("Failed to write modification truth header")?;
+
+        Ok(Self { writer })
+    }
+
+    /// Writes the modification sites of a simulated read.
+    pub fn write_read(&mut self, read: &SimulatedRead) -> Result<()> {
+        let name = String::from_utf8_lossy(read.record.name());
+        for call in &read.modifications {
+            let reference_position = call
+                .reference_position
+                .map_or_else(|| ".".to_string(), |p| p.to_string());
+            writeln!(
+                self.writer,
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                name,
+                call.position,
+                read.sequence_id,
+                reference_position,
+                call.code,
+                u8::from(call.modified),
+                call.probability
+            )
+            .context("Failed to write modification truth record")?;
+        }
+        Ok(())
+    }
+
+    /// Flushes buffered rows to the file.
+    pub fn finish(mut self) -> Result<()> {
+        self.writer
+            .flush()
+            .context("Failed to flush modification truth file")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noodles::fastq;
+
+    #[test]
+    fn test_write_truth() {
+        let path = std::env::temp_dir().join("readfaker_truth_test.tsv");
+        let read = SimulatedRead {
+            record: fastq::Record::new(
+                fastq::record::Definition::new("read1", ""),
+                b"ACGT".to_vec(),
+                b"!!!!".to_vec(),
+            ),
+            sequence_id: "chr1".to_string(),
+            start: 10,
+            end: 14,
+            variants: vec!["rs1".to_string(), "rs2".to_string()],
+            haplotype: Some(2),
+            clone: Some("A".to_string()),
+            repeats: vec![("HTT".to_string(), 42)],
+            modifications: Vec::new(),
+            reverse: true,
+            polya_length: None,
+            end_reason: None,
+            duplex: None,
+            parent_id: None,
+            partners: Vec::new(),
+            timing: None,
+        };
+
+        let mut writer = TruthWriter::new(&path).unwrap();
+        writer.write_read(&read).unwrap();
+        writer.finish().unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(
+            contents,
+            "read_id\tsequence_id\tstart\tend\tstrand\thaplotype\tclone\tvariants\trepeats\nread1\tchr1\t10\t14\t-\t2\tA\trs1,rs2\tHTT=42\n"
+        );
+    }
+}
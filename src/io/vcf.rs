@@ -0,0 +1,235 @@
+//! VCF file reading and writing.
+
+use super::is_gzip_compressed;
+use anyhow::{Context, Result, bail};
+use flate2::read::MultiGzDecoder;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+
+/// A single VCF data line.
+///
+/// Only the fields needed to apply variants are interpreted; everything else is kept as text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VcfRecord {
+    pub chrom: String,
+    /// 1-based position, as in the file
+    pub position: usize,
+    pub id: Option<String>,
+    pub reference: String,
+    pub alternates: Vec<String>,
+    pub info: String,
+    pub format: Option<String>,
+    pub samples: Vec<String>,
+}
+
+impl VcfRecord {
+    /// Returns the value of an INFO key, or an empty string for flags.
+    pub fn info_value(&self, key: &str) -> Option<&str> {
+        self.info.split(';').find_map(|entry| {
+            let (k, v) = entry.split_once('=').unwrap_or((entry, ""));
+            (k == key).then_some(v)
+        })
+    }
+
+    /// Returns the value of a FORMAT key for the given sample.
+    pub fn sample_value(&self, sample_index: usize, key: &str) -> Option<&str> {
+        let position = self.format.as_ref()?.split(':').position(|k| k == key)?;
+        self.samples.get(sample_index)?.split(':').nth(position)
+    }
+
+    /// Returns the allele indices of a sample's genotype (`None` for missing alleles) and
+    /// whether the genotype is phased.
+    pub fn genotype(&self, sample_index: usize) -> Option<(Vec<Option<usize>>, bool)> {
+        let gt = self.sample_value(sample_index, "GT")?;
+        let phased = !gt.contains('/');
+        let alleles = gt
+            .split(['/', '|'])
+            .map(|allele| allele.parse().ok())
+            .collect();
+        Some((alleles, phased))
+    }
+}
+
+/// Reader for VCF files.
+pub struct VcfReader;
+
+impl VcfReader {
+    /// Reads all data lines from a VCF file.
+    ///
+    /// Automatically detects and handles both compressed (gzip/bgzf) and uncompressed files.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the VCF file
+    ///
+    /// # Returns
+    /// Tuple of (sample names, records)
+    pub fn read(path: &Path) -> Result<(Vec<String>, Vec<VcfRecord>)> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open VCF file: {}", path.display()))?;
+
+        // Check if file is gzip-compressed by reading magic bytes
+        let mut buffered = BufReader::new(file);
+        let is_compressed = is_gzip_compressed(&mut buffered)?;
+
+        let reader: Box<dyn BufRead> = if is_compressed {
+            Box::new(BufReader::new(MultiGzDecoder::new(buffered)))
+        } else {
+            Box::new(buffered)
+        };
+
+        let mut samples = Vec::new();
+        let mut records = Vec::new();
+        for (line_number, line) in reader.lines().enumerate() {
+            let line =
+                line.with_context(|| format!("Failed to read VCF file: {}", path.display()))?;
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with("##") {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('#') {
+                samples = header.split('\t').skip(9).map(str::to_string).collect();
+                continue;
+            }
+
+            records.push(parse_line(line).with_context(|| {
+                format!(
+                    "Failed to parse VCF line {} in {}",
+                    line_number + 1,
+                    path.display()
+                )
+            })?);
+        }
+
+        Ok((samples, records))
+    }
+}
+
+/// Writer for sites-only VCF files.
+pub struct VcfWriter {
+    writer: BufWriter<File>,
+}
+
+impl VcfWriter {
+    /// Creates a VCF file and writes its header.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the output file
+    /// * `contigs` - Names and lengths of the sequences, for `##contig` lines
+    /// * `meta_lines` - Additional meta-information lines (e.g. `##INFO=<...>`), without the `##`
+    pub fn new(path: &Path, contigs: &[(&str, usize)], meta_lines: &[&str]) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create VCF file: {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+
+        let mut header = String::from("##fileformat=VCFv4.2\n");
+        for (name, length) in contigs {
+            header.push_str(&format!("##contig=<ID={},length={}>\n", name, length));
+        }
+        for line in meta_lines {
+            header.push_str(&format!("##{}\n", line));
+        }
+        header.push_str("#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\n");
+        writer
+            .write_all(header.as_bytes())
+            .context("Failed to write VCF header")?;
+
+        Ok(Self { writer })
+    }
+
+    /// Writes the site columns of a record, with `PASS` as filter and no quality.
+    pub fn write_record(&mut self, record: &VcfRecord) -> Result<()> {
+        writeln!(
+            self.writer,
+            "{}\t{}\t{}\t{}\t{}\t.\tPASS\t{}",
+            record.chrom,
+            record.position,
+            record.id.as_deref().unwrap_or("."),
+            record.reference,
+            record.alternates.join(","),
+            if record.info.is_empty() {
+                "."
+            } else {
+                &record.info
+            }
+        )
+        .context("Failed to write VCF record")
+    }
+
+    /// Flushes buffered records to the file.
+    pub fn finish(mut self) -> Result<()> {
+        self.writer.flush().context("Failed to flush VCF file")
+    }
+}
+
+/// Parses a single tab-separated VCF data line.
+fn parse_line(line: &str) -> Result<VcfRecord> {
+    let columns: Vec<&str> = line.split('\t').collect();
+    if columns.len() < 8 {
+        bail!("Expected at least 8 columns, got {}", columns.len());
+    }
+
+    let position: usize = columns[1]
+        .parse()
+        .with_context(|| format!("Invalid position '{}'", columns[1]))?;
+    if position == 0 {
+        bail!("Position must be 1-based");
+    }
+
+    Ok(VcfRecord {
+        chrom: columns[0].to_string(),
+        position,
+        id: Some(columns[2].to_string()).filter(|id| id != "."),
+        reference: columns[3].to_string(),
+        alternates: columns[4].split(',').map(str::to_string).collect(),
+        info: columns[7].to_string(),
+        format: columns.get(8).map(|s| s.to_string()),
+        samples: columns.iter().skip(9).map(|s| s.to_string()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        let record =
+            parse_line("chr1\t100\trs1\tA\tG,T\t50\tPASS\tDP=10;SOMATIC\tGT:AF\t0|1:0.5").unwrap();
+        assert_eq!(record.chrom, "chr1");
+        assert_eq!(record.position, 100);
+        assert_eq!(record.id.as_deref(), Some("rs1"));
+        assert_eq!(record.alternates, vec!["G", "T"]);
+        assert_eq!(record.info_value("DP"), Some("10"));
+        assert_eq!(record.info_value("SOMATIC"), Some(""));
+        assert_eq!(record.info_value("END"), None);
+        assert_eq!(record.sample_value(0, "AF"), Some("0.5"));
+        assert_eq!(record.genotype(0), Some((vec![Some(0), Some(1)], true)));
+
+        let record = parse_line("chr1\t5\t.\tA\t<DEL>\t.\t.\tEND=20").unwrap();
+        assert_eq!(record.id, None);
+        assert_eq!(record.genotype(0), None);
+
+        assert!(parse_line("chr1\t0\t.\tA\tG\t.\t.\t.").is_err());
+    }
+
+    #[test]
+    fn test_write_and_read_back() {
+        let path = std::env::temp_dir().join("readfaker_vcf_test.vcf");
+        let record = parse_line("chr1\t5\tsv1\tA\t<DEL>\t.\t.\tSVTYPE=DEL;END=20").unwrap();
+
+        let mut writer = VcfWriter::new(
+            &path,
+            &[("chr1", 100)],
+            &["ALT=<ID=DEL,Description=\"Deletion\">"],
+        )
+        .unwrap();
+        writer.write_record(&record).unwrap();
+        writer.finish().unwrap();
+
+        let (samples, records) = VcfReader::read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!(samples.is_empty());
+        assert_eq!(records, vec![record]);
+    }
+}
@@ -1,5 +1,13 @@
+pub mod amplicons;
 pub mod cli;
 pub mod generator;
+pub mod genomes;
+pub mod haplotype;
 pub mod io;
 pub mod models;
+pub mod repeats;
+pub mod run;
+pub mod sv;
+pub mod transcriptome;
 pub mod utils;
+pub mod variants;
@@ -1,20 +1,98 @@
-use anyhow::Result;
+use anyhow::{Result, bail};
 use clap::Parser;
+use readfaker::amplicons::PrimerScheme;
 use readfaker::cli::{Cli, fmt};
 use readfaker::generator::ReadGenerator;
-use readfaker::io::{BamWriter, FastaReader, FastqWriter};
-use readfaker::models::ErrorModel;
-use readfaker::utils::load_models;
+use readfaker::genomes::{GenomeBuilder, apply_phase_weights};
+use readfaker::haplotype::Haplotype;
+use readfaker::io::{
+    BedReader, ExpressionReader, FastaReader, GtfReader, LiveWriter, ModificationTruthWriter,
+    OutputFormat, ReadWriter, Reference, SequencingSummaryWriter, TruthWriter, VcfReader,
+};
+use readfaker::models::{
+    AdaptiveSampling, AlphabetPolicy, CutSites, DuplexModel, MethylationModel, TargetRegions,
+};
+use readfaker::repeats::{RepeatAlleles, RepeatLocus};
+use readfaker::run::{MetadataSource, RunDrift, RunMetadata, RunTimeline};
+use readfaker::sv::{SvSimulator, read_sv_list};
+use readfaker::transcriptome::{RnaProtocol, Transcriptome, splice_transcripts};
+use readfaker::utils::{load_models, mean_qscore};
+
+/// Number of reads generated in parallel before being handed to the writer.
+const BATCH_SIZE: usize = 10_000;
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
+    if cli.shard.is_some() && cli.seed.is_none() {
+        bail!("--shard requires --seed so that all shards draw from the same run");
+    }
+    if cli.ploidy == 0 {
+        bail!("--ploidy must be at least 1");
+    }
+    if cli.ploidy > 1 && cli.vcf.is_none() && cli.somatic_vcf.is_none() {
+        bail!("--ploidy requires --vcf or --somatic-vcf");
+    }
+    let simulate_svs = cli.sv_list.is_some() || cli.random_svs > 0;
+    if (cli.vcf.is_some() || cli.somatic_vcf.is_some() || simulate_svs) && cli.indexed_reference {
+        bail!(
+            "--vcf, --somatic-vcf, --sv-list and --random-svs require the reference to be loaded into memory (without --indexed-reference)"
+        );
+    }
+    if cli.truth_vcf.is_some() && !simulate_svs && cli.somatic_vcf.is_none() {
+        bail!("--truth-vcf requires --sv-list, --random-svs or --somatic-vcf");
+    }
+    if cli.methylation_truth.is_some() && cli.methylation.is_empty() && cli.bedmethyl.is_none() {
+        bail!("--methylation-truth requires --methylation or --bedmethyl");
+    }
+    let rna_mode = cli.transcripts.is_some() || cli.gtf.is_some();
+    if cli.expression.is_some() && !rna_mode {
+        bail!("--expression requires --transcripts or --gtf");
+    }
+    if cli.rna_protocol == RnaProtocol::DirectRna && !rna_mode {
+        bail!("--rna-protocol direct-rna requires --transcripts or --gtf");
+    }
+    if cli.uracil && cli.rna_protocol != RnaProtocol::DirectRna {
+        bail!("--uracil requires --rna-protocol direct-rna");
+    }
+    if cli.duplex_fraction.is_some() && cli.rna_protocol == RnaProtocol::DirectRna {
+        bail!("Direct RNA molecules are single-stranded and cannot be read as duplex");
+    }
+    if cli.gtf.is_some() && cli.indexed_reference {
+        bail!(
+            "--gtf requires the reference to be loaded into memory (without --indexed-reference)"
+        );
+    }
+    let output_format = OutputFormat::resolve(cli.output_format, &cli.output);
+    let fail_format = cli
+        .fail_output
+        .as_deref()
+        .map(|path| OutputFormat::resolve(cli.output_format, path));
+    // Setup stages draw from the same seed as the reads, so pick one up front
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    let read_range = match cli.shard {
+        Some(shard) => shard.read_range(cli.num_reads),
+        None => 0..cli.num_reads,
+    };
+
     if cli.verbose {
         eprintln!("{}", fmt::header("ReadFaker Configuration"));
+        let transcripts = Vec::from_iter(cli.transcripts.clone());
+        let (label, paths) = if !transcripts.is_empty() {
+            ("Transcripts", &transcripts)
+        } else if cli.haplotype.is_empty() {
+            ("Reference", &cli.reference)
+        } else {
+            ("Haplotypes", &cli.haplotype)
+        };
         eprintln!(
             "{}: {}",
-            fmt::param_aligned("Reference", 16),
-            cli.reference.display()
+            fmt::param_aligned(label, 16),
+            paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
         );
         eprintln!(
             "{}: {}",
@@ -31,9 +109,163 @@ fn main() -> Result<()> {
             fmt::param_aligned("Number of reads", 16),
             cli.num_reads
         );
+        eprintln!(
+            "{}: {}",
+            fmt::param_aligned("Threads", 16),
+            if cli.threads == 0 {
+                "auto".to_string()
+            } else {
+                cli.threads.to_string()
+            }
+        );
         if let Some(seed) = cli.seed {
             eprintln!("{}: {}", fmt::param_aligned("Random seed", 16), seed);
         }
+        if let Some(vcf) = &cli.vcf {
+            eprintln!("{}: {}", fmt::param_aligned("Variants", 16), vcf.display());
+        }
+        if cli.ploidy > 1 {
+            eprintln!("{}: {}", fmt::param_aligned("Ploidy", 16), cli.ploidy);
+        }
+        if let Some(path) = &cli.somatic_vcf {
+            eprintln!(
+                "{}: {}",
+                fmt::param_aligned("Somatic variants", 16),
+                path.display()
+            );
+            eprintln!("{}: {}", fmt::param_aligned("Purity", 16), cli.purity);
+            if !cli.clones.is_empty() {
+                eprintln!(
+                    "{}: {}",
+                    fmt::param_aligned("Clones", 16),
+                    cli.clones
+                        .iter()
+                        .map(|clone| clone.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+        if let Some(path) = &cli.sv_list {
+            eprintln!("{}: {}", fmt::param_aligned("SV list", 16), path.display());
+        }
+        if cli.random_svs > 0 {
+            eprintln!(
+                "{}: {} ({}-{} bp)",
+                fmt::param_aligned("Random SVs", 16),
+                cli.random_svs,
+                cli.sv_min_size,
+                cli.sv_max_size
+            );
+        }
+        if let Some(path) = &cli.str_loci {
+            eprintln!("{}: {}", fmt::param_aligned("STR loci", 16), path.display());
+        }
+        if !cli.methylation.is_empty() {
+            eprintln!(
+                "{}: {}",
+                fmt::param_aligned("Methylation", 16),
+                cli.methylation
+                    .iter()
+                    .map(|motif| motif.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        if let Some(path) = &cli.bedmethyl {
+            eprintln!(
+                "{}: {}",
+                fmt::param_aligned("bedMethyl", 16),
+                path.display()
+            );
+        }
+        if let Some(path) = &cli.gtf {
+            eprintln!(
+                "{}: {}",
+                fmt::param_aligned("Annotation", 16),
+                path.display()
+            );
+        }
+        if let Some(path) = &cli.expression {
+            eprintln!(
+                "{}: {} ({:?})",
+                fmt::param_aligned("Expression", 16),
+                path.display(),
+                cli.expression_unit
+            );
+        }
+        if rna_mode {
+            eprintln!(
+                "{}: {:?}{}",
+                fmt::param_aligned("RNA protocol", 16),
+                cli.rna_protocol,
+                if cli.uracil { " (U alphabet)" } else { "" }
+            );
+            eprintln!(
+                "{}: {}",
+                fmt::param_aligned("Truncated", 16),
+                cli.truncated_fraction
+            );
+            eprintln!(
+                "{}: {}",
+                fmt::param_aligned("Poly-A length", 16),
+                cli.polya_length
+            );
+        }
+        if let Some(path) = &cli.primer_scheme {
+            eprintln!(
+                "{}: {} (depth SD {}, dropout {})",
+                fmt::param_aligned("Primer scheme", 16),
+                path.display(),
+                cli.amplicon_depth_sd,
+                cli.amplicon_dropout
+            );
+        }
+        if let Some(path) = &cli.cut_sites {
+            eprintln!(
+                "{}: {} (background {})",
+                fmt::param_aligned("Cut sites", 16),
+                path.display(),
+                cli.background_fraction
+            );
+        }
+        if let Some(path) = &cli.adaptive_sampling {
+            eprintln!(
+                "{}: {} (decision length {})",
+                fmt::param_aligned("Adaptive", 16),
+                path.display(),
+                cli.decision_length
+            );
+        }
+        if let Some(fraction) = cli.duplex_fraction {
+            eprintln!(
+                "{}: {} of molecules{}",
+                fmt::param_aligned("Duplex", 16),
+                fraction,
+                if cli.duplex_consensus {
+                    format!(", consensus +Q{}", cli.duplex_quality_boost)
+                } else {
+                    String::new()
+                }
+            );
+        }
+        if let Some(targets) = &cli.targets {
+            eprintln!(
+                "{}: {} ({:?})",
+                fmt::param_aligned("Targets", 16),
+                targets.display(),
+                cli.target_mode
+            );
+        }
+        if let Some(shard) = cli.shard {
+            eprintln!(
+                "{}: {} (reads {}-{})",
+                fmt::param_aligned("Shard", 16),
+                shard,
+                read_range.start,
+                read_range.end
+            );
+        }
         eprintln!();
     }
 
@@ -42,7 +274,7 @@ fn main() -> Result<()> {
     }
     let (length_model, quality_model) = load_models(&cli.input, cli.seed)?;
 
-    let error_model = ErrorModel::new(
+    let error_model = cli.rna_protocol.error_model(
         cli.error_sub,
         cli.error_ins,
         cli.error_del,
@@ -80,51 +312,419 @@ fn main() -> Result<()> {
         eprintln!();
     }
 
-    let mut generator = ReadGenerator::new(
-        FastaReader::read(&cli.reference)?,
+    let alphabet_policy = AlphabetPolicy::new(
+        Some(cli.soft_masked),
+        cli.soft_masked_error_factor,
+        Some(cli.iupac),
+        cli.max_n_fraction,
+    )?;
+
+    let mut haplotypes = load_haplotypes(&cli, seed)?;
+    if let Some(path) = &cli.str_loci {
+        let loci = RepeatLocus::from_bed(&BedReader::read(path)?, coordinates(&haplotypes))?;
+        let phases = haplotypes
+            .iter()
+            .filter_map(Haplotype::phase)
+            .max()
+            .unwrap_or(1);
+        if let Some(locus) = loci
+            .iter()
+            .find(|locus| locus.alleles.len() != 1 && locus.alleles.len() != phases)
+        {
+            bail!(
+                "Repeat locus {} has {} alleles but there are {} haplotypes",
+                locus.id,
+                locus.alleles.len(),
+                phases
+            );
+        }
+        haplotypes = haplotypes
+            .into_iter()
+            .map(|haplotype| {
+                let phase = haplotype.phase().map_or(0, |phase| phase - 1);
+                let repeats = RepeatAlleles::new(&loci, phase, haplotype.variants());
+                haplotype.with_repeats(repeats)
+            })
+            .collect();
+    }
+    if let Some(path) = &cli.targets {
+        let targets = TargetRegions::new(
+            &BedReader::read(path)?,
+            coordinates(&haplotypes),
+            cli.target_mode,
+            cli.target_weights,
+        )?;
+        haplotypes = haplotypes
+            .into_iter()
+            .map(|haplotype| {
+                let targets = match haplotype.variants() {
+                    Some(layout) => targets.on_haplotype(layout)?,
+                    None => targets.clone(),
+                };
+                Ok(haplotype.with_targets(targets))
+            })
+            .collect::<Result<_>>()?;
+    }
+
+    let primer_scheme = match &cli.primer_scheme {
+        Some(path) => {
+            let scheme = PrimerScheme::from_bed(&BedReader::read(path)?, coordinates(&haplotypes))?
+                .with_depth_variation(cli.amplicon_depth_sd, cli.amplicon_dropout, seed)?;
+            if cli.verbose {
+                eprintln!(
+                    "{}",
+                    fmt::progress(format!(
+                        "{} amplicons, {} dropped out{}",
+                        scheme.amplicons().len(),
+                        scheme.dropped().len(),
+                        if scheme.dropped().is_empty() {
+                            String::new()
+                        } else {
+                            format!(" ({})", scheme.dropped().join(", "))
+                        }
+                    ))
+                );
+            }
+            Some(scheme)
+        }
+        None => None,
+    };
+
+    let cut_sites = cli
+        .cut_sites
+        .as_deref()
+        .map(|path| {
+            CutSites::new(
+                &BedReader::read(path)?,
+                coordinates(&haplotypes),
+                cli.background_fraction,
+                cli.target_weights,
+            )
+        })
+        .transpose()?;
+
+    let adaptive_sampling = cli
+        .adaptive_sampling
+        .as_deref()
+        .map(|path| {
+            AdaptiveSampling::new(
+                &BedReader::read(path)?,
+                coordinates(&haplotypes),
+                cli.decision_length.clone(),
+            )
+        })
+        .transpose()?;
+
+    let duplex = match cli.duplex_fraction {
+        Some(fraction) => {
+            let model = DuplexModel::new(fraction, cli.duplex_quality_boost)?;
+            Some(if cli.duplex_consensus {
+                model.with_consensus()
+            } else {
+                model
+            })
+        }
+        None => None,
+    };
+
+    let methylation = if cli.methylation.is_empty() && cli.bedmethyl.is_none() {
+        None
+    } else {
+        let model = MethylationModel::new(cli.methylation.clone());
+        Some(match &cli.bedmethyl {
+            Some(path) => model.with_sites(&BedReader::read(path)?, coordinates(&haplotypes))?,
+            None => model,
+        })
+    };
+
+    let transcriptome = if rna_mode {
+        let expression = cli
+            .expression
+            .as_deref()
+            .map(|path| ExpressionReader::read(path, cli.expression_unit))
+            .transpose()?;
+        let transcriptome = Transcriptome::new(
+            haplotypes[0].reference(),
+            expression.as_ref(),
+            cli.truncated_fraction,
+            cli.polya_length.clone(),
+        )?
+        .with_protocol(cli.rna_protocol);
+        let transcriptome = if cli.uracil {
+            transcriptome.with_uracil()
+        } else {
+            transcriptome
+        };
+        if cli.verbose {
+            eprintln!(
+                "{}",
+                fmt::progress(format!(
+                    "{} of {} transcripts expressed",
+                    transcriptome.expressed(),
+                    haplotypes[0].reference().len()
+                ))
+            );
+        }
+        Some(transcriptome)
+    } else {
+        None
+    };
+
+    let run_metadata = match cli.run_metadata {
+        Some(MetadataSource::Simulated) => Some(RunMetadata::simulated(seed)),
+        Some(MetadataSource::Input) => Some(RunMetadata::from_input(&cli.input, seed)?),
+        None => None,
+    }
+    .map(|run| match cli.header_style {
+        Some(style) => run.with_header_style(style),
+        None => run,
+    });
+    if cli.verbose
+        && let Some(run) = &run_metadata
+    {
+        eprintln!(
+            "{}",
+            fmt::progress(format!(
+                "Run {} on flow cell {} ({:?} headers)",
+                run.run_id, run.flow_cell_id, run.header_style
+            ))
+        );
+    }
+    let drift = if cli.drift_from_input {
+        Some(RunDrift::from_input(&cli.input)?)
+    } else if cli.pore_half_life.is_some()
+        || cli.length_drift.is_some()
+        || cli.quality_drift.is_some()
+    {
+        Some(RunDrift::new(
+            cli.pore_half_life,
+            cli.length_drift.unwrap_or(0.0),
+            cli.quality_drift.unwrap_or(0.0),
+        )?)
+    } else {
+        None
+    };
+    if cli.verbose
+        && let Some(drift) = &drift
+    {
+        eprintln!(
+            "{}",
+            fmt::progress(format!(
+                "Pore half-life {}, length drift {:+.2}%/h, quality drift {:+.3} Q/h",
+                drift
+                    .pore_half_life
+                    .map_or("none".to_string(), |hours| format!("{:.1} h", hours)),
+                drift.length_drift * 100.0,
+                drift.quality_drift
+            ))
+        );
+    }
+    let timeline = if run_metadata.is_some()
+        || cli.sequencing_summary.is_some()
+        || drift.is_some()
+        || cli.live
+    {
+        let timeline = RunTimeline::new(
+            cli.channels,
+            cli.bases_per_second,
+            length_model.mean().unwrap_or(0.0),
+        )?;
+        Some(match drift {
+            Some(drift) => timeline.with_drift(drift),
+            None => timeline,
+        })
+    } else {
+        None
+    };
+
+    let mut generator = ReadGenerator::from_haplotypes(
+        haplotypes,
         length_model,
         quality_model,
         error_model,
-        cli.seed,
-    )?;
+        Some(seed),
+    )?
+    .with_alphabet_policy(alphabet_policy)
+    .with_too_long_policy(cli.too_long)
+    .with_max_attempts(cli.max_attempts);
+    if let Some(methylation) = methylation {
+        generator = generator.with_methylation(methylation);
+    }
+    if let Some(transcriptome) = transcriptome {
+        generator = generator.with_transcriptome(transcriptome);
+    }
+    if let Some(primer_scheme) = primer_scheme {
+        generator = generator.with_primer_scheme(primer_scheme);
+    }
+    if let Some(cut_sites) = cut_sites {
+        generator = generator.with_cut_sites(cut_sites);
+    }
+    if let Some(adaptive_sampling) = adaptive_sampling {
+        generator = generator.with_adaptive_sampling(adaptive_sampling);
+    }
+    if let Some(duplex) = duplex {
+        generator = generator.with_duplex(duplex);
+    }
+    if let Some(timeline) = timeline {
+        generator = generator.with_run_timeline(timeline);
+    }
 
-    // Detect output format based on extension
-    let output_ext = cli
-        .output
-        .extension()
-        .and_then(|s| s.to_str())
-        .unwrap_or("")
-        .to_lowercase();
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.threads)
+        .build_global()?;
 
     if cli.verbose {
         eprintln!(
             "{}",
-            fmt::progress(format!("Generating {} reads...", cli.num_reads))
+            fmt::progress(format!("Generating {} reads...", read_range.len()))
         );
     }
 
-    match output_ext.to_lowercase().as_str() {
-        "bam" => {
-            let mut writer = BamWriter::new(&cli.output, cli.compression_threads)?;
-            for _ in 0..cli.num_reads {
-                let read = generator.generate_read()?;
-                let name = std::str::from_utf8(read.name()).expect("UUID should be valid UTF-8");
-                writer.write_record(name, read.sequence(), read.quality_scores())?;
+    // Summaries and live chunks are named after a run, even without run metadata in the reads
+    let run = run_metadata
+        .clone()
+        .unwrap_or_else(|| RunMetadata::simulated(seed));
+    let mut live_writer = if cli.live {
+        let mut live_writer = LiveWriter::new(
+            &cli.output,
+            output_format,
+            run.clone(),
+            run_metadata.is_some(),
+            cli.compression_threads,
+        )?
+        .with_min_qscore(cli.min_qscore)
+        .with_chunk_reads(cli.chunk_reads)?;
+        if let Some(seconds) = cli.chunk_seconds {
+            live_writer = live_writer.with_chunk_seconds(seconds)?;
+        }
+        if let Some(speed) = cli.live_speed {
+            live_writer = live_writer.with_speed(speed)?;
+        }
+        Some(live_writer)
+    } else {
+        None
+    };
+    let mut summary_writer = cli
+        .sequencing_summary
+        .as_deref()
+        .map(|path| {
+            SequencingSummaryWriter::new(path, run, &cli.output, output_format)
+                .map(|writer| writer.with_min_qscore(cli.min_qscore))
+        })
+        .transpose()?;
+    // Reads split into pass and fail carry the mean quality they were split by
+    let mut fail_writer = cli
+        .fail_output
+        .as_ref()
+        .zip(fail_format)
+        .map(|(path, format)| {
+            ReadWriter::with_format(path, format, cli.compression_threads, run_metadata.clone())
+                .map(ReadWriter::with_qscore)
+        })
+        .transpose()?;
+    let mut writer = (!cli.live)
+        .then(|| {
+            ReadWriter::with_format(
+                &cli.output,
+                output_format,
+                cli.compression_threads,
+                run_metadata,
+            )
+        })
+        .transpose()?
+        .map(|writer| match cli.fail_output {
+            Some(_) => writer.with_qscore(),
+            None => writer,
+        });
+    let mut failed_reads = 0;
+    let mut truth_writer = cli.truth.as_deref().map(TruthWriter::new).transpose()?;
+    let mut methylation_writer = cli
+        .methylation_truth
+        .as_deref()
+        .map(ModificationTruthWriter::new)
+        .transpose()?;
+    generator.seek(read_range.start as u64)?;
+    let mut remaining = read_range.len();
+    while remaining > 0 {
+        let count = remaining.min(BATCH_SIZE);
+        for read in generator.generate_batch(count)? {
+            let failed =
+                fail_writer.is_some() && mean_qscore(read.record.quality_scores()) < cli.min_qscore;
+            if failed {
+                failed_reads += 1;
+            }
+            if let Some(writer) = if failed {
+                fail_writer.as_mut()
+            } else {
+                writer.as_mut()
+            } {
+                writer.write_read(&read)?;
+            }
+            let chunk = live_writer
+                .as_mut()
+                .map(|live_writer| live_writer.write_read(&read))
+                .transpose()?;
+            if let Some(truth_writer) = &mut truth_writer {
+                truth_writer.write_read(&read)?;
+            }
+            if let Some(methylation_writer) = &mut methylation_writer {
+                methylation_writer.write_read(&read)?;
             }
-            writer.finish()?;
-        }
-        _ => {
-            // Default to FASTQ for all other extensions
-            let mut writer = FastqWriter::new(&cli.output, cli.compression_threads)?;
-            for _ in 0..cli.num_reads {
-                let read = generator.generate_read()?;
-                writer.write_record(&read)?;
+            if let Some(summary_writer) = &mut summary_writer {
+                // Reads outside the output are listed with the file they went to
+                let destination = match &chunk {
+                    Some(chunk) => Some(chunk.as_path()),
+                    None => cli.fail_output.as_deref().filter(|_| failed),
+                };
+                match destination {
+                    Some(path) => summary_writer.write_read_in(&read, path)?,
+                    None => summary_writer.write_read(&read)?,
+                }
             }
-            writer.finish()?;
         }
+        remaining -= count;
+    }
+    if let Some(writer) = writer {
+        writer.finish()?;
+    }
+    if let Some(fail_writer) = fail_writer {
+        fail_writer.finish()?;
+    }
+    if let Some(live_writer) = live_writer {
+        live_writer.finish()?;
+    }
+    if let Some(truth_writer) = truth_writer {
+        truth_writer.finish()?;
+    }
+    if let Some(methylation_writer) = methylation_writer {
+        methylation_writer.finish()?;
+    }
+    if let Some(summary_writer) = summary_writer {
+        summary_writer.finish()?;
+    }
+
+    if generator.skipped_reads() > 0 {
+        eprintln!(
+            "{}",
+            fmt::warning(format!(
+                "Skipped {} reads longer than their reference sequence",
+                generator.skipped_reads()
+            ))
+        );
     }
 
     if cli.verbose {
+        if let Some(fail_output) = &cli.fail_output {
+            eprintln!(
+                "{}",
+                fmt::progress(format!(
+                    "{} reads with a mean quality below Q{} written to {}",
+                    failed_reads,
+                    cli.min_qscore,
+                    fail_output.display()
+                ))
+            );
+        }
         eprintln!(
             "{}",
             fmt::success(format!("Output written to {}", cli.output.display()))
@@ -133,3 +733,144 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Loads the haplotypes to draw reads from: one per `--haplotype` FASTA, one per ploidy
+/// level (and tumour clone) derived from the VCFs and structural variants, or the reference
+/// itself. Haplotype weights are applied per phase. In RNA mode the single haplotype holds
+/// the transcript sequences.
+///
+/// Also writes the truth VCF of the structural and somatic variants, if requested.
+fn load_haplotypes(cli: &Cli, seed: u64) -> Result<Vec<Haplotype>> {
+    let phases = if cli.haplotype.is_empty() {
+        cli.ploidy
+    } else {
+        cli.haplotype.len()
+    };
+    if let Some(weights) = &cli.haplotype_weights
+        && weights.len() != phases
+    {
+        bail!(
+            "--haplotype-weights has {} values but there are {} haplotypes",
+            weights.len(),
+            phases
+        );
+    }
+
+    if let Some(path) = &cli.transcripts {
+        let reference = Reference::from_paths(std::slice::from_ref(path), cli.indexed_reference)?;
+        return Ok(vec![Haplotype::new(reference)]);
+    }
+    if let Some(path) = &cli.gtf {
+        let genome = FastaReader::read_all(&cli.reference)?;
+        let transcripts = splice_transcripts(&GtfReader::read(path)?, &genome)?;
+        return Ok(vec![Haplotype::new(transcripts)]);
+    }
+
+    if !cli.haplotype.is_empty() {
+        let phased = cli.haplotype.len() > 1;
+        let haplotypes = cli
+            .haplotype
+            .iter()
+            .enumerate()
+            .map(|(i, path)| {
+                let reference =
+                    Reference::from_paths(std::slice::from_ref(path), cli.indexed_reference)?;
+                let haplotype = Haplotype::new(reference);
+                Ok(if phased {
+                    haplotype.with_phase(i + 1)
+                } else {
+                    haplotype
+                })
+            })
+            .collect::<Result<_>>()?;
+        return apply_phase_weights(haplotypes, cli.haplotype_weights.as_deref());
+    }
+
+    if cli.vcf.is_none()
+        && cli.somatic_vcf.is_none()
+        && cli.sv_list.is_none()
+        && cli.random_svs == 0
+    {
+        let reference = Reference::from_paths(&cli.reference, cli.indexed_reference)?;
+        return apply_phase_weights(
+            vec![Haplotype::new(reference)],
+            cli.haplotype_weights.as_deref(),
+        );
+    }
+
+    build_genomes(cli, seed)
+}
+
+/// Builds the haplotypes carrying germline variants, structural variants and (for tumour
+/// samples) the somatic variants of each clone, and writes the truth VCF if requested.
+fn build_genomes(cli: &Cli, seed: u64) -> Result<Vec<Haplotype>> {
+    let sequences = FastaReader::read_all(&cli.reference)?;
+
+    // Structural variants are shared by all haplotypes
+    let mut svs = match &cli.sv_list {
+        Some(path) => read_sv_list(&BedReader::read(path)?, &sequences, seed)?,
+        None => Vec::new(),
+    };
+    if cli.random_svs > 0 {
+        let simulator = SvSimulator::new(cli.sv_types.clone(), cli.sv_min_size, cli.sv_max_size)?;
+        let random_svs = simulator.simulate(cli.random_svs, &sequences, &svs, seed)?;
+        svs.extend(random_svs);
+    }
+
+    let mut builder = GenomeBuilder::new(sequences, cli.ploidy).with_svs(svs);
+    if let Some(sample) = &cli.sample {
+        builder = builder.with_sample(sample);
+    }
+    if let Some(vcf) = &cli.vcf {
+        builder = builder.with_germline(VcfReader::read(vcf)?);
+    }
+    if let Some(sample) = &cli.somatic_sample {
+        builder = builder.with_somatic_sample(sample);
+    }
+    if let Some(vcf) = &cli.somatic_vcf {
+        builder = builder.with_somatic(VcfReader::read(vcf)?, &cli.clones, cli.purity)?;
+    }
+    if let Some(weights) = &cli.haplotype_weights {
+        builder = builder.with_phase_weights(weights.clone());
+    }
+    let genomes = builder.build()?;
+
+    if genomes.unphased > 0 {
+        eprintln!(
+            "{}",
+            fmt::warning(format!(
+                "{} heterozygous genotypes are unphased; their alleles are assigned to haplotypes in the order listed",
+                genomes.unphased
+            ))
+        );
+    }
+    if genomes.unsupported > 0 {
+        eprintln!(
+            "{}",
+            fmt::warning(format!(
+                "Ignored {} unsupported variants",
+                genomes.unsupported
+            ))
+        );
+    }
+    if genomes.overlapping > 0 {
+        eprintln!(
+            "{}",
+            fmt::warning(format!(
+                "Ignored {} variants overlapping a previous variant",
+                genomes.overlapping
+            ))
+        );
+    }
+    if let Some(path) = &cli.truth_vcf {
+        genomes.write_truth_vcf(path)?;
+    }
+
+    Ok(genomes.haplotypes)
+}
+
+/// Returns the sequences BED coordinates refer to: the reference the variants of the
+/// haplotypes were applied to, or the sequences of the first haplotype otherwise.
+fn coordinates(haplotypes: &[Haplotype]) -> &Reference {
+    haplotypes[0].reference().unedited()
+}
@@ -0,0 +1,164 @@
+use crate::io::bed::BedRecord;
+use crate::io::fasta::Reference;
+use crate::repeats::CopyNumber;
+use anyhow::{Result, bail};
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Why the sequencing of a read stopped, as reported by MinKNOW.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndReason {
+    /// The molecule was read to its end
+    SignalPositive,
+    /// The molecule was ejected by adaptive sampling
+    DataServiceUnblockMuxChange,
+}
+
+impl EndReason {
+    /// Returns the name MinKNOW gives to the end reason.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EndReason::SignalPositive => "signal_positive",
+            EndReason::DataServiceUnblockMuxChange => "data_service_unblock_mux_change",
+        }
+    }
+}
+
+impl Display for EndReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Adaptive sampling (read-until) against a set of target regions.
+///
+/// The sequencer decides on every molecule after reading its first bases: molecules whose
+/// start maps to a target are read to the end, and the rest are ejected from the pore, which
+/// leaves a short rejected read.
+pub struct AdaptiveSampling {
+    /// Merged target intervals per sequence, sorted by start
+    regions: Vec<Vec<(usize, usize)>>,
+    decision_length: CopyNumber,
+}
+
+impl AdaptiveSampling {
+    /// Builds the adaptive sampling targets from BED intervals.
+    ///
+    /// # Arguments
+    /// * `records` - Target intervals
+    /// * `reference` - Reference the intervals refer to
+    /// * `decision_length` - Number of bases read before an off-target molecule is ejected
+    ///
+    /// # Errors
+    /// Returns an error if an interval refers to an unknown sequence or extends past its end
+    pub fn new(
+        records: &[BedRecord],
+        reference: &Reference,
+        decision_length: CopyNumber,
+    ) -> Result<Self> {
+        let sequence_indices: HashMap<&str, usize> = (0..reference.len())
+            .map(|i| (reference.sequence_id(i), i))
+            .collect();
+
+        let mut regions = vec![Vec::new(); reference.len()];
+        for record in records {
+            let region = format!("{}:{}-{}", record.chrom, record.start, record.end);
+            let Some(&sequence_index) = sequence_indices.get(record.chrom.as_str()) else {
+                bail!(
+                    "Target region {} refers to a sequence not in the reference",
+                    region
+                );
+            };
+            let sequence_length = reference.sequence_length(sequence_index);
+            if record.end > sequence_length {
+                bail!(
+                    "Target region {} extends past the sequence end ({} bp)",
+                    region,
+                    sequence_length
+                );
+            }
+            regions[sequence_index].push((record.start, record.end));
+        }
+
+        for intervals in &mut regions {
+            intervals.sort_unstable();
+            let mut merged: Vec<(usize, usize)> = Vec::with_capacity(intervals.len());
+            for &(start, end) in intervals.iter() {
+                match merged.last_mut() {
+                    Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                    _ => merged.push((start, end)),
+                }
+            }
+            *intervals = merged;
+        }
+
+        Ok(Self {
+            regions,
+            decision_length,
+        })
+    }
+
+    /// Draws the number of bases read from a molecule before a decision is made on it.
+    pub fn decision_length<R: Rng>(&self, rng: &mut R) -> usize {
+        self.decision_length.sample(rng).max(1)
+    }
+
+    /// Returns true if an interval overlaps a target region.
+    ///
+    /// # Arguments
+    /// * `sequence_index` - Sequence of the interval
+    /// * `start` - Zero-based start of the interval
+    /// * `end` - Zero-based, exclusive end of the interval
+    pub fn is_on_target(&self, sequence_index: usize, start: usize, end: usize) -> bool {
+        let intervals = &self.regions[sequence_index];
+        // First interval ending after the start, the only candidate for an overlap
+        let idx = intervals.partition_point(|&(_, region_end)| region_end <= start);
+        intervals
+            .get(idx)
+            .is_some_and(|&(region_start, _)| region_start < end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::fasta::FastaRecord;
+
+    #[test]
+    fn test_on_target_decisions() {
+        let reference = Reference::from(vec![
+            FastaRecord {
+                id: "chr1".to_string(),
+                sequence: vec![b'A'; 1000],
+            },
+            FastaRecord {
+                id: "chr2".to_string(),
+                sequence: vec![b'A'; 1000],
+            },
+        ]);
+        let region = |start, end| BedRecord {
+            chrom: "chr1".to_string(),
+            start,
+            end,
+            fields: Vec::new(),
+        };
+        let adaptive = AdaptiveSampling::new(
+            &[region(500, 600), region(100, 200), region(150, 250)],
+            &reference,
+            CopyNumber::Fixed(400),
+        )
+        .unwrap();
+
+        assert!(adaptive.is_on_target(0, 0, 101));
+        assert!(!adaptive.is_on_target(0, 0, 100));
+        assert!(adaptive.is_on_target(0, 240, 260));
+        assert!(!adaptive.is_on_target(0, 250, 500));
+        assert!(adaptive.is_on_target(0, 599, 1000));
+        assert!(!adaptive.is_on_target(1, 0, 1000));
+
+        assert!(
+            AdaptiveSampling::new(&[region(900, 1100)], &reference, CopyNumber::Fixed(1)).is_err()
+        );
+    }
+}
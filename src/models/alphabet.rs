@@ -0,0 +1,216 @@
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+const MAX_N_FRACTION_DEFAULT: f64 = 0.1;
+
+/// How soft-masked (lowercase) reference bases are written to reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum SoftMaskPolicy {
+    /// Convert soft-masked bases to uppercase
+    #[default]
+    Uppercase,
+    /// Keep soft-masked bases in lowercase
+    Keep,
+}
+
+/// How IUPAC ambiguity codes (including N) in the reference are written to reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum IupacPolicy {
+    /// Replace each ambiguity code with a random base compatible with it
+    #[default]
+    Resolve,
+    /// Keep ambiguity codes as they are
+    Keep,
+}
+
+/// Policy for handling the reference alphabet when extracting read templates.
+///
+/// Reference windows with too many `N` bases are rejected; soft-masked and ambiguous bases
+/// in accepted windows are normalized according to the configured policies.
+#[derive(Debug, Clone)]
+pub struct AlphabetPolicy {
+    pub soft_mask: SoftMaskPolicy,
+    /// Multiplier applied to the error probability of soft-masked positions
+    pub soft_mask_error_factor: f64,
+    pub iupac: IupacPolicy,
+    /// Maximum fraction of `N` bases allowed in a sampled reference window
+    pub max_n_fraction: f64,
+}
+
+impl Default for AlphabetPolicy {
+    fn default() -> Self {
+        Self {
+            soft_mask: SoftMaskPolicy::default(),
+            soft_mask_error_factor: 1.0,
+            iupac: IupacPolicy::default(),
+            max_n_fraction: MAX_N_FRACTION_DEFAULT,
+        }
+    }
+}
+
+impl AlphabetPolicy {
+    /// Creates a new alphabet policy with specified or default settings.
+    ///
+    /// # Arguments
+    /// * `soft_mask` - Handling of soft-masked bases (default: uppercase)
+    /// * `soft_mask_error_factor` - Error probability multiplier at soft-masked positions (default: 1.0)
+    /// * `iupac` - Handling of ambiguity codes (default: resolve)
+    /// * `max_n_fraction` - Maximum fraction of `N` bases per window (default: 0.1)
+    ///
+    /// # Errors
+    /// Returns an error if the error factor is negative or the N fraction is outside [0.0, 1.0]
+    pub fn new(
+        soft_mask: Option<SoftMaskPolicy>,
+        soft_mask_error_factor: Option<f64>,
+        iupac: Option<IupacPolicy>,
+        max_n_fraction: Option<f64>,
+    ) -> Result<Self> {
+        let default = Self::default();
+        let soft_mask_error_factor =
+            soft_mask_error_factor.unwrap_or(default.soft_mask_error_factor);
+        let max_n_fraction = max_n_fraction.unwrap_or(default.max_n_fraction);
+
+        if !(0.0..).contains(&soft_mask_error_factor) {
+            bail!(
+                "Soft-masked error factor must be non-negative, got {}",
+                soft_mask_error_factor
+            );
+        }
+        if !(0.0..=1.0).contains(&max_n_fraction) {
+            bail!(
+                "Maximum N fraction must be between 0.0 and 1.0, got {}",
+                max_n_fraction
+            );
+        }
+
+        Ok(Self {
+            soft_mask: soft_mask.unwrap_or(default.soft_mask),
+            soft_mask_error_factor,
+            iupac: iupac.unwrap_or(default.iupac),
+            max_n_fraction,
+        })
+    }
+
+    /// Returns true if the window has an acceptable fraction of `N` bases.
+    pub fn accepts(&self, window: &[u8]) -> bool {
+        if window.is_empty() {
+            return true;
+        }
+        let n_count = window
+            .iter()
+            .filter(|b| b.eq_ignore_ascii_case(&b'N'))
+            .count();
+        n_count as f64 / window.len() as f64 <= self.max_n_fraction
+    }
+
+    /// Normalizes a reference window in place according to the policy.
+    ///
+    /// # Arguments
+    /// * `window` - Reference bases to normalize
+    /// * `rng` - Random number generator for resolving ambiguity codes
+    ///
+    /// # Returns
+    /// Per-base flags marking which positions were soft-masked in the reference
+    pub fn normalize(&self, window: &mut [u8], rng: &mut impl Rng) -> Vec<bool> {
+        let mut soft_masked = Vec::with_capacity(window.len());
+
+        for base in window.iter_mut() {
+            let is_lower = base.is_ascii_lowercase();
+            soft_masked.push(is_lower);
+
+            let mut upper = base.to_ascii_uppercase();
+            if self.iupac == IupacPolicy::Resolve {
+                upper = resolve_iupac(upper, rng);
+            }
+
+            *base = if is_lower && self.soft_mask == SoftMaskPolicy::Keep {
+                upper.to_ascii_lowercase()
+            } else {
+                upper
+            };
+        }
+
+        soft_masked
+    }
+}
+
+/// Resolves an uppercase IUPAC code to one of the bases it represents.
+///
+/// Canonical bases are returned unchanged, `U` is read as `T`, and unknown symbols
+/// are treated like `N`.
+fn resolve_iupac(base: u8, rng: &mut impl Rng) -> u8 {
+    let choices: &[u8] = match base {
+        b'A' | b'C' | b'G' | b'T' => return base,
+        b'U' => return b'T',
+        b'R' => b"AG",
+        b'Y' => b"CT",
+        b'S' => b"CG",
+        b'W' => b"AT",
+        b'K' => b"GT",
+        b'M' => b"AC",
+        b'B' => b"CGT",
+        b'D' => b"AGT",
+        b'H' => b"ACT",
+        b'V' => b"ACG",
+        _ => b"ACGT",
+    };
+    *choices.choose(rng).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_default_policy() {
+        let policy = AlphabetPolicy::default();
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut window = b"ACgtRN".to_vec();
+
+        let soft_masked = policy.normalize(&mut window, &mut rng);
+
+        assert_eq!(&window[..4], b"ACGT");
+        assert!(b"AG".contains(&window[4]));
+        assert!(b"ACGT".contains(&window[5]));
+        assert_eq!(soft_masked, vec![false, false, true, true, false, false]);
+    }
+
+    #[test]
+    fn test_keep_policies() {
+        let policy = AlphabetPolicy::new(
+            Some(SoftMaskPolicy::Keep),
+            None,
+            Some(IupacPolicy::Keep),
+            None,
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut window = b"ACgtRyN".to_vec();
+
+        policy.normalize(&mut window, &mut rng);
+
+        assert_eq!(window, b"ACgtRyN");
+    }
+
+    #[test]
+    fn test_resolve_iupac_is_compatible() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..100 {
+            assert!(b"CT".contains(&resolve_iupac(b'Y', &mut rng)));
+            assert!(b"ACG".contains(&resolve_iupac(b'V', &mut rng)));
+        }
+        assert_eq!(resolve_iupac(b'U', &mut rng), b'T');
+    }
+
+    #[test]
+    fn test_n_fraction_threshold() {
+        let policy = AlphabetPolicy::new(None, None, None, Some(0.25)).unwrap();
+        assert!(policy.accepts(b"ACGN"));
+        assert!(!policy.accepts(b"ACnN"));
+        assert!(AlphabetPolicy::new(None, None, None, Some(1.5)).is_err());
+    }
+}
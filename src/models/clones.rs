@@ -0,0 +1,342 @@
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Name of the clone founding the tumour, which every tumour cell descends from.
+pub const FOUNDING_CLONE: &str = "founder";
+
+/// Name reserved for the normal cells of a tumour sample.
+pub const NORMAL: &str = "normal";
+
+/// VCF header lines describing the INFO fields of somatic truth records.
+pub const VCF_META_LINES: &[&str] = &[
+    "INFO=<ID=SOMATIC,Number=0,Type=Flag,Description=\"Somatic variant\">",
+    "INFO=<ID=CLONE,Number=1,Type=String,Description=\"Clone in which the variant arose\">",
+    "INFO=<ID=VAF,Number=A,Type=Float,Description=\"Expected variant allele fraction in the sample\">",
+];
+
+/// Tolerance used when comparing cell fractions.
+const EPSILON: f64 = 1e-9;
+
+/// A subclone definition, written as `NAME:CCF[:PARENT]`.
+///
+/// The cancer cell fraction (CCF) is the share of tumour cells carrying the clone's
+/// variants, including the cells of its descendants.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CloneSpec {
+    pub name: String,
+    pub cancer_cell_fraction: f64,
+    /// Parent clone (the founding clone if None)
+    pub parent: Option<String>,
+}
+
+impl FromStr for CloneSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let name = parts.next().unwrap_or_default().trim();
+        let cancer_cell_fraction = parts
+            .next()
+            .ok_or_else(|| format!("expected NAME:CCF[:PARENT], got '{}'", s))?;
+        let cancer_cell_fraction: f64 = cancer_cell_fraction
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid cancer cell fraction '{}'", cancer_cell_fraction))?;
+        let parent = parts.next().map(|p| p.trim().to_string());
+        if parts.next().is_some() || name.is_empty() {
+            return Err(format!("expected NAME:CCF[:PARENT], got '{}'", s));
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            cancer_cell_fraction,
+            parent,
+        })
+    }
+}
+
+impl Display for CloneSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.name, self.cancer_cell_fraction)?;
+        if let Some(parent) = &self.parent {
+            write!(f, ":{}", parent)?;
+        }
+        Ok(())
+    }
+}
+
+/// Clonal structure of a tumour sample.
+///
+/// Clones form a tree rooted at the founding clone. Each clone carries its own somatic
+/// variants plus those of its ancestors, and the sample mixes normal cells with the cells of
+/// every clone in proportion to the purity and the clone cell fractions.
+#[derive(Debug, Clone)]
+pub struct CloneTree {
+    /// Clone names, the founding clone first
+    names: Vec<String>,
+    /// Parent index of each clone (None for the founding clone)
+    parents: Vec<Option<usize>>,
+    /// Cancer cell fraction of each clone
+    cancer_cell_fractions: Vec<f64>,
+    purity: f64,
+}
+
+impl CloneTree {
+    /// Creates the clonal structure of a tumour sample.
+    ///
+    /// # Arguments
+    /// * `subclones` - Subclone definitions; parents must be defined before their children
+    /// * `purity` - Fraction of tumour cells in the sample
+    ///
+    /// # Errors
+    /// Returns an error if the purity or a cell fraction is outside (0, 1], a parent is
+    /// unknown, a name is repeated or reserved, or the children of a clone add up to more
+    /// than its own cell fraction
+    pub fn new(subclones: &[CloneSpec], purity: f64) -> Result<Self> {
+        if !(purity > 0.0 && purity <= 1.0) {
+            bail!("Purity must be in (0, 1], got {}", purity);
+        }
+
+        let mut names = vec![FOUNDING_CLONE.to_string()];
+        let mut parents = vec![None];
+        let mut cancer_cell_fractions = vec![1.0];
+        let mut indices = HashMap::from([(FOUNDING_CLONE.to_string(), 0)]);
+
+        for spec in subclones {
+            if spec.name == NORMAL || indices.contains_key(&spec.name) {
+                bail!("Clone name '{}' is reserved or already used", spec.name);
+            }
+            let parent = spec.parent.as_deref().unwrap_or(FOUNDING_CLONE);
+            let Some(&parent_index) = indices.get(parent) else {
+                bail!(
+                    "Parent '{}' of clone '{}' must be defined before it",
+                    parent,
+                    spec.name
+                );
+            };
+            if !(spec.cancer_cell_fraction > 0.0 && spec.cancer_cell_fraction <= 1.0) {
+                bail!(
+                    "Cancer cell fraction of clone '{}' must be in (0, 1], got {}",
+                    spec.name,
+                    spec.cancer_cell_fraction
+                );
+            }
+
+            indices.insert(spec.name.clone(), names.len());
+            names.push(spec.name.clone());
+            parents.push(Some(parent_index));
+            cancer_cell_fractions.push(spec.cancer_cell_fraction);
+        }
+
+        let tree = Self {
+            names,
+            parents,
+            cancer_cell_fractions,
+            purity,
+        };
+        for (i, name) in tree.names.iter().enumerate() {
+            if tree.cell_fraction(i) < -EPSILON {
+                bail!(
+                    "Subclones of '{}' add up to more than its cancer cell fraction of {}",
+                    name,
+                    tree.cancer_cell_fractions[i]
+                );
+            }
+        }
+
+        Ok(tree)
+    }
+
+    /// Returns the number of clones, including the founding clone.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns true if the tree has no clones.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns the name of a clone.
+    pub fn name(&self, clone: usize) -> &str {
+        &self.names[clone]
+    }
+
+    /// Returns the index of the clone with the given name.
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|n| n == name)
+    }
+
+    /// Returns the fraction of tumour cells in the sample.
+    pub fn purity(&self) -> f64 {
+        self.purity
+    }
+
+    /// Returns the fraction of sample cells belonging to a clone itself, excluding the
+    /// cells of its descendants.
+    pub fn sample_fraction(&self, clone: usize) -> f64 {
+        self.purity * self.cell_fraction(clone).max(0.0)
+    }
+
+    /// Returns true if cells of `clone` carry the variants of `ancestor`.
+    pub fn descends_from(&self, clone: usize, ancestor: usize) -> bool {
+        let mut current = Some(clone);
+        while let Some(index) = current {
+            if index == ancestor {
+                return true;
+            }
+            current = self.parents[index];
+        }
+        false
+    }
+
+    /// Fraction of tumour cells belonging to a clone itself.
+    fn cell_fraction(&self, clone: usize) -> f64 {
+        let children: f64 = (0..self.names.len())
+            .filter(|&i| self.parents[i] == Some(clone))
+            .map(|i| self.cancer_cell_fractions[i])
+            .sum();
+        self.cancer_cell_fractions[clone] - children
+    }
+}
+
+/// Derives the clones carrying somatic variants given by allele fraction instead of clone.
+///
+/// A variant on one of `ploidy` haplotypes of a clone has an allele fraction of
+/// `purity * CCF / ploidy`. Distinct allele fractions become a linear chain of nested clones
+/// below the founding clone, named after the fraction (e.g. `vaf0.1`); fractions reached by
+/// the founding clone itself are assigned to it.
+///
+/// # Arguments
+/// * `vafs` - Allele fractions of the variants
+/// * `ploidy` - Number of haplotypes of the genome
+/// * `purity` - Fraction of tumour cells in the sample
+///
+/// # Returns
+/// Tuple of (clone name of each allele fraction, subclones to add to the tree)
+///
+/// # Errors
+/// Returns an error if an allele fraction is not positive or exceeds `purity / ploidy`
+pub fn vaf_clones(
+    vafs: &[f64],
+    ploidy: usize,
+    purity: f64,
+) -> Result<(Vec<String>, Vec<CloneSpec>)> {
+    let max_vaf = purity / ploidy as f64;
+    let mut distinct: Vec<f64> = Vec::new();
+    for &vaf in vafs {
+        if !(vaf > 0.0 && vaf <= max_vaf + EPSILON) {
+            bail!(
+                "Variant allele fraction {} is not reachable with purity {} and ploidy {} (maximum {})",
+                vaf,
+                purity,
+                ploidy,
+                max_vaf
+            );
+        }
+        if !distinct.contains(&vaf) {
+            distinct.push(vaf);
+        }
+    }
+    distinct.sort_by(|a, b| b.total_cmp(a));
+
+    let mut specs: Vec<CloneSpec> = Vec::new();
+    for &vaf in &distinct {
+        if vaf >= max_vaf - EPSILON {
+            continue;
+        }
+        specs.push(CloneSpec {
+            name: format!("vaf{}", vaf),
+            cancer_cell_fraction: vaf / max_vaf,
+            parent: specs.last().map(|parent| parent.name.clone()),
+        });
+    }
+    let names = vafs
+        .iter()
+        .map(|&vaf| {
+            if vaf >= max_vaf - EPSILON {
+                FOUNDING_CLONE.to_string()
+            } else {
+                format!("vaf{}", vaf)
+            }
+        })
+        .collect();
+
+    Ok((names, specs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(s: &str) -> CloneSpec {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_clone_spec() {
+        assert_eq!(
+            spec("A:0.4"),
+            CloneSpec {
+                name: "A".to_string(),
+                cancer_cell_fraction: 0.4,
+                parent: None
+            }
+        );
+        assert_eq!(spec("B:0.1:A").parent.as_deref(), Some("A"));
+        assert!("A".parse::<CloneSpec>().is_err());
+        assert!("A:x".parse::<CloneSpec>().is_err());
+        assert!("A:0.1:B:C".parse::<CloneSpec>().is_err());
+    }
+
+    #[test]
+    fn test_clone_fractions() {
+        let tree = CloneTree::new(&[spec("A:0.6"), spec("B:0.2:A"), spec("C:0.3")], 0.5).unwrap();
+        let fractions: Vec<f64> = (0..tree.len()).map(|i| tree.sample_fraction(i)).collect();
+        let expected = [0.05, 0.2, 0.1, 0.15];
+        for (fraction, expected) in fractions.iter().zip(expected) {
+            assert!((fraction - expected).abs() < 1e-12, "{:?}", fractions);
+        }
+        // Tumour cells add up to the purity
+        assert!((fractions.iter().sum::<f64>() - 0.5).abs() < 1e-12);
+
+        let b = tree.index("B").unwrap();
+        assert!(tree.descends_from(b, tree.index("A").unwrap()));
+        assert!(tree.descends_from(b, 0));
+        assert!(!tree.descends_from(b, tree.index("C").unwrap()));
+    }
+
+    #[test]
+    fn test_vaf_clones() {
+        let (names, specs) = vaf_clones(&[0.1, 0.4, 0.25, 0.1], 2, 0.8).unwrap();
+        assert_eq!(names, ["vaf0.1", "founder", "vaf0.25", "vaf0.1"]);
+        assert_eq!(specs.len(), 2);
+        assert_eq!(specs[1].parent.as_deref(), Some("vaf0.25"));
+
+        // Every variant gets its allele fraction back
+        let tree = CloneTree::new(&specs, 0.8).unwrap();
+        for (name, vaf) in names.iter().zip([0.1, 0.4, 0.25, 0.1]) {
+            let clone = tree.index(name).unwrap();
+            let carriers: f64 = (0..tree.len())
+                .filter(|&i| tree.descends_from(i, clone))
+                .map(|i| tree.sample_fraction(i))
+                .sum();
+            assert!((carriers / 2.0 - vaf).abs() < 1e-12);
+        }
+
+        assert!(vaf_clones(&[0.5], 2, 0.8).is_err());
+        assert!(vaf_clones(&[0.0], 2, 0.8).is_err());
+    }
+
+    #[test]
+    fn test_invalid_clone_trees() {
+        assert!(CloneTree::new(&[], 0.0).is_err());
+        assert!(CloneTree::new(&[spec("A:0.6"), spec("B:0.6")], 1.0).is_err());
+        assert!(CloneTree::new(&[spec("B:0.2:A"), spec("A:0.6")], 1.0).is_err());
+        assert!(CloneTree::new(&[spec("A:0.6"), spec("A:0.1")], 1.0).is_err());
+        assert!(CloneTree::new(&[spec("normal:0.5")], 1.0).is_err());
+        assert!(CloneTree::new(&[spec("A:0.6"), spec("B:0.7:A")], 1.0).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{Result, bail};
+use rand::Rng;
+
+const PHRED_OFFSET: u8 = 33;
+
+/// Highest quality given to duplex bases, as in dorado's duplex calls.
+const MAX_DUPLEX_QUALITY: u8 = 50;
+
+/// Role of a read in duplex basecalling, as in dorado's `dx` tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplexRole {
+    /// A simplex read without a duplex offspring (`dx:i:0`)
+    Simplex,
+    /// A simplex read whose molecule also produced a duplex read (`dx:i:-1`)
+    Parent,
+    /// A duplex consensus read of a template and complement pair (`dx:i:1`)
+    Duplex,
+}
+
+impl DuplexRole {
+    /// Returns the value dorado writes in the `dx` tag for this role.
+    pub fn tag_value(self) -> i32 {
+        match self {
+            DuplexRole::Simplex => 0,
+            DuplexRole::Parent => -1,
+            DuplexRole::Duplex => 1,
+        }
+    }
+}
+
+/// Model of duplex sequencing, where the complement strand of some molecules is read right
+/// after the template strand.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuplexModel {
+    fraction: f64,
+    quality_boost: u8,
+    consensus: bool,
+}
+
+impl DuplexModel {
+    /// Creates a duplex model that pairs a fraction of the molecules.
+    ///
+    /// # Arguments
+    /// * `fraction` - Fraction of molecules whose complement strand is also read
+    /// * `quality_boost` - Phred quality added to every base of duplex consensus reads
+    ///
+    /// # Errors
+    /// Returns an error if `fraction` is not between 0 and 1
+    pub fn new(fraction: f64, quality_boost: u8) -> Result<Self> {
+        if !(0.0..=1.0).contains(&fraction) {
+            bail!("Duplex fraction must be between 0 and 1, got {}", fraction);
+        }
+        Ok(Self {
+            fraction,
+            quality_boost,
+            consensus: false,
+        })
+    }
+
+    /// Also produces a duplex consensus read for every template and complement pair.
+    pub fn with_consensus(mut self) -> Self {
+        self.consensus = true;
+        self
+    }
+
+    /// Returns true if duplex consensus reads are produced.
+    pub fn consensus(&self) -> bool {
+        self.consensus
+    }
+
+    /// Decides whether the complement strand of a molecule is read.
+    pub fn is_paired<R: Rng>(&self, rng: &mut R) -> bool {
+        rng.random_bool(self.fraction)
+    }
+
+    /// Raises simplex qualities to duplex qualities, capped at Q50.
+    ///
+    /// # Arguments
+    /// * `qualities` - Phred+33 quality string, modified in place
+    pub fn boost_qualities(&self, qualities: &mut [u8]) {
+        for quality in qualities {
+            let phred = quality.saturating_sub(PHRED_OFFSET);
+            *quality = phred
+                .saturating_add(self.quality_boost)
+                .min(MAX_DUPLEX_QUALITY)
+                .max(phred)
+                + PHRED_OFFSET;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_boost_qualities() {
+        let model = DuplexModel::new(0.5, 10).unwrap();
+        let mut qualities = vec![b'+', b'?', b'Q', b'~'];
+        model.boost_qualities(&mut qualities);
+        // Q10 -> Q20, Q30 -> Q40, Q48 -> Q50 (capped), Q93 stays
+        assert_eq!(qualities, vec![b'5', b'I', b'S', b'~']);
+
+        assert!(DuplexModel::new(1.5, 10).is_err());
+    }
+}
@@ -26,7 +26,7 @@ pub enum AlterationType {
 /// - The sum of substitution, insertion, and deletion rates must be ≤ 1.0
 /// - Extension rates define geometric distribution for indel lengths
 /// - If the sum < 1.0, some errors will result in no alteration
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ErrorModel {
     pub substitution_rate: f64,
     pub insertion_rate: f64,
@@ -1,8 +1,23 @@
+use clap::ValueEnum;
 use rand::Rng;
 use std::collections::BTreeMap;
 
+/// What to do when a sampled read length exceeds the chosen reference sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum TooLongPolicy {
+    /// Draw a new length and reference sequence
+    #[default]
+    Resample,
+    /// Start the read at a random position and truncate it at the sequence end
+    Truncate,
+    /// Choose the reference sequence first, then sample a length that fits in it
+    Conditional,
+    /// Skip the read and count it
+    Skip,
+}
+
 /// Empirical model of read lengths built from observed sequencing data.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct LengthModel {
     length_histogram: BTreeMap<usize, usize>,
     total_count: usize,
@@ -50,6 +65,55 @@ impl LengthModel {
 
         None
     }
+
+    /// Samples a random length from the model, restricted to lengths up to `max_length`.
+    ///
+    /// # Arguments
+    /// * `max_length` - Longest acceptable length
+    /// * `rng` - Random number generator
+    ///
+    /// # Returns
+    /// A randomly sampled read length, or None if no observed length is short enough
+    pub fn sample_at_most<R: Rng>(&self, max_length: usize, rng: &mut R) -> Option<usize> {
+        let eligible_count: usize = self
+            .length_histogram
+            .range(..=max_length)
+            .map(|(_, &c)| c)
+            .sum();
+        if eligible_count == 0 {
+            return None;
+        }
+
+        let target = rng.random_range(0..eligible_count);
+        let mut cumulative = 0;
+
+        for (&length, &count) in self.length_histogram.range(..=max_length) {
+            cumulative += count;
+            if cumulative > target {
+                return Some(length);
+            }
+        }
+
+        None
+    }
+
+    /// Returns the mean of the observed lengths, or None if the model is empty.
+    pub fn mean(&self) -> Option<f64> {
+        if self.total_count == 0 {
+            return None;
+        }
+        let total: usize = self
+            .length_histogram
+            .iter()
+            .map(|(&length, &count)| length * count)
+            .sum();
+        Some(total as f64 / self.total_count as f64)
+    }
+
+    /// Returns true if no lengths have been added to the model.
+    pub fn is_empty(&self) -> bool {
+        self.total_count == 0
+    }
 }
 
 #[cfg(test)]
@@ -91,4 +155,19 @@ mod tests {
         // Should produce identical sequences
         assert_eq!(samples1, samples2);
     }
+
+    #[test]
+    fn test_sample_at_most() {
+        let mut model = LengthModel::new();
+        model.add_value(100);
+        model.add_value(5000);
+        model.add_value(50000);
+
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..20 {
+            let sampled = model.sample_at_most(10000, &mut rng).unwrap();
+            assert!(sampled == 100 || sampled == 5000);
+        }
+        assert_eq!(model.sample_at_most(99, &mut rng), None);
+    }
 }
@@ -0,0 +1,439 @@
+use crate::io::bed::BedRecord;
+use crate::io::fasta::Reference;
+use crate::utils::sample_normal;
+use anyhow::{Result, anyhow, bail};
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Mean log-odds of the modification probability called for a site in its true state.
+const CALL_LOGIT_MEAN: f64 = 3.0;
+
+/// Spread of the called log-odds, which leaves some calls ambiguous or wrong.
+const CALL_LOGIT_SD: f64 = 1.5;
+
+/// Returns the canonical base carrying a modification code, as used in `MM` tags.
+///
+/// Supported codes are `m` (5mC), `h` (5hmC) and `a` (6mA).
+pub fn canonical_base(code: char) -> Option<u8> {
+    match code {
+        'm' | 'h' => Some(b'C'),
+        'a' => Some(b'A'),
+        _ => None,
+    }
+}
+
+/// Global modification rate of a sequence motif, written as `MOTIF:CODE:RATE`.
+///
+/// The modified base is the first occurrence of the code's canonical base in the motif
+/// (e.g. the C of `CG`, or the A of `GATC`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotifRate {
+    pub motif: Vec<u8>,
+    pub code: char,
+    /// Probability that a site is modified in a read
+    pub rate: f64,
+}
+
+impl MotifRate {
+    /// Returns the offset of the modified base within the motif.
+    fn offset(&self) -> usize {
+        let base = canonical_base(self.code).expect("validated when parsed");
+        self.motif
+            .iter()
+            .position(|&b| b == base)
+            .expect("validated when parsed")
+    }
+}
+
+impl FromStr for MotifRate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').map(str::trim).collect();
+        let [motif, code, rate] = parts[..] else {
+            return Err(format!("expected MOTIF:CODE:RATE, got '{}'", s));
+        };
+
+        let motif = motif.to_ascii_uppercase().into_bytes();
+        if motif.is_empty() || !motif.iter().all(|b| b"ACGT".contains(b)) {
+            return Err(format!("invalid motif in '{}'", s));
+        }
+        let mut chars = code.chars();
+        let (Some(code), None) = (chars.next(), chars.next()) else {
+            return Err(format!("invalid modification code in '{}'", s));
+        };
+        let Some(base) = canonical_base(code) else {
+            return Err(format!(
+                "unsupported modification code '{}' (expected m, h or a)",
+                code
+            ));
+        };
+        if !motif.contains(&base) {
+            return Err(format!(
+                "motif in '{}' has no {} to modify",
+                s, base as char
+            ));
+        }
+        let rate: f64 = rate
+            .parse()
+            .map_err(|_| format!("invalid rate in '{}'", s))?;
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(format!("rate in '{}' must be between 0 and 1", s));
+        }
+
+        Ok(Self { motif, code, rate })
+    }
+}
+
+impl Display for MotifRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            String::from_utf8_lossy(&self.motif),
+            self.code,
+            self.rate
+        )
+    }
+}
+
+/// A modification site of one read, with its true state and called probability.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModificationCall {
+    /// Zero-based position of the site in the read
+    pub position: usize,
+    /// Zero-based reference position of the site, if known
+    pub reference_position: Option<usize>,
+    pub code: char,
+    /// Whether the site is modified in the simulated molecule
+    pub modified: bool,
+    /// Called probability of modification, scaled to 0-255 as in `ML` tags
+    pub probability: u8,
+}
+
+/// Site-specific modification rate from a bedMethyl file.
+#[derive(Debug, Clone, PartialEq)]
+struct SiteRate {
+    position: usize,
+    code: char,
+    /// Whether the site is on the reverse strand
+    reverse: bool,
+    rate: f64,
+}
+
+/// Model of base modifications and of the calls made on them.
+///
+/// Every read draws the state of each modification site from its rate: the site-specific
+/// fraction from a bedMethyl file if listed there, or the global rate of its motif otherwise.
+/// Calls report a probability of modification whose log-odds are normally distributed
+/// around the true state, like the output of a basecaller's modification model.
+#[derive(Debug, Clone, Default)]
+pub struct MethylationModel {
+    motifs: Vec<MotifRate>,
+    /// Site rates per reference sequence, sorted by position
+    sites: Vec<Vec<SiteRate>>,
+}
+
+impl MethylationModel {
+    /// Creates a model from global motif rates.
+    ///
+    /// # Arguments
+    /// * `motifs` - Motifs whose sites are called, with their modification rates
+    pub fn new(motifs: Vec<MotifRate>) -> Self {
+        Self {
+            motifs,
+            sites: Vec::new(),
+        }
+    }
+
+    /// Adds site-specific modification fractions from bedMethyl records.
+    ///
+    /// Uses the modification code (4th column), strand (6th column) and percent modified
+    /// (11th column). Sites on the `-` strand are called on reads of reverse-complemented
+    /// templates, and the other sites on forward reads; sites with unsupported codes are
+    /// ignored.
+    ///
+    /// # Arguments
+    /// * `records` - bedMethyl records
+    /// * `reference` - Reference sequences the records refer to
+    ///
+    /// # Errors
+    /// Returns an error if a record refers to an unknown sequence or lacks a valid percentage
+    pub fn with_sites(mut self, records: &[BedRecord], reference: &Reference) -> Result<Self> {
+        let sequence_indices: HashMap<&str, usize> = (0..reference.len())
+            .map(|i| (reference.sequence_id(i), i))
+            .collect();
+
+        self.sites = vec![Vec::new(); reference.len()];
+        for record in records {
+            let code = record.name().and_then(|name| {
+                let mut chars = name.chars();
+                chars.next().filter(|_| chars.next().is_none())
+            });
+            let Some(code) = code.filter(|&code| canonical_base(code).is_some()) else {
+                continue;
+            };
+            let Some(&sequence_index) = sequence_indices.get(record.chrom.as_str()) else {
+                bail!(
+                    "bedMethyl site {}:{} refers to a sequence not in the reference",
+                    record.chrom,
+                    record.start
+                );
+            };
+            let percent: f64 = record
+                .field(7)
+                .and_then(|s| s.parse().ok())
+                .filter(|percent| (0.0..=100.0).contains(percent))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "bedMethyl site {}:{} has no valid percent modified (11th column)",
+                        record.chrom,
+                        record.start
+                    )
+                })?;
+
+            self.sites[sequence_index].push(SiteRate {
+                position: record.start,
+                code,
+                reverse: record.strand() == Some('-'),
+                rate: percent / 100.0,
+            });
+        }
+        for sites in &mut self.sites {
+            sites.sort_by_key(|site| (site.position, site.code));
+        }
+
+        Ok(self)
+    }
+
+    /// Draws the true state and call of every modification site in a read template.
+    ///
+    /// # Arguments
+    /// * `template` - Read template, before sequencing errors
+    /// * `sequence_index` - Reference sequence the template comes from
+    /// * `reverse` - Whether the template is reverse complemented, reading `-` strand sites
+    /// * `reference_position` - Maps a template position to its reference position, if known
+    /// * `rng` - Random stream of the read being generated
+    ///
+    /// # Returns
+    /// Calls sorted by template position
+    pub fn call(
+        &self,
+        template: &[u8],
+        sequence_index: usize,
+        reverse: bool,
+        reference_position: impl Fn(usize) -> Option<usize>,
+        rng: &mut impl Rng,
+    ) -> Vec<ModificationCall> {
+        let sites = self
+            .sites
+            .get(sequence_index)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        // (position, code, rate), with site rates taking precedence over motif rates
+        let mut candidates = Vec::new();
+        if !sites.is_empty() {
+            for (position, base) in template.iter().enumerate() {
+                let base = base.to_ascii_uppercase();
+                if base != b'C' && base != b'A' {
+                    continue;
+                }
+                let Some(reference_position) = reference_position(position) else {
+                    continue;
+                };
+                let first = sites.partition_point(|site| site.position < reference_position);
+                for site in sites[first..]
+                    .iter()
+                    .take_while(|site| site.position == reference_position)
+                    .filter(|site| site.reverse == reverse)
+                    .filter(|site| canonical_base(site.code) == Some(base))
+                {
+                    candidates.push((position, site.code, site.rate));
+                }
+            }
+        }
+        for motif in &self.motifs {
+            let offset = motif.offset();
+            for (start, window) in template.windows(motif.motif.len()).enumerate() {
+                if window.eq_ignore_ascii_case(&motif.motif) {
+                    candidates.push((start + offset, motif.code, motif.rate));
+                }
+            }
+        }
+        candidates.sort_by_key(|&(position, code, _)| (position, code));
+        candidates.dedup_by_key(|&mut (position, code, _)| (position, code));
+
+        candidates
+            .into_iter()
+            .map(|(position, code, rate)| {
+                let modified = rng.random_bool(rate);
+                let mean = if modified {
+                    CALL_LOGIT_MEAN
+                } else {
+                    -CALL_LOGIT_MEAN
+                };
+                let logit = sample_normal(rng, mean, CALL_LOGIT_SD);
+                let probability = 1.0 / (1.0 + (-logit).exp());
+                ModificationCall {
+                    position,
+                    reference_position: reference_position(position),
+                    code,
+                    modified,
+                    probability: (probability * 256.0).floor().min(255.0) as u8,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Formats modification calls as the values of SAM `MM` and `ML` tags.
+///
+/// Calls are grouped by modification code, with `?` marking bases without a call as
+/// unknown rather than unmodified, as basecallers do.
+///
+/// # Arguments
+/// * `sequence` - Read sequence
+/// * `calls` - Calls on canonical bases of the read, sorted by position
+///
+/// # Returns
+/// Tuple of (`MM` string, `ML` probabilities)
+pub fn format_mm_ml(sequence: &[u8], calls: &[ModificationCall]) -> (String, Vec<u8>) {
+    let mut codes: Vec<char> = calls.iter().map(|call| call.code).collect();
+    codes.sort_by_key(|&code| (canonical_base(code), code));
+    codes.dedup();
+
+    let mut mm = String::new();
+    let mut ml = Vec::with_capacity(calls.len());
+    for code in codes {
+        let base = canonical_base(code).expect("calls have supported codes");
+        mm.push_str(&format!("{}+{}?", base as char, code));
+        let mut previous = 0;
+        for call in calls.iter().filter(|call| call.code == code) {
+            let skipped = sequence[previous..call.position]
+                .iter()
+                .filter(|b| b.to_ascii_uppercase() == base)
+                .count();
+            mm.push_str(&format!(",{}", skipped));
+            ml.push(call.probability);
+            previous = call.position + 1;
+        }
+        mm.push(';');
+    }
+
+    (mm, ml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::fasta::FastaRecord;
+    use crate::variants::reverse_complement;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_parse_motif_rate() {
+        let cpg: MotifRate = "cg:m:0.75".parse().unwrap();
+        assert_eq!(cpg.motif, b"CG");
+        assert_eq!(cpg.offset(), 0);
+        let gatc: MotifRate = "GATC:a:1".parse().unwrap();
+        assert_eq!(gatc.offset(), 1);
+
+        assert!("CG:m".parse::<MotifRate>().is_err());
+        assert!("CG:x:0.5".parse::<MotifRate>().is_err());
+        assert!("GT:a:0.5".parse::<MotifRate>().is_err());
+        assert!("CG:m:1.5".parse::<MotifRate>().is_err());
+    }
+
+    #[test]
+    fn test_call_sites() {
+        let reference = Reference::from(vec![FastaRecord {
+            id: "chr1".to_string(),
+            sequence: b"ACGTTCGACGA".to_vec(),
+        }]);
+        let bed = |start: usize, code: &str, strand: &str, percent: &str| BedRecord {
+            chrom: "chr1".to_string(),
+            start,
+            end: start + 1,
+            fields: [code, "0", strand, "0", "0", "0", "10", percent]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        };
+        // The CpG at 5 is never methylated, and the A at 7 always carries 6mA; on the reverse
+        // strand, the C of the CpG at 8 is never methylated
+        let model = MethylationModel::new(vec!["CG:m:1".parse().unwrap()])
+            .with_sites(
+                &[
+                    bed(5, "m", "+", "0"),
+                    bed(7, "a", "+", "100"),
+                    bed(9, "m", "-", "0"),
+                ],
+                &reference,
+            )
+            .unwrap();
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let template = reference.fetch(0, 0, 11).unwrap();
+        let calls = model.call(&template, 0, false, Some, &mut rng);
+        let sites: Vec<(usize, char, bool)> = calls
+            .iter()
+            .map(|call| (call.position, call.code, call.modified))
+            .collect();
+        assert_eq!(
+            sites,
+            [
+                (1, 'm', true),
+                (5, 'm', false),
+                (7, 'a', true),
+                (8, 'm', true)
+            ]
+        );
+
+        // Reverse-complemented templates read the sites of the `-` strand
+        let reversed = reverse_complement(&template);
+        let calls = model.call(&reversed, 0, true, |position| Some(10 - position), &mut rng);
+        let sites: Vec<(usize, Option<usize>, bool)> = calls
+            .iter()
+            .map(|call| (call.position, call.reference_position, call.modified))
+            .collect();
+        assert_eq!(
+            sites,
+            [(1, Some(9), false), (4, Some(6), true), (8, Some(2), true)]
+        );
+
+        // Calls are mostly confident and on the right side
+        let mut correct = 0;
+        for _ in 0..100 {
+            for call in model.call(&template, 0, false, Some, &mut rng) {
+                correct += usize::from(call.modified == (call.probability >= 128));
+            }
+        }
+        assert!(correct > 350, "{}", correct);
+    }
+
+    #[test]
+    fn test_format_mm_ml() {
+        let call = |position: usize, code: char, probability: u8| ModificationCall {
+            position,
+            reference_position: None,
+            code,
+            modified: true,
+            probability,
+        };
+        let (mm, ml) = format_mm_ml(
+            b"ACGTCCGACGA",
+            &[
+                call(1, 'm', 200),
+                call(5, 'm', 10),
+                call(7, 'a', 250),
+                call(8, 'm', 128),
+            ],
+        );
+        assert_eq!(mm, "A+a?,1;C+m?,0,1,0;");
+        assert_eq!(ml, [250, 200, 10, 128]);
+    }
+}
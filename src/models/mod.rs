@@ -1,9 +1,23 @@
-//! Empirical models for read length, errors and quality scores based on observed sequencing data.
+//! Empirical models for read length, errors and quality scores based on observed sequencing data,
+//! plus the policies controlling how reference bases are turned into read templates, the
+//! clonal structure of tumour samples, base modifications, adaptive sampling and duplex reads.
 
+pub mod adaptive;
+pub mod alphabet;
+pub mod clones;
+pub mod duplex;
 pub mod error;
 pub mod length;
+pub mod methylation;
 pub mod quality;
+pub mod targets;
 
+pub use adaptive::{AdaptiveSampling, EndReason};
+pub use alphabet::{AlphabetPolicy, IupacPolicy, SoftMaskPolicy};
+pub use clones::{CloneSpec, CloneTree, vaf_clones};
+pub use duplex::{DuplexModel, DuplexRole};
 pub use error::ErrorModel;
-pub use length::LengthModel;
+pub use length::{LengthModel, TooLongPolicy};
+pub use methylation::{MethylationModel, ModificationCall, MotifRate};
 pub use quality::QualityModel;
+pub use targets::{CutSite, CutSites, SamplingSpan, TargetMode, TargetRegions};
@@ -11,6 +11,7 @@ const DEFAULT_BUCKET_WIDTH: usize = 100;
 const DEFAULT_MAX_ITEMS_PER_BUCKET: usize = 1000;
 
 /// A bucket storing quality strings for reads within a specific length range.
+#[derive(Clone)]
 struct QualityBatch {
     qualities: Vec<Vec<u8>>,
     max_capacity: usize,
@@ -74,6 +75,7 @@ impl QualityBatch {
 /// up to a threshold (default 20kb), with ultra-long reads stored in a catch-all bucket.
 ///
 /// Each bucket uses reservoir sampling to cap memory usage while maintaining diversity.
+#[derive(Clone)]
 pub struct QualityModel {
     /// Quality batches organized by length range.
     batches: Vec<QualityBatch>,
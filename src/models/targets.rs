@@ -0,0 +1,503 @@
+use crate::io::bed::BedRecord;
+use crate::io::fasta::Reference;
+use crate::variants::VariantLayout;
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use rand::Rng;
+use std::collections::HashMap;
+
+/// How reads relate to the target regions they are sampled from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum TargetMode {
+    /// Reads lie entirely inside a target region
+    #[default]
+    Contained,
+    /// Reads overlap a target region by at least one base
+    Overlap,
+}
+
+/// A stretch of a reference sequence that a read is sampled from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SamplingSpan {
+    pub sequence_index: usize,
+    /// Zero-based start of the span
+    pub start: usize,
+    /// Zero-based, exclusive end of the span
+    pub end: usize,
+    /// Length of the whole reference sequence
+    pub sequence_length: usize,
+    /// Whether reads only need to overlap the span rather than lie inside it
+    pub overlap: bool,
+}
+
+impl SamplingSpan {
+    /// Creates a span covering a whole reference sequence.
+    pub fn whole_sequence(sequence_index: usize, sequence_length: usize) -> Self {
+        Self {
+            sequence_index,
+            start: 0,
+            end: sequence_length,
+            sequence_length,
+            overlap: false,
+        }
+    }
+
+    /// Returns the longest read that can be placed in this span.
+    pub fn max_length(&self) -> usize {
+        if self.overlap {
+            self.sequence_length
+        } else {
+            self.end - self.start
+        }
+    }
+
+    /// Returns the window (start, end) a read of the given length must lie within.
+    ///
+    /// For overlapping spans, the window extends `length - 1` bases beyond each side of the
+    /// span (clipped to the sequence), so any read placed in it overlaps the span.
+    pub fn window(&self, length: usize) -> (usize, usize) {
+        if self.overlap {
+            let flank = length.saturating_sub(1);
+            (
+                self.start.saturating_sub(flank),
+                (self.end + flank).min(self.sequence_length),
+            )
+        } else {
+            (self.start, self.end)
+        }
+    }
+}
+
+/// Weighted target regions that read positions are drawn from.
+///
+/// Regions are chosen with probability proportional to their length times their weight,
+/// so sampling density is uniform within regions of equal weight.
+#[derive(Debug, Clone)]
+pub struct TargetRegions {
+    spans: Vec<SamplingSpan>,
+    weights: Vec<f64>,
+    cumulative_weights: Vec<f64>,
+}
+
+impl TargetRegions {
+    /// Builds target regions from BED intervals.
+    ///
+    /// Intervals are weighted equally unless `weighted` is set, in which case weights are
+    /// taken from the BED score column (5th column) when present, and default to 1.0
+    /// otherwise. Intervals with zero weight are never sampled.
+    ///
+    /// # Arguments
+    /// * `records` - Target intervals
+    /// * `reference` - Reference the intervals refer to
+    /// * `mode` - Whether reads must lie inside or just overlap the targets
+    /// * `weighted` - Whether the BED scores weight the intervals
+    ///
+    /// # Errors
+    /// Returns an error if an interval refers to an unknown sequence, extends past its end,
+    /// has a negative weight, or if all intervals have zero weight
+    pub fn new(
+        records: &[BedRecord],
+        reference: &Reference,
+        mode: TargetMode,
+        weighted: bool,
+    ) -> Result<Self> {
+        let sequence_indices: HashMap<&str, usize> = (0..reference.len())
+            .map(|i| (reference.sequence_id(i), i))
+            .collect();
+
+        let mut spans = Vec::with_capacity(records.len());
+        let mut weights = Vec::with_capacity(records.len());
+
+        for record in records {
+            let Some(&sequence_index) = sequence_indices.get(record.chrom.as_str()) else {
+                bail!(
+                    "Target region {}:{}-{} refers to a sequence not in the reference",
+                    record.chrom,
+                    record.start,
+                    record.end
+                );
+            };
+            let sequence_length = reference.sequence_length(sequence_index);
+            if record.end > sequence_length {
+                bail!(
+                    "Target region {}:{}-{} extends past the sequence end ({} bp)",
+                    record.chrom,
+                    record.start,
+                    record.end,
+                    sequence_length
+                );
+            }
+
+            let weight = if weighted {
+                record.score().unwrap_or(1.0)
+            } else {
+                1.0
+            };
+            if !(0.0..).contains(&weight) {
+                bail!(
+                    "Target region {}:{}-{} has an invalid weight {}",
+                    record.chrom,
+                    record.start,
+                    record.end,
+                    weight
+                );
+            }
+
+            spans.push(SamplingSpan {
+                sequence_index,
+                start: record.start,
+                end: record.end,
+                sequence_length,
+                overlap: mode == TargetMode::Overlap,
+            });
+            weights.push(weight);
+        }
+
+        Self::weigh(spans, weights)
+    }
+
+    /// Follows the target regions onto a haplotype, so they cover the same bases of its
+    /// sequences as they do of the reference.
+    ///
+    /// # Arguments
+    /// * `layout` - Variants applied to the haplotype
+    ///
+    /// # Errors
+    /// Returns an error if the variants delete all targets with a positive weight
+    pub fn on_haplotype(&self, layout: &VariantLayout) -> Result<Self> {
+        let spans = self
+            .spans
+            .iter()
+            .map(|span| SamplingSpan {
+                start: layout.to_haplotype(span.sequence_index, span.start),
+                end: layout.to_haplotype(span.sequence_index, span.end),
+                sequence_length: layout.to_haplotype(span.sequence_index, span.sequence_length),
+                ..*span
+            })
+            .collect();
+        Self::weigh(spans, self.weights.clone())
+            .map_err(|_| anyhow!("Variants of a haplotype delete all of its target regions"))
+    }
+
+    /// Weighs spans by their length times their weight.
+    fn weigh(spans: Vec<SamplingSpan>, weights: Vec<f64>) -> Result<Self> {
+        let cumulative_weights: Vec<f64> = spans
+            .iter()
+            .zip(&weights)
+            .scan(0.0, |total, (span, weight)| {
+                *total += weight * (span.end - span.start) as f64;
+                Some(*total)
+            })
+            .collect();
+        if cumulative_weights.last().is_none_or(|&total| total <= 0.0) {
+            bail!("Target regions must have a positive total weight");
+        }
+
+        Ok(Self {
+            spans,
+            weights,
+            cumulative_weights,
+        })
+    }
+
+    /// Chooses a target region at random according to the weights.
+    pub fn choose<R: Rng>(&self, rng: &mut R) -> SamplingSpan {
+        let total = *self.cumulative_weights.last().unwrap();
+        let target = rng.random_range(0.0..total);
+        let idx = self
+            .cumulative_weights
+            .partition_point(|&cumulative| cumulative <= target)
+            .min(self.spans.len() - 1);
+        self.spans[idx]
+    }
+
+    /// Returns the longest read that fits in any target region.
+    pub fn max_length(&self) -> usize {
+        self.spans.iter().map(|s| s.max_length()).max().unwrap_or(0)
+    }
+}
+
+/// A Cas9 cut site that reads start from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CutSite {
+    pub sequence_index: usize,
+    /// Zero-based position of the first base after the cut
+    pub position: usize,
+    /// Whether reads extend towards the sequence start (and are reverse complemented)
+    pub reverse: bool,
+}
+
+/// Cut sites of a Cas9 enrichment, with a background of off-target reads.
+///
+/// Sites are chosen with probability proportional to their weight. Reads start exactly at
+/// the cut and extend away from it on the strand given for the site.
+pub struct CutSites {
+    sites: Vec<CutSite>,
+    cumulative_weights: Vec<f64>,
+    background_fraction: f64,
+}
+
+impl CutSites {
+    /// Builds cut sites from BED intervals.
+    ///
+    /// The cut lies before the interval start, and the strand column (6th) sets the direction
+    /// of the reads: `+` reads cover the bases after the cut, `-` reads the bases before it.
+    /// Sites are weighted equally unless `weighted` is set, in which case weights are taken
+    /// from the BED score column (5th column) when present, and default to 1.0 otherwise.
+    ///
+    /// # Arguments
+    /// * `records` - Cut site intervals
+    /// * `reference` - Reference the sites refer to
+    /// * `background_fraction` - Fraction of reads drawn from random positions instead
+    /// * `weighted` - Whether the BED scores weight the sites
+    ///
+    /// # Errors
+    /// Returns an error if a site refers to an unknown sequence, has no strand, has no bases
+    /// to read in its direction or a negative weight, if all sites have zero weight, or if
+    /// `background_fraction` is not between 0 and 1
+    pub fn new(
+        records: &[BedRecord],
+        reference: &Reference,
+        background_fraction: f64,
+        weighted: bool,
+    ) -> Result<Self> {
+        if !(0.0..=1.0).contains(&background_fraction) {
+            bail!(
+                "Background fraction must be between 0 and 1, got {}",
+                background_fraction
+            );
+        }
+        let sequence_indices: HashMap<&str, usize> = (0..reference.len())
+            .map(|i| (reference.sequence_id(i), i))
+            .collect();
+
+        let mut sites = Vec::with_capacity(records.len());
+        let mut cumulative_weights = Vec::with_capacity(records.len());
+        let mut total_weight = 0.0;
+
+        for record in records {
+            let site = format!("{}:{}", record.chrom, record.start);
+            let Some(&sequence_index) = sequence_indices.get(record.chrom.as_str()) else {
+                bail!(
+                    "Cut site {} refers to a sequence not in the reference",
+                    site
+                );
+            };
+            let reverse = match record.strand() {
+                Some(strand) => strand == '-',
+                None => bail!("Cut site {} has no strand in the 6th column", site),
+            };
+            let sequence_length = reference.sequence_length(sequence_index);
+            if (reverse && record.start == 0) || (!reverse && record.start >= sequence_length) {
+                bail!("Cut site {} has no bases to read on its strand", site);
+            }
+
+            let weight = if weighted {
+                record.score().unwrap_or(1.0)
+            } else {
+                1.0
+            };
+            if !(0.0..).contains(&weight) {
+                bail!("Cut site {} has an invalid weight {}", site, weight);
+            }
+
+            total_weight += weight;
+            sites.push(CutSite {
+                sequence_index,
+                position: record.start,
+                reverse,
+            });
+            cumulative_weights.push(total_weight);
+        }
+
+        if total_weight <= 0.0 {
+            bail!("Cut sites must have a positive total weight");
+        }
+
+        Ok(Self {
+            sites,
+            cumulative_weights,
+            background_fraction,
+        })
+    }
+
+    /// Chooses the cut site of the next read, or None for a background read.
+    pub fn choose<R: Rng>(&self, rng: &mut R) -> Option<CutSite> {
+        if rng.random_bool(self.background_fraction) {
+            return None;
+        }
+        let total = *self.cumulative_weights.last().unwrap();
+        let target = rng.random_range(0.0..total);
+        let idx = self
+            .cumulative_weights
+            .partition_point(|&cumulative| cumulative <= target)
+            .min(self.sites.len() - 1);
+        Some(self.sites[idx])
+    }
+
+    /// Returns the number of cut sites.
+    pub fn len(&self) -> usize {
+        self.sites.len()
+    }
+
+    /// Returns true if there are no cut sites.
+    pub fn is_empty(&self) -> bool {
+        self.sites.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::fasta::FastaRecord;
+    use crate::variants::{Variant, VariantKind, place_variants};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn bed(chrom: &str, start: usize, end: usize, score: Option<&str>) -> BedRecord {
+        let mut fields = Vec::new();
+        if let Some(score) = score {
+            fields = vec!["target".to_string(), score.to_string()];
+        }
+        BedRecord {
+            chrom: chrom.to_string(),
+            start,
+            end,
+            fields,
+        }
+    }
+
+    fn reference() -> Reference {
+        Reference::from(vec![FastaRecord {
+            id: "chr1".to_string(),
+            sequence: vec![b'A'; 1000],
+        }])
+    }
+
+    #[test]
+    fn test_weighted_choice() {
+        let records = vec![
+            bed("chr1", 0, 100, Some("0")),
+            bed("chr1", 500, 600, Some("2")),
+        ];
+        let targets =
+            TargetRegions::new(&records, &reference(), TargetMode::Contained, true).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..100 {
+            let span = targets.choose(&mut rng);
+            assert_eq!((span.start, span.end), (500, 600));
+        }
+
+        // Scores are ignored unless they are asked to weight the regions
+        let targets =
+            TargetRegions::new(&records, &reference(), TargetMode::Contained, false).unwrap();
+        let first = (0..100)
+            .filter(|_| targets.choose(&mut rng).start == 0)
+            .count();
+        assert!((30..70).contains(&first), "{}", first);
+    }
+
+    #[test]
+    fn test_overlap_window() {
+        let records = vec![bed("chr1", 10, 20, None)];
+        let targets =
+            TargetRegions::new(&records, &reference(), TargetMode::Overlap, false).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let span = targets.choose(&mut rng);
+
+        assert_eq!(span.window(5), (6, 24));
+        assert_eq!(span.window(50), (0, 69));
+        assert_eq!(targets.max_length(), 1000);
+    }
+
+    #[test]
+    fn test_targets_on_haplotype() {
+        let records = vec![bed("chr1", 100, 200, None), bed("chr1", 500, 600, None)];
+        let targets =
+            TargetRegions::new(&records, &reference(), TargetMode::Contained, false).unwrap();
+        let deletion = |start, end| Variant {
+            label: "del".to_string(),
+            kind: VariantKind::Deletion,
+            sequence_index: 0,
+            start,
+            end,
+            replacement: b"A".to_vec(),
+        };
+
+        // A deletion before the second target shifts it, and the sequence shortens
+        let (layout, _) = place_variants(&[1000], vec![deletion(300, 401)]);
+        let shifted = targets.on_haplotype(&layout).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..100 {
+            let span = shifted.choose(&mut rng);
+            assert!(matches!((span.start, span.end), (100, 200) | (400, 500)));
+            assert_eq!(span.sequence_length, 900);
+        }
+
+        // Targets deleted entirely are never sampled
+        let (layout, _) = place_variants(&[1000], vec![deletion(50, 301)]);
+        let shifted = targets.on_haplotype(&layout).unwrap();
+        for _ in 0..100 {
+            assert_eq!(shifted.choose(&mut rng).start, 250);
+        }
+        let (layout, _) = place_variants(&[1000], vec![deletion(50, 701)]);
+        assert!(targets.on_haplotype(&layout).is_err());
+    }
+
+    #[test]
+    fn test_cut_sites() {
+        let cut = |start: usize, score: &str, strand: &str| BedRecord {
+            chrom: "chr1".to_string(),
+            start,
+            end: start + 1,
+            fields: vec!["cut".to_string(), score.to_string(), strand.to_string()],
+        };
+        let records = vec![cut(100, "1", "+"), cut(800, "3", "-"), cut(500, "0", "+")];
+        let sites = CutSites::new(&records, &reference(), 0.2, true).unwrap();
+        assert_eq!(sites.len(), 3);
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let (mut background, mut forward, mut reverse) = (0, 0, 0);
+        for _ in 0..1000 {
+            match sites.choose(&mut rng) {
+                None => background += 1,
+                Some(site) if site.reverse => {
+                    assert_eq!(site.position, 800);
+                    reverse += 1;
+                }
+                Some(site) => {
+                    assert_eq!(site.position, 100);
+                    forward += 1;
+                }
+            }
+        }
+        assert!((150..250).contains(&background));
+        assert!(reverse > 2 * forward);
+
+        // Without weights, the zero-score site is drawn as often as the others
+        let sites = CutSites::new(&records, &reference(), 0.0, false).unwrap();
+        let unscored = (0..300)
+            .filter(|_| sites.choose(&mut rng).unwrap().position == 500)
+            .count();
+        assert!((70..130).contains(&unscored), "{}", unscored);
+
+        assert!(CutSites::new(&[cut(0, "1", "-")], &reference(), 0.0, false).is_err());
+        assert!(CutSites::new(&[cut(10, "1", ".")], &reference(), 0.0, false).is_err());
+        assert!(CutSites::new(&records, &reference(), 1.5, false).is_err());
+    }
+
+    #[test]
+    fn test_invalid_regions() {
+        let unknown = vec![bed("chrX", 0, 10, None)];
+        assert!(TargetRegions::new(&unknown, &reference(), TargetMode::Contained, false).is_err());
+
+        let past_end = vec![bed("chr1", 900, 1001, None)];
+        assert!(TargetRegions::new(&past_end, &reference(), TargetMode::Contained, false).is_err());
+
+        let zero_weight = vec![bed("chr1", 0, 10, Some("0"))];
+        assert!(
+            TargetRegions::new(&zero_weight, &reference(), TargetMode::Contained, true).is_err()
+        );
+    }
+}
@@ -0,0 +1,464 @@
+//! Short tandem repeat (STR) expansions.
+//!
+//! Repeat loci are read from a BED file giving the repeat tract, its motif and the copy
+//! number of each haplotype's allele. Unlike other variants, repeat alleles are built for
+//! every read: copy numbers given as a distribution are drawn anew for each read reaching the
+//! locus, which models somatic instability of expanded repeats.
+
+use crate::io::bed::BedRecord;
+use crate::io::fasta::Reference;
+use crate::utils::sample_normal;
+use crate::variants::VariantLayout;
+use anyhow::{Result, anyhow, bail};
+use rand::Rng;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Copy number of a repeat allele.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CopyNumber {
+    /// The same number of copies in every read (`N`)
+    Fixed(usize),
+    /// Normally distributed across reads, rounded to whole copies (`MEAN:SD`)
+    Normal { mean: f64, sd: f64 },
+    /// Uniformly distributed across reads (`MIN-MAX`)
+    Uniform { min: usize, max: usize },
+}
+
+impl CopyNumber {
+    /// Draws the copy number of the allele in one read.
+    pub fn sample(&self, rng: &mut impl Rng) -> usize {
+        match *self {
+            CopyNumber::Fixed(copies) => copies,
+            CopyNumber::Normal { mean, sd } => {
+                sample_normal(rng, mean, sd).round().max(0.0) as usize
+            }
+            CopyNumber::Uniform { min, max } => rng.random_range(min..=max),
+        }
+    }
+}
+
+impl FromStr for CopyNumber {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("expected N, MEAN:SD or MIN-MAX copies, got '{}'", s);
+        if let Some((mean, sd)) = s.split_once(':') {
+            let mean: f64 = mean.trim().parse().map_err(|_| invalid())?;
+            let sd: f64 = sd.trim().parse().map_err(|_| invalid())?;
+            if !(mean >= 0.0 && sd >= 0.0 && mean.is_finite() && sd.is_finite()) {
+                return Err(invalid());
+            }
+            Ok(CopyNumber::Normal { mean, sd })
+        } else if let Some((min, max)) = s.split_once('-') {
+            let min = min.trim().parse().map_err(|_| invalid())?;
+            let max = max.trim().parse().map_err(|_| invalid())?;
+            if min > max {
+                return Err(invalid());
+            }
+            Ok(CopyNumber::Uniform { min, max })
+        } else {
+            s.trim()
+                .parse()
+                .map(CopyNumber::Fixed)
+                .map_err(|_| invalid())
+        }
+    }
+}
+
+impl Display for CopyNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CopyNumber::Fixed(copies) => write!(f, "{}", copies),
+            CopyNumber::Normal { mean, sd } => write!(f, "{}:{}", mean, sd),
+            CopyNumber::Uniform { min, max } => write!(f, "{}-{}", min, max),
+        }
+    }
+}
+
+/// A repeat locus on the reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatLocus {
+    pub id: String,
+    pub sequence_index: usize,
+    /// Zero-based start of the reference repeat tract
+    pub start: usize,
+    /// Zero-based, exclusive end of the reference repeat tract
+    pub end: usize,
+    pub motif: Vec<u8>,
+    /// Copy number of each haplotype's allele (a single value applies to all haplotypes)
+    pub alleles: Vec<CopyNumber>,
+}
+
+impl RepeatLocus {
+    /// Reads repeat loci from BED records.
+    ///
+    /// The interval is the reference repeat tract (which the alleles replace), the name
+    /// column the locus ID, the 5th column the motif and the 6th column the copy numbers of
+    /// the alleles, comma-separated in haplotype order.
+    ///
+    /// # Arguments
+    /// * `records` - BED records describing the loci
+    /// * `reference` - Reference sequences the records refer to
+    ///
+    /// # Errors
+    /// Returns an error if a record refers to an unknown sequence, lies outside it, overlaps
+    /// another locus, or lacks a motif or valid copy numbers
+    pub fn from_bed(records: &[BedRecord], reference: &Reference) -> Result<Vec<Self>> {
+        let sequence_indices: HashMap<&str, usize> = (0..reference.len())
+            .map(|i| (reference.sequence_id(i), i))
+            .collect();
+
+        let mut loci = Vec::with_capacity(records.len());
+        for record in records {
+            let region = format!("{}:{}-{}", record.chrom, record.start, record.end);
+            let Some(&sequence_index) = sequence_indices.get(record.chrom.as_str()) else {
+                bail!(
+                    "Repeat locus {} refers to a sequence not in the reference",
+                    region
+                );
+            };
+            let sequence_length = reference.sequence_length(sequence_index);
+            if record.start > record.end || record.end > sequence_length {
+                bail!(
+                    "Repeat locus {} extends past the sequence end ({} bp)",
+                    region,
+                    sequence_length
+                );
+            }
+
+            let motif = record
+                .field(1)
+                .ok_or_else(|| anyhow!("Repeat locus {} has no motif in the 5th column", region))?;
+            let alleles = record
+                .field(2)
+                .ok_or_else(|| anyhow!("Repeat locus {} has no copy numbers", region))?
+                .split(',')
+                .map(|copies| {
+                    copies
+                        .parse()
+                        .map_err(|e| anyhow!("Repeat locus {}: {}", region, e))
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            loci.push(Self {
+                id: record.name().map_or(region, str::to_string),
+                sequence_index,
+                start: record.start,
+                end: record.end,
+                motif: motif.to_ascii_uppercase().into_bytes(),
+                alleles,
+            });
+        }
+
+        loci.sort_by_key(|locus| (locus.sequence_index, locus.start, locus.end));
+        for pair in loci.windows(2) {
+            if pair[0].sequence_index == pair[1].sequence_index && pair[1].start < pair[0].end {
+                bail!("Repeat loci {} and {} overlap", pair[0].id, pair[1].id);
+            }
+        }
+
+        Ok(loci)
+    }
+
+    /// Returns the copy number of a haplotype's allele.
+    ///
+    /// # Arguments
+    /// * `haplotype` - Zero-based haplotype number
+    pub fn allele(&self, haplotype: usize) -> &CopyNumber {
+        self.alleles
+            .get(haplotype)
+            .unwrap_or(&self.alleles[self.alleles.len() - 1])
+    }
+}
+
+/// A repeat allele placed on the sequences of a haplotype.
+#[derive(Debug, Clone, PartialEq)]
+struct RepeatAllele {
+    id: String,
+    start: usize,
+    end: usize,
+    motif: Vec<u8>,
+    copies: CopyNumber,
+}
+
+/// The repeat alleles of one haplotype, expanded into read templates as they are fetched.
+#[derive(Debug, Clone, Default)]
+pub struct RepeatAlleles {
+    /// Alleles per sequence, sorted by position
+    sequences: Vec<Vec<RepeatAllele>>,
+}
+
+/// A read template with its repeat alleles expanded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpandedTemplate {
+    pub sequence: Vec<u8>,
+    /// Zero-based, exclusive end of the template on the haplotype's sequence
+    pub end: usize,
+    /// Copy number of each repeat allele in the template, by locus ID
+    pub repeats: Vec<(String, usize)>,
+    /// Where the expanded repeat tracts lie in the template, in template order
+    pub tracts: Vec<ExpandedTract>,
+}
+
+/// An expanded repeat tract of a read template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExpandedTract {
+    /// Zero-based offset of the tract in the template
+    pub offset: usize,
+    /// Number of tract bases in the template
+    pub length: usize,
+    /// Number of bases of the haplotype's sequence the tract bases stand for
+    pub replaced: usize,
+}
+
+/// Returns the position on the haplotype's sequence of a template base, following the
+/// template past its expanded repeat tracts.
+///
+/// # Arguments
+/// * `tracts` - Expanded tracts of the template, in template order
+/// * `start` - Zero-based start of the template on the haplotype's sequence
+/// * `position` - Zero-based position of the base in the template
+///
+/// # Returns
+/// The haplotype position, or None if the base lies in an expanded tract
+pub fn haplotype_position(
+    tracts: &[ExpandedTract],
+    start: usize,
+    position: usize,
+) -> Option<usize> {
+    let (mut replaced, mut expanded) = (0, 0);
+    for tract in tracts {
+        if position < tract.offset {
+            break;
+        }
+        if position < tract.offset + tract.length {
+            return None;
+        }
+        replaced += tract.replaced;
+        expanded += tract.length;
+    }
+    Some(start + position + replaced - expanded)
+}
+
+impl RepeatAlleles {
+    /// Places the alleles of a haplotype on its sequences.
+    ///
+    /// # Arguments
+    /// * `loci` - Repeat loci in reference coordinates
+    /// * `haplotype` - Zero-based haplotype number, choosing each locus' allele
+    /// * `layout` - Variants applied to the haplotype, to map loci onto its sequences
+    pub fn new(loci: &[RepeatLocus], haplotype: usize, layout: Option<&VariantLayout>) -> Self {
+        let mut sequences: Vec<Vec<RepeatAllele>> = Vec::new();
+        for locus in loci {
+            let (start, end) = match layout {
+                Some(layout) => (
+                    layout.to_haplotype(locus.sequence_index, locus.start),
+                    layout.to_haplotype(locus.sequence_index, locus.end),
+                ),
+                None => (locus.start, locus.end),
+            };
+            if sequences.len() <= locus.sequence_index {
+                sequences.resize_with(locus.sequence_index + 1, Vec::new);
+            }
+            sequences[locus.sequence_index].push(RepeatAllele {
+                id: locus.id.clone(),
+                start,
+                end,
+                motif: locus.motif.clone(),
+                copies: locus.allele(haplotype).clone(),
+            });
+        }
+        Self { sequences }
+    }
+
+    /// Fetches a read template, replacing the repeat tracts it reaches by alleles with a
+    /// freshly drawn number of copies.
+    ///
+    /// The template starts at `start` and is `length` bases long, or shorter if it reaches
+    /// the end of the sequence. Templates without repeat loci draw nothing from `rng`.
+    ///
+    /// # Arguments
+    /// * `reference` - Sequences of the haplotype
+    /// * `sequence_index` - Position of the sequence
+    /// * `start` - Zero-based start of the template on the haplotype's sequence
+    /// * `length` - Length of the template
+    /// * `rng` - Random stream of the read being generated
+    pub fn fetch(
+        &self,
+        reference: &Reference,
+        sequence_index: usize,
+        start: usize,
+        length: usize,
+        rng: &mut impl Rng,
+    ) -> Result<ExpandedTemplate> {
+        let alleles = self
+            .sequences
+            .get(sequence_index)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let first = alleles.partition_point(|allele| allele.end < start);
+        let sequence_length = reference.sequence_length(sequence_index);
+
+        let mut sequence = Vec::with_capacity(length);
+        let mut position = start;
+        let mut repeats = Vec::new();
+        let mut tracts = Vec::new();
+        for allele in &alleles[first..] {
+            if sequence.len() >= length {
+                break;
+            }
+            // Tracts ending at the template start lie before it, unless they are empty
+            if allele.end <= position && allele.start < allele.end {
+                continue;
+            }
+            if allele.start > position {
+                let take = (allele.start - position).min(length - sequence.len());
+                sequence.extend(reference.fetch(sequence_index, position, take)?);
+                position += take;
+                if sequence.len() >= length {
+                    break;
+                }
+            }
+
+            let copies = allele.copies.sample(rng);
+            let expanded = allele.motif.repeat(copies);
+            let offset = (position - allele.start).min(expanded.len());
+            let take = (expanded.len() - offset).min(length - sequence.len());
+            tracts.push(ExpandedTract {
+                offset: sequence.len(),
+                length: take,
+                replaced: allele.end - position,
+            });
+            sequence.extend_from_slice(&expanded[offset..offset + take]);
+            repeats.push((allele.id.clone(), copies));
+            position = allele.end;
+        }
+        if sequence.len() < length {
+            let take = (length - sequence.len()).min(sequence_length - position);
+            sequence.extend(reference.fetch(sequence_index, position, take)?);
+            position += take;
+        }
+
+        Ok(ExpandedTemplate {
+            sequence,
+            end: position,
+            repeats,
+            tracts,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::fasta::FastaRecord;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn reference() -> Reference {
+        // CAG repeat tract of 3 copies at 10..19
+        Reference::from(vec![FastaRecord {
+            id: "chr1".to_string(),
+            sequence: b"AAAAAAAAAACAGCAGCAGTTTTTTTTTT".to_vec(),
+        }])
+    }
+
+    fn bed(fields: &[&str]) -> BedRecord {
+        BedRecord {
+            chrom: "chr1".to_string(),
+            start: 10,
+            end: 19,
+            fields: fields.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_copy_number() {
+        assert_eq!("30".parse(), Ok(CopyNumber::Fixed(30)));
+        assert_eq!(
+            "200:15".parse(),
+            Ok(CopyNumber::Normal {
+                mean: 200.0,
+                sd: 15.0
+            })
+        );
+        assert_eq!(
+            "50-60".parse(),
+            Ok(CopyNumber::Uniform { min: 50, max: 60 })
+        );
+        assert!("60-50".parse::<CopyNumber>().is_err());
+        assert!("x".parse::<CopyNumber>().is_err());
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let copies = CopyNumber::Normal {
+            mean: 100.0,
+            sd: 5.0,
+        };
+        let draws: Vec<usize> = (0..1000).map(|_| copies.sample(&mut rng)).collect();
+        let mean = draws.iter().sum::<usize>() as f64 / draws.len() as f64;
+        assert!((mean - 100.0).abs() < 1.0, "{}", mean);
+    }
+
+    #[test]
+    fn test_read_loci() {
+        let reference = reference();
+        let loci = RepeatLocus::from_bed(&[bed(&["HTT", "cag", "3,40"])], &reference).unwrap();
+        assert_eq!(loci[0].id, "HTT");
+        assert_eq!(loci[0].motif, b"CAG");
+        assert_eq!(loci[0].allele(1), &CopyNumber::Fixed(40));
+        assert_eq!(loci[0].allele(2), &CopyNumber::Fixed(40));
+
+        assert!(RepeatLocus::from_bed(&[bed(&["HTT"])], &reference).is_err());
+        assert!(RepeatLocus::from_bed(&[bed(&["HTT", "CAG", "many"])], &reference).is_err());
+        assert!(
+            RepeatLocus::from_bed(
+                &[bed(&["A", "CAG", "3"]), bed(&["B", "CAG", "3"])],
+                &reference
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_expand_templates() {
+        let reference = reference();
+        let loci = RepeatLocus::from_bed(&[bed(&["HTT", "CAG", "3,5"])], &reference).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+
+        // The reference allele leaves templates unchanged
+        let alleles = RepeatAlleles::new(&loci, 0, None);
+        let template = alleles.fetch(&reference, 0, 8, 13, &mut rng).unwrap();
+        assert_eq!(template.sequence, b"AACAGCAGCAGTT");
+        assert_eq!(template.end, 21);
+        assert_eq!(template.repeats, vec![("HTT".to_string(), 3)]);
+
+        // Expanded alleles lengthen the template within the same length budget
+        let alleles = RepeatAlleles::new(&loci, 1, None);
+        let template = alleles.fetch(&reference, 0, 8, 13, &mut rng).unwrap();
+        assert_eq!(template.sequence, b"AACAGCAGCAGCA");
+        assert_eq!(template.end, 19);
+        let template = alleles.fetch(&reference, 0, 13, 14, &mut rng).unwrap();
+        assert_eq!(template.sequence, b"CAGCAGCAGCAGTT");
+
+        // Bases past the tract map back to the haplotype, those within it do not
+        let tract = ExpandedTract {
+            offset: 0,
+            length: 12,
+            replaced: 6,
+        };
+        assert_eq!(template.tracts, vec![tract]);
+        assert_eq!(haplotype_position(&template.tracts, 13, 5), None);
+        assert_eq!(haplotype_position(&template.tracts, 13, 12), Some(19));
+        let template = alleles.fetch(&reference, 0, 6, 24, &mut rng).unwrap();
+        assert_eq!(haplotype_position(&template.tracts, 6, 3), Some(9));
+        assert_eq!(haplotype_position(&template.tracts, 6, 4), None);
+        assert_eq!(haplotype_position(&template.tracts, 6, 19), Some(19));
+
+        // Templates away from the locus are fetched as is
+        let template = alleles.fetch(&reference, 0, 0, 5, &mut rng).unwrap();
+        assert_eq!(template.sequence, b"AAAAA");
+        assert!(template.repeats.is_empty());
+    }
+}
@@ -0,0 +1,877 @@
+//! Run metadata of the simulated sequencing run.
+//!
+//! MinKNOW writes run-level fields (run ID, flow cell, sample, basecalling model) and
+//! per-read fields (channel, read number, start time) into FASTQ headers as `key=value` pairs,
+//! while dorado writes them as SAM tags (`ch`, `st`, `rn`, `du`, `ns`, `qs`, `RG`) in BAM
+//! files and FASTQ comments. Reads are spread over the channels of a flow cell at a steady
+//! rate, or a declining one as pores fail, and their durations follow from the translocation
+//! speed. Read lengths and qualities may drift with the time into the run.
+
+use crate::io::bam::BamReader;
+use crate::io::fastq::FastqReader;
+use crate::utils::{derive_rng, mean_qscore};
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use noodles::sam;
+use noodles::sam::alignment::record::data::field::Tag;
+use noodles::sam::alignment::record_buf::data::field::Value;
+use noodles::sam::header::record::value::Map;
+use noodles::sam::header::record::value::map::ReadGroup;
+use noodles::sam::header::record::value::map::read_group::tag as read_group_tag;
+use rand::Rng;
+use std::path::Path;
+
+/// Random stream used for the run identifiers (see [`derive_rng`]).
+const RUN_STREAM: u64 = 3;
+
+/// Start of simulated runs (2024-01-01T00:00:00Z), fixed so that output is reproducible.
+const DEFAULT_START_TIME: f64 = 1_704_067_200.0;
+
+/// Basecalling model reported for simulated runs.
+const DEFAULT_BASECALL_MODEL: &str = "dna_r10.4.1_e8.2_400bps_sup@v5.0.0";
+
+/// Signal sampling rate of R10.4.1 flow cells, in Hz.
+const DEFAULT_SAMPLE_RATE: u32 = 5000;
+
+/// Mean time between the end of a read and the capture of the next molecule, in seconds.
+const CAPTURE_TIME: f64 = 1.0;
+
+/// Drift rates are given per hour of the run.
+const SECONDS_PER_HOUR: f64 = 3600.0;
+
+/// Smallest fraction of its sampled length a read keeps as lengths drift down.
+const MIN_LENGTH_SCALE: f64 = 0.05;
+
+/// Number of time bins the read rate of the input is counted in to estimate pore loss.
+const RATE_BINS: usize = 20;
+
+/// dorado tag with the start time of a read.
+const START_TIME_TAG: Tag = Tag::new(b's', b't');
+
+/// Where the run metadata comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MetadataSource {
+    /// Realistic values for a simulated MinION run
+    Simulated,
+    /// Values and header style of the input reads, simulated where missing
+    Input,
+}
+
+/// How run metadata is written into FASTQ headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum HeaderStyle {
+    /// MinKNOW `key=value` fields (`runid=`, `ch=`, `start_time=`, ...)
+    #[default]
+    Minknow,
+    /// dorado SAM tags (`ch:i:`, `st:Z:`, `RG:Z:`, ...)
+    Dorado,
+}
+
+/// Run-level metadata shared by all reads of a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunMetadata {
+    pub run_id: String,
+    pub flow_cell_id: String,
+    pub sample_id: String,
+    pub protocol_group_id: String,
+    pub basecall_model: String,
+    /// Start of the run, in seconds since the Unix epoch
+    pub start_time: f64,
+    /// Signal sampling rate, in Hz
+    pub sample_rate: u32,
+    pub header_style: HeaderStyle,
+}
+
+impl RunMetadata {
+    /// Creates the metadata of a simulated MinION run.
+    ///
+    /// The run and flow cell IDs are drawn from the seed, so a seed always yields the same run.
+    ///
+    /// # Arguments
+    /// * `seed` - Random seed of the run
+    pub fn simulated(seed: u64) -> Self {
+        let mut rng = derive_rng(seed, RUN_STREAM, 0);
+        let run_id = (0..40)
+            .map(|_| char::from_digit(rng.random_range(0..16), 16).unwrap())
+            .collect();
+        let flow_cell_id = format!("FAX{:05}", rng.random_range(0..100_000));
+        Self {
+            run_id,
+            flow_cell_id,
+            sample_id: "readfaker".to_string(),
+            protocol_group_id: "readfaker".to_string(),
+            basecall_model: DEFAULT_BASECALL_MODEL.to_string(),
+            start_time: DEFAULT_START_TIME,
+            sample_rate: DEFAULT_SAMPLE_RATE,
+            header_style: HeaderStyle::default(),
+        }
+    }
+
+    /// Creates run metadata following the conventions of an input FASTQ or BAM file.
+    ///
+    /// Fields are taken from the MinKNOW `key=value` fields or dorado SAM tags of the first
+    /// FASTQ record, or from the first read group of a BAM header, and the header style from
+    /// the convention found. The start time of the first read is taken as the run start.
+    /// Fields missing from the input are simulated.
+    ///
+    /// # Arguments
+    /// * `path` - Input FASTQ or BAM file
+    /// * `seed` - Random seed for the fields that are simulated
+    ///
+    /// # Errors
+    /// Returns an error if the input cannot be read or carries no run metadata
+    pub fn from_input(path: &Path, seed: u64) -> Result<Self> {
+        let mut metadata = Self::simulated(seed);
+        let file_name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        let found = if file_name.ends_with(".bam") {
+            let reader = BamReader::from_path(path)?;
+            metadata.update_from_header(reader.header())
+        } else {
+            match FastqReader::from_path(path)?.next().transpose()? {
+                Some(record) => {
+                    metadata.update_from_description(&String::from_utf8_lossy(record.description()))
+                }
+                None => false,
+            }
+        };
+        if !found {
+            bail!(
+                "No MinKNOW or dorado run metadata found in {}",
+                path.display()
+            );
+        }
+
+        Ok(metadata)
+    }
+
+    /// Takes run fields from a FASTQ description, in MinKNOW or dorado convention.
+    ///
+    /// # Returns
+    /// True if any run field was found
+    fn update_from_description(&mut self, description: &str) -> bool {
+        let mut found = false;
+        for field in description.split_whitespace() {
+            if let Some((key, value)) = field.split_once('=') {
+                let value = value.to_string();
+                match key {
+                    "runid" => self.run_id = value,
+                    "flow_cell_id" => self.flow_cell_id = value,
+                    "sample_id" => self.sample_id = value,
+                    "protocol_group_id" => self.protocol_group_id = value,
+                    "basecall_model_version_id" => self.basecall_model = value,
+                    "start_time" => match parse_timestamp(&value) {
+                        Some(time) => self.start_time = time,
+                        None => continue,
+                    },
+                    _ => continue,
+                }
+                self.header_style = HeaderStyle::Minknow;
+                found = true;
+            } else if let Some(value) = field.strip_prefix("RG:Z:") {
+                // dorado read groups are `<run ID>_<model>`, with a barcode suffix if demultiplexed
+                if let Some((run_id, model)) = value.split_once('_') {
+                    self.run_id = run_id.to_string();
+                    self.basecall_model = model.to_string();
+                }
+                self.header_style = HeaderStyle::Dorado;
+                found = true;
+            } else if let Some(value) = field.strip_prefix("st:Z:")
+                && let Some(time) = parse_timestamp(value)
+            {
+                self.start_time = time;
+                self.header_style = HeaderStyle::Dorado;
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Takes run fields from the first read group of a SAM header, as written by dorado.
+    ///
+    /// # Returns
+    /// True if the header has a read group
+    fn update_from_header(&mut self, header: &sam::Header) -> bool {
+        let Some((_, read_group)) = header.read_groups().first() else {
+            return false;
+        };
+        let field = |tag| {
+            read_group
+                .other_fields()
+                .get(&tag)
+                .map(|value| value.to_string())
+        };
+        if let Some(description) = field(read_group_tag::DESCRIPTION) {
+            for (key, value) in description
+                .split_whitespace()
+                .filter_map(|f| f.split_once('='))
+            {
+                match key {
+                    "runid" => self.run_id = value.to_string(),
+                    "basecall_model" => self.basecall_model = value.to_string(),
+                    _ => {}
+                }
+            }
+        }
+        if let Some(flow_cell_id) = field(read_group_tag::PLATFORM_UNIT) {
+            self.flow_cell_id = flow_cell_id;
+        }
+        if let Some(sample_id) = field(read_group_tag::SAMPLE) {
+            self.sample_id = sample_id;
+        }
+        if let Some(time) = field(read_group_tag::PRODUCED_AT).and_then(|dt| parse_timestamp(&dt)) {
+            self.start_time = time;
+        }
+        self.header_style = HeaderStyle::Dorado;
+        true
+    }
+
+    /// Sets how the metadata is written into FASTQ headers.
+    pub fn with_header_style(mut self, header_style: HeaderStyle) -> Self {
+        self.header_style = header_style;
+        self
+    }
+
+    /// Returns the read group ID dorado gives to reads of the run.
+    pub fn read_group_id(&self) -> String {
+        format!("{}_{}", self.run_id, self.basecall_model)
+    }
+
+    /// Returns the SAM header read group describing the run, as written by dorado.
+    pub fn read_group(&self) -> Map<ReadGroup> {
+        let mut read_group = Map::<ReadGroup>::default();
+        let fields = read_group.other_fields_mut();
+        fields.insert(
+            read_group_tag::PLATFORM_UNIT,
+            self.flow_cell_id.clone().into(),
+        );
+        fields.insert(
+            read_group_tag::PRODUCED_AT,
+            format_timestamp(self.start_time).into(),
+        );
+        fields.insert(read_group_tag::PLATFORM, "ONT".into());
+        fields.insert(
+            read_group_tag::DESCRIPTION,
+            format!(
+                "runid={} basecall_model={}",
+                self.run_id, self.basecall_model
+            )
+            .into(),
+        );
+        fields.insert(read_group_tag::LIBRARY, self.sample_id.clone().into());
+        fields.insert(read_group_tag::SAMPLE, self.sample_id.clone().into());
+        read_group
+    }
+
+    /// Returns the absolute time of a point in the run, formatted as MinKNOW and dorado do.
+    ///
+    /// # Arguments
+    /// * `offset` - Seconds since the start of the run
+    pub fn timestamp(&self, offset: f64) -> String {
+        format_timestamp(self.start_time + offset)
+    }
+}
+
+/// Where and when a read was sequenced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReadTiming {
+    /// 1-based channel of the flow cell
+    pub channel: u32,
+    /// 1-based well (mux) of the channel
+    pub mux: u8,
+    /// Read number, increasing over the run
+    pub read_number: u64,
+    /// Start of the read, in seconds since the start of the run
+    pub start_time: f64,
+    /// Time the read spent in the pore, in seconds
+    pub duration: f64,
+}
+
+/// Changes of a flow cell over the course of a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RunDrift {
+    /// Hours after which half of the pores have stopped reading, or None if they never fail
+    pub pore_half_life: Option<f64>,
+    /// Relative change of read lengths per hour (-0.01 makes reads 1% shorter every hour)
+    pub length_drift: f64,
+    /// Change of base qualities per hour, in Phred units
+    pub quality_drift: f64,
+}
+
+impl RunDrift {
+    /// Creates a drift model from its rates.
+    ///
+    /// # Arguments
+    /// * `pore_half_life` - Hours after which half of the pores have stopped reading
+    /// * `length_drift` - Relative change of read lengths per hour
+    /// * `quality_drift` - Change of base qualities per hour, in Phred units
+    ///
+    /// # Errors
+    /// Returns an error if the half-life is not positive or a rate is not finite
+    pub fn new(pore_half_life: Option<f64>, length_drift: f64, quality_drift: f64) -> Result<Self> {
+        if let Some(half_life) = pore_half_life
+            && !(half_life > 0.0 && half_life.is_finite())
+        {
+            bail!(
+                "Pore half-life must be a positive number of hours, got {}",
+                half_life
+            );
+        }
+        if !length_drift.is_finite() || !quality_drift.is_finite() {
+            bail!("Drift rates must be finite numbers");
+        }
+        Ok(Self {
+            pore_half_life,
+            length_drift,
+            quality_drift,
+        })
+    }
+
+    /// Estimates the drift of a run from the start times of its reads.
+    ///
+    /// Start times are taken from MinKNOW `start_time=` fields or dorado `st` tags. The pore
+    /// half-life is fitted to the decline of the read rate (reads are assumed to be a uniform
+    /// sample of the run), and length and quality drifts are linear trends over the run.
+    ///
+    /// # Arguments
+    /// * `path` - Input FASTQ or BAM file
+    ///
+    /// # Errors
+    /// Returns an error if the input cannot be read or fewer than two reads have distinct
+    /// start times
+    pub fn from_input(path: &Path) -> Result<Self> {
+        let file_name = path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+
+        // Start time, length and mean quality of every read with a start time
+        let mut reads: Vec<(f64, f64, f64)> = Vec::new();
+        if file_name.ends_with(".bam") {
+            for record in BamReader::from_path(path)? {
+                let record = record?;
+                let Some(Value::String(value)) = record.data().get(&START_TIME_TAG) else {
+                    continue;
+                };
+                if let Some(time) = parse_timestamp(&String::from_utf8_lossy(value)) {
+                    let qualities: Vec<u8> = record
+                        .quality_scores()
+                        .as_ref()
+                        .iter()
+                        .map(|&q| q.saturating_add(33))
+                        .collect();
+                    reads.push((
+                        time,
+                        record.sequence().len() as f64,
+                        f64::from(mean_qscore(&qualities)),
+                    ));
+                }
+            }
+        } else {
+            for record in FastqReader::from_path(path)? {
+                let record = record?;
+                let description = String::from_utf8_lossy(record.description());
+                let time = description.split_whitespace().find_map(|field| {
+                    field
+                        .strip_prefix("start_time=")
+                        .or_else(|| field.strip_prefix("st:Z:"))
+                        .and_then(parse_timestamp)
+                });
+                if let Some(time) = time {
+                    reads.push((
+                        time,
+                        record.sequence().len() as f64,
+                        f64::from(mean_qscore(record.quality_scores())),
+                    ));
+                }
+            }
+        }
+
+        let first = reads.iter().map(|r| r.0).fold(f64::INFINITY, f64::min);
+        let last = reads.iter().map(|r| r.0).fold(f64::NEG_INFINITY, f64::max);
+        if reads.len() < 2 || last <= first {
+            bail!(
+                "Estimating the run drift needs reads with distinct start times, found {} in {}",
+                reads.len(),
+                path.display()
+            );
+        }
+        let hours = |time: f64| (time - first) / SECONDS_PER_HOUR;
+
+        let span = hours(last);
+        let mut counts = [0usize; RATE_BINS];
+        for read in &reads {
+            let bin = (hours(read.0) / span * RATE_BINS as f64) as usize;
+            counts[bin.min(RATE_BINS - 1)] += 1;
+        }
+        // The read rate decays exponentially as pores fail
+        let rate_trend = linear_fit(counts.iter().enumerate().filter(|(_, c)| **c > 0).map(
+            |(bin, &count)| {
+                let midpoint = (bin as f64 + 0.5) * span / RATE_BINS as f64;
+                (midpoint, (count as f64).ln())
+            },
+        ));
+        let pore_half_life = rate_trend
+            .filter(|&(_, slope)| slope < 0.0)
+            .map(|(_, slope)| std::f64::consts::LN_2 / -slope);
+
+        let length_drift = linear_fit(reads.iter().map(|r| (hours(r.0), r.1)))
+            .filter(|&(intercept, _)| intercept > 0.0)
+            .map_or(0.0, |(intercept, slope)| slope / intercept);
+        let quality_drift =
+            linear_fit(reads.iter().map(|r| (hours(r.0), r.2))).map_or(0.0, |(_, slope)| slope);
+
+        Self::new(pore_half_life, length_drift, quality_drift)
+    }
+}
+
+/// Timeline of a run, placing reads on channels as pores capture molecules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunTimeline {
+    channels: u32,
+    bases_per_second: f64,
+    /// Average time between the starts of consecutive reads on the flow cell, in seconds
+    read_interval: f64,
+    drift: RunDrift,
+}
+
+impl RunTimeline {
+    /// Creates a timeline where every channel reads molecules back to back.
+    ///
+    /// # Arguments
+    /// * `channels` - Number of channels of the flow cell (512 for a MinION)
+    /// * `bases_per_second` - Translocation speed
+    /// * `mean_length` - Mean read length, which sets the rate reads start at
+    ///
+    /// # Errors
+    /// Returns an error if there are no channels or the speed is not positive
+    pub fn new(channels: u32, bases_per_second: f64, mean_length: f64) -> Result<Self> {
+        if channels == 0 {
+            bail!("A flow cell needs at least one channel");
+        }
+        if !(bases_per_second > 0.0 && bases_per_second.is_finite()) {
+            bail!(
+                "Translocation speed must be a positive number, got {}",
+                bases_per_second
+            );
+        }
+        let read_time = mean_length / bases_per_second + CAPTURE_TIME;
+        Ok(Self {
+            channels,
+            bases_per_second,
+            read_interval: read_time / f64::from(channels),
+            drift: RunDrift::default(),
+        })
+    }
+
+    /// Lets pores fail and read lengths and qualities change over the run.
+    pub fn with_drift(mut self, drift: RunDrift) -> Self {
+        self.drift = drift;
+        self
+    }
+
+    /// Returns true if read lengths or qualities depend on the time into the run.
+    pub fn has_drift(&self) -> bool {
+        self.drift.length_drift != 0.0 || self.drift.quality_drift != 0.0
+    }
+
+    /// Returns the time a read of the given length spends in the pore, in seconds.
+    pub fn duration(&self, length: usize) -> f64 {
+        length as f64 / self.bases_per_second
+    }
+
+    /// Returns the rate pores fail at, per second, if they do.
+    fn pore_decay(&self) -> Option<f64> {
+        self.drift
+            .pore_half_life
+            .map(|half_life| std::f64::consts::LN_2 / (half_life * SECONDS_PER_HOUR))
+    }
+
+    /// Returns the number of channels still reading at a point in the run.
+    ///
+    /// Active pores decline exponentially, down to a single channel.
+    pub fn active_channels(&self, time: f64) -> u32 {
+        match self.pore_decay() {
+            Some(decay) => {
+                let active = (f64::from(self.channels) * (-decay * time).exp()).ceil();
+                (active as u32).clamp(1, self.channels)
+            }
+            None => self.channels,
+        }
+    }
+
+    /// Draws the expected start time of a read, in seconds since the start of the run.
+    ///
+    /// Reads start in index order, at a rate proportional to the active channels. The expected
+    /// start time sets the drift of the read and the channels it can go to, while the read
+    /// itself waits for its channel (see [`RunTimeline::time_read`]). It is the first draw of
+    /// `rng`, so that it can be drawn again before the read is made.
+    ///
+    /// # Arguments
+    /// * `index` - Zero-based index of the read in the run
+    /// * `rng` - Random stream of the read
+    pub fn start_time<R: Rng>(&self, index: u64, rng: &mut R) -> f64 {
+        let reads = index as f64 + rng.random::<f64>();
+        let Some(decay) = self.pore_decay() else {
+            return reads * self.read_interval;
+        };
+        // Reads started by time t are (1 - e^(-decay t)) / (decay interval) until a single
+        // channel is left, and one every `channels` intervals afterwards
+        let initial_rate = 1.0 / self.read_interval;
+        let channels = f64::from(self.channels);
+        let last_pore_time = channels.ln() / decay;
+        let last_pore_reads = initial_rate / decay * (1.0 - 1.0 / channels);
+        if reads < last_pore_reads {
+            -(1.0 - reads * decay / initial_rate).ln() / decay
+        } else {
+            last_pore_time + (reads - last_pore_reads) * self.read_interval * channels
+        }
+    }
+
+    /// Scales a sampled read length to the length drift at a point in the run.
+    ///
+    /// # Arguments
+    /// * `length` - Length drawn from the length model
+    /// * `time` - Start of the read, in seconds since the start of the run
+    pub fn drift_length(&self, length: usize, time: f64) -> usize {
+        let scale = (1.0 + self.drift.length_drift * time / SECONDS_PER_HOUR).max(MIN_LENGTH_SCALE);
+        ((length as f64 * scale).round() as usize).max(1)
+    }
+
+    /// Shifts sampled qualities by the quality drift at a point in the run.
+    ///
+    /// # Arguments
+    /// * `qualities` - Phred+33 quality string, modified in place
+    /// * `time` - Start of the read, in seconds since the start of the run
+    pub fn drift_qualities(&self, qualities: &mut [u8], time: f64) {
+        let shift = (self.drift.quality_drift * time / SECONDS_PER_HOUR).round() as i32;
+        if shift == 0 {
+            return;
+        }
+        for quality in qualities {
+            let phred = (i32::from(quality.saturating_sub(33)) + shift).clamp(0, 93);
+            *quality = phred as u8 + 33;
+        }
+    }
+
+    /// Places a read on the timeline.
+    ///
+    /// Reads are dealt in turn to the channels active at their expected start time (see
+    /// [`RunTimeline::start_time`]), and each starts once its channel has finished its previous
+    /// read and captured a new molecule, so reads on a channel never overlap. Reads must be
+    /// placed in index order.
+    ///
+    /// # Arguments
+    /// * `index` - Zero-based index of the read in the run
+    /// * `length` - Read length
+    /// * `occupancy` - When the channels finish their reads, updated with this read
+    /// * `rng` - Random stream of the read
+    pub fn time_read<R: Rng>(
+        &self,
+        index: u64,
+        length: usize,
+        occupancy: &mut ChannelOccupancy,
+        rng: &mut R,
+    ) -> ReadTiming {
+        let active = self.active_channels(self.start_time(index, rng));
+        let channel = (index % u64::from(active)) as u32 + 1;
+        // Pores wait an exponentially distributed time to capture a molecule
+        let capture_time = -CAPTURE_TIME * (1.0 - rng.random::<f64>()).ln();
+        let start_time = occupancy.free_at(channel) + capture_time;
+        let duration = self.duration(length);
+        occupancy.occupy(channel, start_time + duration);
+        ReadTiming {
+            channel,
+            mux: rng.random_range(1..=4),
+            // Read numbers leave room for the complement of duplex molecules
+            read_number: 2 * index + 1,
+            start_time,
+            duration,
+        }
+    }
+}
+
+/// When each channel of a run finishes the read it is sequencing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelOccupancy {
+    /// End of the last read of every channel, in seconds since the start of the run
+    free_at: Vec<f64>,
+}
+
+impl ChannelOccupancy {
+    /// Returns the time a channel finishes its last read (0 if it has read nothing yet).
+    pub fn free_at(&self, channel: u32) -> f64 {
+        self.free_at
+            .get(channel as usize - 1)
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Keeps a channel busy until the given time.
+    pub fn occupy(&mut self, channel: u32, until: f64) {
+        let index = channel as usize - 1;
+        if self.free_at.len() <= index {
+            self.free_at.resize(index + 1, 0.0);
+        }
+        self.free_at[index] = self.free_at[index].max(until);
+    }
+}
+
+/// Fits a least-squares line through points.
+///
+/// # Returns
+/// The intercept and slope, or None if the points do not span more than one x value
+fn linear_fit(points: impl Iterator<Item = (f64, f64)>) -> Option<(f64, f64)> {
+    let (mut n, mut sum_x, mut sum_y, mut sum_xx, mut sum_xy) = (0.0, 0.0, 0.0, 0.0, 0.0);
+    for (x, y) in points {
+        n += 1.0;
+        sum_x += x;
+        sum_y += y;
+        sum_xx += x * x;
+        sum_xy += x * y;
+    }
+    let variance = n * sum_xx - sum_x * sum_x;
+    if n < 2.0 || variance <= f64::EPSILON * n * sum_xx {
+        return None;
+    }
+    let slope = (n * sum_xy - sum_x * sum_y) / variance;
+    Some(((sum_y - slope * sum_x) / n, slope))
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC timestamp with milliseconds.
+pub fn format_timestamp(time: f64) -> String {
+    let millis = (time * 1000.0).round() as i64;
+    let (days, millis_of_day) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+    let (year, month, day) = civil_from_days(days);
+    let seconds = millis_of_day / 1000;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}+00:00",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        millis_of_day % 1000
+    )
+}
+
+/// Parses an ISO 8601 timestamp (`YYYY-MM-DDTHH:MM:SS[.fff](Z|±HH:MM)`) into seconds since
+/// the Unix epoch.
+pub fn parse_timestamp(timestamp: &str) -> Option<f64> {
+    let (date, time) = timestamp.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+
+    let (time, offset) = if let Some(time) = time.strip_suffix('Z') {
+        (time, 0.0)
+    } else {
+        let sign_position = time.rfind(['+', '-'])?;
+        let (time, zone) = time.split_at(sign_position);
+        let (hours, minutes) = zone[1..].split_once(':').unwrap_or((&zone[1..], "0"));
+        let offset = (hours.parse::<f64>().ok()? * 60.0 + minutes.parse::<f64>().ok()?) * 60.0;
+        (
+            time,
+            if zone.starts_with('-') {
+                -offset
+            } else {
+                offset
+            },
+        )
+    };
+    let mut time = time.splitn(3, ':').map(str::parse::<f64>);
+    let (hours, minutes, seconds) = (time.next()?.ok()?, time.next()?.ok()?, time.next()?.ok()?);
+
+    let days = days_from_civil(year, month, day) as f64;
+    Some(days * 86_400.0 + hours * 3600.0 + minutes * 60.0 + seconds - offset)
+}
+
+/// Converts a proleptic Gregorian date into days since the Unix epoch.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts days since the Unix epoch into a proleptic Gregorian (year, month, day).
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_timestamps() {
+        assert_eq!(
+            format_timestamp(DEFAULT_START_TIME + 90.5),
+            "2024-01-01T00:01:30.500+00:00"
+        );
+        assert_eq!(
+            format_timestamp(951_782_400.0),
+            "2000-02-29T00:00:00.000+00:00"
+        );
+        assert_eq!(
+            parse_timestamp("2024-01-01T02:01:30.5+02:00"),
+            Some(DEFAULT_START_TIME + 90.5)
+        );
+        assert_eq!(parse_timestamp("2000-02-29T00:00:00Z"), Some(951_782_400.0));
+        assert_eq!(parse_timestamp("2024-01-01"), None);
+    }
+
+    #[test]
+    fn test_metadata_from_descriptions() {
+        let mut minknow = RunMetadata::simulated(1);
+        assert!(minknow.update_from_description(
+            "runid=abc123 read=17 ch=42 start_time=2024-03-05T10:00:00Z flow_cell_id=PAW00001 \
+             protocol_group_id=pg sample_id=s1 basecall_model_version_id=dna_r10.4.1_e8.2_400bps_hac@v4.3.0"
+        ));
+        assert_eq!(minknow.run_id, "abc123");
+        assert_eq!(minknow.flow_cell_id, "PAW00001");
+        assert_eq!(minknow.sample_id, "s1");
+        assert_eq!(minknow.basecall_model, "dna_r10.4.1_e8.2_400bps_hac@v4.3.0");
+        assert_eq!(
+            format_timestamp(minknow.start_time),
+            "2024-03-05T10:00:00.000+00:00"
+        );
+        assert_eq!(minknow.header_style, HeaderStyle::Minknow);
+
+        let mut dorado = RunMetadata::simulated(1);
+        assert!(dorado.update_from_description(
+            "qs:f:14.2\tch:i:7\tst:Z:2024-03-05T10:00:00.000+00:00\tRG:Z:abc123_dna_r10.4.1_e8.2_400bps_sup@v5.0.0"
+        ));
+        assert_eq!(dorado.run_id, "abc123");
+        assert_eq!(dorado.basecall_model, "dna_r10.4.1_e8.2_400bps_sup@v5.0.0");
+        assert_eq!(dorado.header_style, HeaderStyle::Dorado);
+
+        assert!(!RunMetadata::simulated(1).update_from_description("HP:i:1"));
+        assert_eq!(RunMetadata::simulated(1), RunMetadata::simulated(1));
+        assert_ne!(
+            RunMetadata::simulated(1).run_id,
+            RunMetadata::simulated(2).run_id
+        );
+    }
+
+    #[test]
+    fn test_timeline() {
+        let timeline = RunTimeline::new(512, 400.0, 4000.0).unwrap();
+        let mut occupancy = ChannelOccupancy::default();
+        let timings: Vec<ReadTiming> = (0..=5120)
+            .map(|index| {
+                let mut rng = StdRng::seed_from_u64(index);
+                timeline.time_read(index, 4000, &mut occupancy, &mut rng)
+            })
+            .collect();
+        assert_eq!((timings[0].channel, timings[513].channel), (1, 2));
+        assert!((1..=4).contains(&timings[0].mux));
+        assert_eq!(timings[0].duration, 10.0);
+
+        // Reads on a channel follow each other without overlapping
+        for (previous, next) in timings.iter().zip(&timings[512..]) {
+            assert_eq!(previous.channel, next.channel);
+            assert!(next.start_time > previous.start_time + previous.duration);
+        }
+        // Every channel has read ten 11 s molecules (with capture) before read 5120
+        assert!((timings[5120].start_time - 110.0).abs() < 10.0);
+        assert!(RunTimeline::new(0, 400.0, 4000.0).is_err());
+    }
+
+    #[test]
+    fn test_drift() {
+        let drift = RunDrift::new(Some(1.0), -0.1, -2.0).unwrap();
+        let timeline = RunTimeline::new(512, 400.0, 4000.0)
+            .unwrap()
+            .with_drift(drift);
+        assert!(timeline.has_drift());
+        assert_eq!(timeline.active_channels(0.0), 512);
+        assert_eq!(timeline.active_channels(3600.0), 256);
+        assert_eq!(timeline.active_channels(1e6), 1);
+
+        // Reads start at a rate that halves every hour
+        let rate = 512.0 / 11.0;
+        let half_hour_reads = rate * 3600.0 / std::f64::consts::LN_2 * (1.0 - 0.5f64.sqrt());
+        let mut rng = StdRng::seed_from_u64(1);
+        let start = timeline.start_time(half_hour_reads as u64, &mut rng);
+        assert!((start - 1800.0).abs() < 1.0);
+        // Start times increase past the point where a single channel is left
+        let mut rng = StdRng::seed_from_u64(1);
+        let late = timeline.start_time(1_000_000, &mut rng);
+        assert!(late > 9.0 * 3600.0);
+        let mut occupancy = ChannelOccupancy::default();
+        assert_eq!(
+            timeline
+                .time_read(1_000_000, 100, &mut occupancy, &mut rng)
+                .channel,
+            1
+        );
+
+        assert_eq!(timeline.drift_length(1000, 0.0), 1000);
+        assert_eq!(timeline.drift_length(1000, 7200.0), 800);
+        assert_eq!(timeline.drift_length(1000, 1e6), 50);
+        let mut qualities = b"+5?".to_vec();
+        timeline.drift_qualities(&mut qualities, 5400.0);
+        // Q10, Q20, Q30 lose 3 (rounded from 2.5 hours x -2)
+        assert_eq!(qualities, b"(2<".to_vec());
+
+        assert!(RunDrift::new(Some(0.0), 0.0, 0.0).is_err());
+        assert!(RunDrift::new(None, f64::NAN, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_drift_from_input() {
+        let path = std::env::temp_dir().join("readfaker_drift_test.fastq");
+        let mut fastq = String::new();
+        // Reads get rarer, shorter and worse over 10 hours
+        let mut id = 0;
+        for hour in 0..10 {
+            let reads = (100.0 * 0.5f64.powf(hour as f64 / 5.0)) as usize;
+            for i in 0..reads {
+                let time =
+                    1_704_067_200.0 + hour as f64 * 3600.0 + i as f64 * 3600.0 / reads as f64;
+                let length = 1000 - 50 * hour;
+                let quality = (b'?' - hour as u8) as char;
+                fastq.push_str(&format!(
+                    "@r{} start_time={}\n{}\n+\n{}\n",
+                    id,
+                    format_timestamp(time),
+                    "A".repeat(length),
+                    quality.to_string().repeat(length)
+                ));
+                id += 1;
+            }
+        }
+        std::fs::write(&path, fastq).unwrap();
+        let drift = RunDrift::from_input(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let half_life = drift.pore_half_life.unwrap();
+        assert!((half_life - 5.0).abs() < 0.5, "half-life {}", half_life);
+        assert!((drift.length_drift + 0.05).abs() < 0.01);
+        assert!((drift.quality_drift + 1.0).abs() < 0.05);
+    }
+}
@@ -0,0 +1,717 @@
+//! Structural variant simulation.
+//!
+//! Structural variants (SVs) are either placed at random with log-uniform sizes or read from
+//! a BED list. They are turned into [`Variant`]s and applied to the reference like any other
+//! variant, and can be described as VCF records for truth output.
+
+use crate::io::bed::BedRecord;
+use crate::io::fasta::FastaRecord;
+use crate::io::vcf::VcfRecord;
+use crate::utils::derive_rng;
+use crate::variants::{Variant, VariantKind, reverse_complement};
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use rand::Rng;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+
+/// Random stream used for placing structural variants (see [`derive_rng`]).
+const SV_STREAM: u64 = 1;
+
+/// Attempts to place a single random SV before giving up.
+const MAX_PLACEMENT_ATTEMPTS: usize = 1000;
+
+/// Meta-information lines describing the records produced by [`truth_records`].
+pub const VCF_META_LINES: &[&str] = &[
+    "ALT=<ID=DEL,Description=\"Deletion\">",
+    "ALT=<ID=INV,Description=\"Inversion\">",
+    "ALT=<ID=DUP,Description=\"Tandem duplication\">",
+    "INFO=<ID=SVTYPE,Number=1,Type=String,Description=\"Type of structural variant\">",
+    "INFO=<ID=SVLEN,Number=1,Type=Integer,Description=\"Difference in length between REF and ALT alleles\">",
+    "INFO=<ID=END,Number=1,Type=Integer,Description=\"End position of the variant\">",
+    "INFO=<ID=MATEID,Number=1,Type=String,Description=\"ID of the mate breakend\">",
+    "INFO=<ID=EVENT,Number=1,Type=String,Description=\"ID of the event the record belongs to\">",
+];
+
+/// Type of a structural variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SvType {
+    /// Deletion of a segment
+    #[value(name = "del")]
+    Deletion,
+    /// Insertion of a random sequence
+    #[value(name = "ins")]
+    Insertion,
+    /// Inversion of a segment
+    #[value(name = "inv")]
+    Inversion,
+    /// Tandem duplication of a segment
+    #[value(name = "dup")]
+    Duplication,
+    /// Segment cut from one sequence and inserted into another
+    #[value(name = "tra")]
+    Translocation,
+}
+
+/// A structural variant on the reference sequences.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructuralVariant {
+    pub id: String,
+    pub sv_type: SvType,
+    pub sequence_index: usize,
+    /// Zero-based start of the affected segment (insertion point for insertions)
+    pub start: usize,
+    /// Zero-based, exclusive end of the affected segment (equal to `start` for insertions)
+    pub end: usize,
+    /// Inserted sequence, for insertions
+    pub inserted: Vec<u8>,
+    /// Sequence index and zero-based insertion point of a translocated segment
+    pub destination: Option<(usize, usize)>,
+}
+
+impl StructuralVariant {
+    /// Returns the size of the variant: the inserted length for insertions, or the
+    /// segment length otherwise.
+    pub fn size(&self) -> usize {
+        match self.sv_type {
+            SvType::Insertion => self.inserted.len(),
+            _ => self.end - self.start,
+        }
+    }
+
+    /// Returns the sequence edits that produce the variant.
+    ///
+    /// # Arguments
+    /// * `sequences` - Reference sequences the variant refers to
+    pub fn variants(&self, sequences: &[FastaRecord]) -> Vec<Variant> {
+        let segment = &sequences[self.sequence_index].sequence[self.start..self.end];
+        let variant = |kind, sequence_index, start, end, replacement| Variant {
+            label: self.id.clone(),
+            kind,
+            sequence_index,
+            start,
+            end,
+            replacement,
+        };
+        let (sequence_index, start, end) = (self.sequence_index, self.start, self.end);
+
+        match (self.sv_type, self.destination) {
+            (SvType::Deletion, _) => {
+                vec![variant(
+                    VariantKind::Deletion,
+                    sequence_index,
+                    start,
+                    end,
+                    Vec::new(),
+                )]
+            }
+            (SvType::Insertion, _) => vec![variant(
+                VariantKind::Insertion,
+                sequence_index,
+                start,
+                start,
+                self.inserted.clone(),
+            )],
+            (SvType::Inversion, _) => vec![variant(
+                VariantKind::Inversion,
+                sequence_index,
+                start,
+                end,
+                reverse_complement(segment),
+            )],
+            (SvType::Duplication, _) => vec![variant(
+                VariantKind::Duplication,
+                sequence_index,
+                end,
+                end,
+                segment.to_vec(),
+            )],
+            (SvType::Translocation, Some((destination_index, position))) => vec![
+                variant(
+                    VariantKind::Translocation,
+                    sequence_index,
+                    start,
+                    end,
+                    Vec::new(),
+                ),
+                variant(
+                    VariantKind::Translocation,
+                    destination_index,
+                    position,
+                    position,
+                    segment.to_vec(),
+                ),
+            ],
+            (SvType::Translocation, None) => Vec::new(),
+        }
+    }
+
+    /// Describes the variant as VCF records.
+    ///
+    /// Deletions, inversions and duplications use symbolic alleles and insertions carry their
+    /// sequence. Translocations are written as the deletion at their source plus the two
+    /// breakend pairs joining the segment to its destination, all sharing an `EVENT` ID.
+    ///
+    /// # Returns
+    /// Pairs of (sequence index, record)
+    pub fn to_vcf(&self, sequences: &[FastaRecord]) -> Vec<(usize, VcfRecord)> {
+        let chrom = |index: usize| sequences[index].id.as_str();
+        let base = |index: usize, position: usize| {
+            (sequences[index].sequence[position] as char)
+                .to_ascii_uppercase()
+                .to_string()
+        };
+        let record = |index: usize, position: usize, id: String, alternate: String, info| {
+            let record = VcfRecord {
+                chrom: chrom(index).to_string(),
+                position,
+                id: Some(id),
+                reference: base(index, position - 1),
+                alternates: vec![alternate],
+                info,
+                format: None,
+                samples: Vec::new(),
+            };
+            (index, record)
+        };
+
+        let (index, start, end, size) = (self.sequence_index, self.start, self.end, self.size());
+        match (self.sv_type, self.destination) {
+            (SvType::Deletion, _) => vec![record(
+                index,
+                start,
+                self.id.clone(),
+                "<DEL>".to_string(),
+                format!("SVTYPE=DEL;SVLEN=-{};END={}", size, end),
+            )],
+            (SvType::Insertion, _) => vec![record(
+                index,
+                start,
+                self.id.clone(),
+                format!(
+                    "{}{}",
+                    base(index, start - 1),
+                    String::from_utf8_lossy(&self.inserted).to_ascii_uppercase()
+                ),
+                format!("SVTYPE=INS;SVLEN={};END={}", size, start),
+            )],
+            (SvType::Inversion, _) => vec![record(
+                index,
+                start,
+                self.id.clone(),
+                "<INV>".to_string(),
+                format!("SVTYPE=INV;SVLEN={};END={}", size, end),
+            )],
+            (SvType::Duplication, _) => vec![record(
+                index,
+                start,
+                self.id.clone(),
+                "<DUP>".to_string(),
+                format!("SVTYPE=DUP;SVLEN={};END={}", size, end),
+            )],
+            (SvType::Translocation, Some((destination, position))) => {
+                let id = |n: usize| format!("{}_{}", self.id, n);
+                let breakend =
+                    |mate: usize| format!("SVTYPE=BND;MATEID={};EVENT={}", id(mate), self.id);
+                vec![
+                    record(
+                        index,
+                        start,
+                        id(0),
+                        "<DEL>".to_string(),
+                        format!("SVTYPE=DEL;SVLEN=-{};END={};EVENT={}", size, end, self.id),
+                    ),
+                    // Destination base before the insertion, joined to the segment start
+                    record(
+                        destination,
+                        position,
+                        id(1),
+                        format!(
+                            "{}[{}:{}[",
+                            base(destination, position - 1),
+                            chrom(index),
+                            start + 1
+                        ),
+                        breakend(2),
+                    ),
+                    record(
+                        index,
+                        start + 1,
+                        id(2),
+                        format!(
+                            "]{}:{}]{}",
+                            chrom(destination),
+                            position,
+                            base(index, start)
+                        ),
+                        breakend(1),
+                    ),
+                    // Segment end, joined to the destination base after the insertion
+                    record(
+                        index,
+                        end,
+                        id(3),
+                        format!(
+                            "{}[{}:{}[",
+                            base(index, end - 1),
+                            chrom(destination),
+                            position + 1
+                        ),
+                        breakend(4),
+                    ),
+                    record(
+                        destination,
+                        position + 1,
+                        id(4),
+                        format!("]{}:{}]{}", chrom(index), end, base(destination, position)),
+                        breakend(3),
+                    ),
+                ]
+            }
+            (SvType::Translocation, None) => Vec::new(),
+        }
+    }
+}
+
+/// Simulator placing structural variants at random positions of the reference.
+pub struct SvSimulator {
+    types: Vec<SvType>,
+    min_size: usize,
+    max_size: usize,
+}
+
+impl SvSimulator {
+    /// Creates a new simulator.
+    ///
+    /// # Arguments
+    /// * `types` - SV types to simulate, used in turn
+    /// * `min_size` - Minimum SV size
+    /// * `max_size` - Maximum SV size; sizes are drawn log-uniformly between both bounds
+    ///
+    /// # Errors
+    /// Returns an error if no types are given or the size bounds are invalid
+    pub fn new(types: Vec<SvType>, min_size: usize, max_size: usize) -> Result<Self> {
+        if types.is_empty() {
+            bail!("At least one SV type is required");
+        }
+        if min_size == 0 || min_size > max_size {
+            bail!(
+                "SV sizes must satisfy 1 <= minimum <= maximum, got {}-{}",
+                min_size,
+                max_size
+            );
+        }
+        Ok(Self {
+            types,
+            min_size,
+            max_size,
+        })
+    }
+
+    /// Places `count` non-overlapping SVs on the reference sequences.
+    ///
+    /// SV types are used in turn so that they are evenly represented. Positions are uniform
+    /// over the reference, and translocations move their segment into a different sequence.
+    ///
+    /// # Arguments
+    /// * `count` - Number of SVs to place
+    /// * `sequences` - Reference sequences
+    /// * `existing` - Already defined SVs to avoid
+    /// * `seed` - Random seed
+    ///
+    /// # Errors
+    /// Returns an error if an SV cannot be placed without overlapping another one
+    pub fn simulate(
+        &self,
+        count: usize,
+        sequences: &[FastaRecord],
+        existing: &[StructuralVariant],
+        seed: u64,
+    ) -> Result<Vec<StructuralVariant>> {
+        let mut rng = derive_rng(seed, SV_STREAM, 0);
+        let mut occupied = Occupied::default();
+        for sv in existing {
+            occupied.insert(sv);
+        }
+
+        let mut svs = Vec::with_capacity(count);
+        for i in 0..count {
+            let sv_type = self.types[i % self.types.len()];
+            if sv_type == SvType::Translocation && sequences.len() < 2 {
+                bail!("Translocations require at least two reference sequences");
+            }
+            let id = format!("sv{}", existing.len() + i + 1);
+
+            let sv = (0..MAX_PLACEMENT_ATTEMPTS)
+                .find_map(|_| {
+                    let sv = self.propose(sv_type, id.clone(), sequences, &mut rng)?;
+                    occupied.is_free(&sv).then_some(sv)
+                })
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Could not place {} without overlapping other SVs after {} attempts",
+                        id,
+                        MAX_PLACEMENT_ATTEMPTS
+                    )
+                })?;
+            occupied.insert(&sv);
+            svs.push(sv);
+        }
+
+        Ok(svs)
+    }
+
+    /// Proposes a random SV, or None if the drawn size does not fit the drawn sequence.
+    fn propose(
+        &self,
+        sv_type: SvType,
+        id: String,
+        sequences: &[FastaRecord],
+        rng: &mut StdRng,
+    ) -> Option<StructuralVariant> {
+        let size = (rng.random_range((self.min_size as f64).ln()..=(self.max_size as f64).ln()))
+            .exp()
+            .round() as usize;
+        let sequence_index = choose_sequence(sequences, None, rng);
+        let length = sequences[sequence_index].sequence.len();
+
+        // Keep a padding base before the variant and at least one base after it
+        let span = if sv_type == SvType::Insertion {
+            0
+        } else {
+            size
+        };
+        if length < span + 2 {
+            return None;
+        }
+        let start = rng.random_range(1..length - span);
+
+        let inserted = if sv_type == SvType::Insertion {
+            random_sequence(size, rng)
+        } else {
+            Vec::new()
+        };
+        let destination = (sv_type == SvType::Translocation).then(|| {
+            let index = choose_sequence(sequences, Some(sequence_index), rng);
+            let length = sequences[index].sequence.len();
+            (index, rng.random_range(1..length.max(2)))
+        });
+        if destination.is_some_and(|(index, _)| sequences[index].sequence.len() < 2) {
+            return None;
+        }
+
+        Some(StructuralVariant {
+            id,
+            sv_type,
+            sequence_index,
+            start,
+            end: start + span,
+            inserted,
+            destination,
+        })
+    }
+}
+
+/// Reads SVs from BED records.
+///
+/// The name column gives the SV type (`DEL`, `INS`, `INV`, `DUP` or `TRA`) and the interval
+/// the affected segment. Insertions happen at the start of the interval; the 5th column holds
+/// their sequence, or they get a random sequence as long as the interval. Translocations move
+/// the segment to the position given in the 5th column as `CHROM:POS` (inserted after the
+/// 1-based position `POS`).
+///
+/// # Arguments
+/// * `records` - BED records describing the SVs
+/// * `sequences` - Reference sequences
+/// * `seed` - Random seed for inserted sequences
+///
+/// # Errors
+/// Returns an error if a record has an unknown type, refers to an unknown sequence, lies
+/// outside its sequence or overlaps another record
+pub fn read_sv_list(
+    records: &[BedRecord],
+    sequences: &[FastaRecord],
+    seed: u64,
+) -> Result<Vec<StructuralVariant>> {
+    let mut rng = derive_rng(seed, SV_STREAM, 1);
+    let sequence_indices: HashMap<&str, usize> = sequences
+        .iter()
+        .enumerate()
+        .map(|(i, record)| (record.id.as_str(), i))
+        .collect();
+    let locate = |chrom: &str| {
+        sequence_indices
+            .get(chrom)
+            .copied()
+            .ok_or_else(|| anyhow!("SV refers to a sequence not in the reference: {}", chrom))
+    };
+
+    let mut svs = Vec::with_capacity(records.len());
+    let mut occupied = Occupied::default();
+    for (i, record) in records.iter().enumerate() {
+        let region = format!("{}:{}-{}", record.chrom, record.start, record.end);
+        let name = record
+            .name()
+            .ok_or_else(|| anyhow!("SV {} has no type in the name column", region))?;
+        let sv_type = SvType::from_str(name, true)
+            .map_err(|_| anyhow!("SV {} has an unknown type '{}'", region, name))?;
+        let sequence_index = locate(&record.chrom)?;
+        let length = sequences[sequence_index].sequence.len();
+        if record.start == 0 || record.end >= length || record.start > record.end {
+            bail!(
+                "SV {} must leave at least one base on each side of its sequence ({} bp)",
+                region,
+                length
+            );
+        }
+
+        let (end, inserted, destination) = match sv_type {
+            SvType::Insertion => {
+                let inserted = match record.field(1) {
+                    Some(sequence) => sequence.as_bytes().to_vec(),
+                    None => random_sequence(record.len(), &mut rng),
+                };
+                (record.start, inserted, None)
+            }
+            SvType::Translocation => {
+                let target = record.field(1).ok_or_else(|| {
+                    anyhow!("Translocation {} needs a CHROM:POS destination", region)
+                })?;
+                let (chrom, position) = target
+                    .rsplit_once(':')
+                    .and_then(|(chrom, position)| Some((chrom, position.parse::<usize>().ok()?)))
+                    .ok_or_else(|| anyhow!("Invalid translocation destination '{}'", target))?;
+                let destination_index = locate(chrom)?;
+                if destination_index == sequence_index {
+                    bail!(
+                        "Translocation {} must move its segment to another sequence",
+                        region
+                    );
+                }
+                let destination_length = sequences[destination_index].sequence.len();
+                if position == 0 || position >= destination_length {
+                    bail!(
+                        "Translocation destination {} must leave at least one base on each side of its sequence ({} bp)",
+                        target,
+                        destination_length
+                    );
+                }
+                (record.end, Vec::new(), Some((destination_index, position)))
+            }
+            _ => (record.end, Vec::new(), None),
+        };
+        if sv_type != SvType::Insertion && record.is_empty() {
+            bail!("SV {} has an empty segment", region);
+        }
+
+        let sv = StructuralVariant {
+            id: format!("sv{}", i + 1),
+            sv_type,
+            sequence_index,
+            start: record.start,
+            end,
+            inserted,
+            destination,
+        };
+        // Overlapping SVs would be applied only in part
+        if !occupied.is_free(&sv) {
+            bail!(
+                "SV {} overlaps or touches another SV of the list; SVs need a base between them",
+                region
+            );
+        }
+        occupied.insert(&sv);
+        svs.push(sv);
+    }
+
+    Ok(svs)
+}
+
+/// Builds the truth VCF records of a set of SVs, sorted by position.
+pub fn truth_records(svs: &[StructuralVariant], sequences: &[FastaRecord]) -> Vec<VcfRecord> {
+    let mut records: Vec<(usize, VcfRecord)> =
+        svs.iter().flat_map(|sv| sv.to_vcf(sequences)).collect();
+    records.sort_by_key(|(index, record)| (*index, record.position));
+    records.into_iter().map(|(_, record)| record).collect()
+}
+
+/// Reference intervals taken by SVs, including a margin of one base on each side.
+#[derive(Default)]
+struct Occupied {
+    intervals: HashMap<usize, Vec<(usize, usize)>>,
+}
+
+impl Occupied {
+    fn spans(sv: &StructuralVariant) -> Vec<(usize, usize, usize)> {
+        let mut spans = vec![(sv.sequence_index, sv.start - 1, sv.end + 1)];
+        if let Some((index, position)) = sv.destination {
+            spans.push((index, position - 1, position + 1));
+        }
+        spans
+    }
+
+    fn is_free(&self, sv: &StructuralVariant) -> bool {
+        Self::spans(sv).iter().all(|&(index, start, end)| {
+            self.intervals
+                .get(&index)
+                .is_none_or(|taken| taken.iter().all(|&(s, e)| end <= s || start >= e))
+        })
+    }
+
+    fn insert(&mut self, sv: &StructuralVariant) {
+        for (index, start, end) in Self::spans(sv) {
+            self.intervals.entry(index).or_default().push((start, end));
+        }
+    }
+}
+
+/// Chooses a sequence with probability proportional to its length.
+fn choose_sequence(sequences: &[FastaRecord], exclude: Option<usize>, rng: &mut StdRng) -> usize {
+    let weight = |i: usize| {
+        if Some(i) == exclude {
+            0
+        } else {
+            sequences[i].sequence.len()
+        }
+    };
+    let total: usize = (0..sequences.len()).map(weight).sum();
+    let mut point = rng.random_range(0..total.max(1));
+    for i in 0..sequences.len() {
+        if point < weight(i) {
+            return i;
+        }
+        point -= weight(i);
+    }
+    sequences.len() - 1
+}
+
+/// Returns a random nucleotide sequence.
+fn random_sequence(length: usize, rng: &mut StdRng) -> Vec<u8> {
+    (0..length)
+        .map(|_| b"ACGT"[rng.random_range(0..4)])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::variants::apply_variants;
+
+    fn sequences() -> Vec<FastaRecord> {
+        vec![
+            FastaRecord {
+                id: "chr1".to_string(),
+                sequence: b"AAAACCCCGGGGTTTT".to_vec(),
+            },
+            FastaRecord {
+                id: "chr2".to_string(),
+                sequence: b"ACGTACGTACGT".to_vec(),
+            },
+        ]
+    }
+
+    fn bed(chrom: &str, start: usize, end: usize, fields: &[&str]) -> BedRecord {
+        BedRecord {
+            chrom: chrom.to_string(),
+            start,
+            end,
+            fields: fields.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_sv_list_applies_to_sequences() {
+        let records = vec![
+            bed("chr1", 4, 8, &["INV"]),
+            bed("chr1", 12, 12, &["INS", "TTT"]),
+            bed("chr2", 2, 4, &["TRA", "chr1:2"]),
+        ];
+        let svs = read_sv_list(&records, &sequences(), 1).unwrap();
+        let variants = svs
+            .iter()
+            .flat_map(|sv| sv.variants(&sequences()))
+            .collect();
+        let (modified, _, skipped) = apply_variants(&sequences(), variants);
+
+        assert_eq!(skipped, 0);
+        assert_eq!(modified[0].sequence, b"AAGTAAGGGGGGGGTTTTTTT");
+        assert_eq!(modified[1].sequence, b"ACACGTACGT");
+
+        assert!(read_sv_list(&[bed("chr1", 0, 4, &["DEL"])], &sequences(), 1).is_err());
+        assert!(read_sv_list(&[bed("chr1", 2, 4, &["CNV"])], &sequences(), 1).is_err());
+        assert!(read_sv_list(&[bed("chr1", 2, 4, &["TRA", "chr1:8"])], &sequences(), 1).is_err());
+
+        // Translocation destinations cannot land on another SV
+        let overlapping = vec![
+            bed("chr1", 4, 8, &["INV"]),
+            bed("chr2", 2, 4, &["TRA", "chr1:6"]),
+        ];
+        assert!(read_sv_list(&overlapping, &sequences(), 1).is_err());
+        let touching = vec![bed("chr1", 4, 8, &["DEL"]), bed("chr1", 8, 10, &["INV"])];
+        assert!(read_sv_list(&touching, &sequences(), 1).is_err());
+    }
+
+    #[test]
+    fn test_truth_records() {
+        let records = vec![
+            bed("chr1", 4, 8, &["DEL"]),
+            bed("chr2", 2, 4, &["TRA", "chr1:12"]),
+        ];
+        let svs = read_sv_list(&records, &sequences(), 1).unwrap();
+        let lines: Vec<String> = truth_records(&svs, &sequences())
+            .iter()
+            .map(|r| {
+                format!(
+                    "{}:{} {} {} {}",
+                    r.chrom, r.position, r.reference, r.alternates[0], r.info
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "chr1:4 A <DEL> SVTYPE=DEL;SVLEN=-4;END=8",
+                "chr1:12 G G[chr2:3[ SVTYPE=BND;MATEID=sv2_2;EVENT=sv2",
+                "chr1:13 T ]chr2:4]T SVTYPE=BND;MATEID=sv2_3;EVENT=sv2",
+                "chr2:2 C <DEL> SVTYPE=DEL;SVLEN=-2;END=4;EVENT=sv2",
+                "chr2:3 G ]chr1:12]G SVTYPE=BND;MATEID=sv2_1;EVENT=sv2",
+                "chr2:4 T T[chr1:13[ SVTYPE=BND;MATEID=sv2_4;EVENT=sv2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_simulate_places_non_overlapping_svs() {
+        let sequences = vec![
+            FastaRecord {
+                id: "chr1".to_string(),
+                sequence: vec![b'A'; 20_000],
+            },
+            FastaRecord {
+                id: "chr2".to_string(),
+                sequence: vec![b'C'; 10_000],
+            },
+        ];
+        let types = vec![
+            SvType::Deletion,
+            SvType::Insertion,
+            SvType::Inversion,
+            SvType::Duplication,
+            SvType::Translocation,
+        ];
+        let simulator = SvSimulator::new(types, 50, 500).unwrap();
+        let svs = simulator.simulate(20, &sequences, &[], 7).unwrap();
+
+        assert_eq!(svs.len(), 20);
+        assert!(svs.iter().all(|sv| (50..=500).contains(&sv.size())));
+        assert_eq!(svs, simulator.simulate(20, &sequences, &[], 7).unwrap());
+
+        let variants = svs.iter().flat_map(|sv| sv.variants(&sequences)).collect();
+        let (_, _, skipped) = apply_variants(&sequences, variants);
+        assert_eq!(skipped, 0);
+
+        assert!(SvSimulator::new(Vec::new(), 50, 500).is_err());
+        assert!(SvSimulator::new(vec![SvType::Deletion], 500, 50).is_err());
+    }
+}
@@ -0,0 +1,477 @@
+//! Transcriptome sampling for cDNA reads.
+//!
+//! In RNA mode reads are drawn from transcript sequences instead of genome windows. Each
+//! read is one cDNA molecule: a transcript chosen in proportion to its expression, either
+//! full-length or missing part of its 5' end, followed by its poly-A tail. As in cDNA
+//! protocols, where either strand of the double-stranded cDNA can be sequenced, half of the
+//! reads are reverse complemented and start with the poly-T stretch instead.
+//!
+//! Direct RNA reads come from the RNA molecule itself: they are always sense-strand. The pore
+//! reads them from the 3' end, which is why truncated molecules keep their 3' end, but like
+//! basecallers they are reported 5'→3', ending with the poly-A tail.
+
+use crate::io::fasta::{FastaRecord, Reference};
+use crate::io::gtf::GtfRecord;
+use crate::models::{ErrorModel, LengthModel};
+use crate::repeats::CopyNumber;
+use crate::variants::reverse_complement;
+use anyhow::{Result, anyhow, bail};
+use clap::ValueEnum;
+use rand::Rng;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Default error type rates of direct RNA reads, where deletions dominate.
+const DIRECT_RNA_SUBSTITUTION_RATE: f64 = 0.35;
+const DIRECT_RNA_INSERTION_RATE: f64 = 0.1;
+const DIRECT_RNA_DELETION_RATE: f64 = 0.55;
+const DIRECT_RNA_DELETION_EXTENSION_RATE: f64 = 0.5;
+
+/// Library preparation of an RNA run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum RnaProtocol {
+    /// cDNA reads from either strand of the molecule, reported 5'→3'
+    #[default]
+    Cdna,
+    /// Direct RNA reads from the sense strand, reported 5'→3' as basecallers do
+    DirectRna,
+}
+
+impl RnaProtocol {
+    /// Builds the error model of the protocol's reads, using the protocol's defaults for the
+    /// rates that are not given.
+    ///
+    /// # Errors
+    /// Returns an error if the rates are invalid (see [`ErrorModel::new`])
+    pub fn error_model(
+        self,
+        substitution_rate: Option<f64>,
+        insertion_rate: Option<f64>,
+        deletion_rate: Option<f64>,
+        insertion_extension_rate: Option<f64>,
+        deletion_extension_rate: Option<f64>,
+    ) -> Result<ErrorModel> {
+        match self {
+            RnaProtocol::Cdna => ErrorModel::new(
+                substitution_rate,
+                insertion_rate,
+                deletion_rate,
+                insertion_extension_rate,
+                deletion_extension_rate,
+            ),
+            RnaProtocol::DirectRna => ErrorModel::new(
+                substitution_rate.or(Some(DIRECT_RNA_SUBSTITUTION_RATE)),
+                insertion_rate.or(Some(DIRECT_RNA_INSERTION_RATE)),
+                deletion_rate.or(Some(DIRECT_RNA_DELETION_RATE)),
+                insertion_extension_rate,
+                deletion_extension_rate.or(Some(DIRECT_RNA_DELETION_EXTENSION_RATE)),
+            ),
+        }
+    }
+}
+
+/// A molecule sampled from a transcript.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptTemplate {
+    pub sequence_index: usize,
+    /// Zero-based start of the molecule on the transcript (after 5' truncation)
+    pub start: usize,
+    /// Zero-based, exclusive end of the molecule on the transcript, excluding the poly-A tail
+    pub end: usize,
+    /// Read template, including the poly-A tail
+    pub sequence: Vec<u8>,
+    /// Whether the template is the reverse complement of the transcript
+    pub reverse: bool,
+    /// Length of the poly-A tail in the template
+    pub polya_length: usize,
+}
+
+/// Expression-weighted transcripts that cDNA molecules are drawn from.
+pub struct Transcriptome {
+    cumulative_weights: Vec<f64>,
+    expressed: usize,
+    truncated_fraction: f64,
+    polya_length: CopyNumber,
+    protocol: RnaProtocol,
+    uracil: bool,
+}
+
+impl Transcriptome {
+    /// Builds a transcriptome from transcript sequences and their expression.
+    ///
+    /// Transcripts missing from the expression table are not expressed. IDs are matched
+    /// exactly, or by the part before the first `|` for GENCODE-style FASTA headers.
+    ///
+    /// # Arguments
+    /// * `reference` - Transcript sequences
+    /// * `expression` - Relative abundance of each transcript (all equal if None)
+    /// * `truncated_fraction` - Fraction of molecules missing part of their 5' end
+    /// * `polya_length` - Length of the poly-A tails
+    ///
+    /// # Errors
+    /// Returns an error if `truncated_fraction` is not between 0 and 1, or if no transcript
+    /// is expressed
+    pub fn new(
+        reference: &Reference,
+        expression: Option<&HashMap<String, f64>>,
+        truncated_fraction: f64,
+        polya_length: CopyNumber,
+    ) -> Result<Self> {
+        if !(0.0..=1.0).contains(&truncated_fraction) {
+            bail!(
+                "Truncated fraction must be between 0 and 1, got {}",
+                truncated_fraction
+            );
+        }
+
+        let mut total = 0.0;
+        let mut expressed = 0;
+        let mut cumulative_weights = Vec::with_capacity(reference.len());
+        for i in 0..reference.len() {
+            let weight = match expression {
+                Some(expression) => {
+                    let id = reference.sequence_id(i);
+                    let short_id = id.split('|').next().unwrap_or(id);
+                    expression
+                        .get(id)
+                        .or_else(|| expression.get(short_id))
+                        .copied()
+                        .unwrap_or(0.0)
+                }
+                None => 1.0,
+            };
+            if weight > 0.0 && reference.sequence_length(i) > 0 {
+                total += weight;
+                expressed += 1;
+            }
+            cumulative_weights.push(total);
+        }
+
+        if expressed == 0 {
+            bail!(
+                "No transcript is expressed; check that the expression table IDs match the transcript IDs"
+            );
+        }
+
+        Ok(Self {
+            cumulative_weights,
+            expressed,
+            truncated_fraction,
+            polya_length,
+            protocol: RnaProtocol::default(),
+            uracil: false,
+        })
+    }
+
+    /// Sets the library preparation, which decides the strand and orientation of the reads.
+    ///
+    /// # Arguments
+    /// * `protocol` - cDNA or direct RNA
+    pub fn with_protocol(mut self, protocol: RnaProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Reports reads in the RNA alphabet, with `U` instead of `T`.
+    pub fn with_uracil(mut self) -> Self {
+        self.uracil = true;
+        self
+    }
+
+    /// Returns true if reads are reported with `U` instead of `T`.
+    pub fn uracil(&self) -> bool {
+        self.uracil
+    }
+
+    /// Returns the number of transcripts with a positive expression.
+    pub fn expressed(&self) -> usize {
+        self.expressed
+    }
+
+    /// Draws a molecule and builds its read template.
+    ///
+    /// Truncated molecules keep a 3' fragment of the transcript whose length is drawn from
+    /// the length model, and are full-length if no modelled length fits the transcript.
+    ///
+    /// # Arguments
+    /// * `reference` - Transcript sequences the transcriptome was built from
+    /// * `length_model` - Model of fragment lengths for truncated molecules
+    /// * `rng` - Random number generator
+    pub fn sample(
+        &self,
+        reference: &Reference,
+        length_model: &LengthModel,
+        rng: &mut impl Rng,
+    ) -> Result<TranscriptTemplate> {
+        let total = *self.cumulative_weights.last().unwrap();
+        let point = rng.random_range(0.0..total);
+        let sequence_index = self
+            .cumulative_weights
+            .partition_point(|&weight| weight <= point)
+            .min(self.cumulative_weights.len() - 1);
+
+        let end = reference.sequence_length(sequence_index);
+        let start = if rng.random_bool(self.truncated_fraction) {
+            length_model
+                .sample_at_most(end, rng)
+                .filter(|&length| length > 0)
+                .map_or(0, |length| end - length)
+        } else {
+            0
+        };
+
+        let mut sequence = reference.fetch(sequence_index, start, end - start)?;
+        let polya_length = self.polya_length.sample(rng);
+        sequence.extend(std::iter::repeat_n(b'A', polya_length));
+        let reverse = match self.protocol {
+            RnaProtocol::Cdna => rng.random_bool(0.5),
+            RnaProtocol::DirectRna => false,
+        };
+        if reverse {
+            sequence = reverse_complement(&sequence);
+        }
+
+        Ok(TranscriptTemplate {
+            sequence_index,
+            start,
+            end,
+            sequence,
+            reverse,
+            polya_length,
+        })
+    }
+}
+
+/// Converts a DNA sequence to the RNA alphabet in place, replacing `T` with `U`.
+pub fn to_uracil(sequence: &mut [u8]) {
+    for base in sequence {
+        match *base {
+            b'T' => *base = b'U',
+            b't' => *base = b'u',
+            _ => {}
+        }
+    }
+}
+
+/// Converts an RNA sequence back to the DNA alphabet in place, replacing `U` with `T`.
+pub fn to_thymine(sequence: &mut [u8]) {
+    for base in sequence {
+        match *base {
+            b'U' => *base = b'T',
+            b'u' => *base = b't',
+            _ => {}
+        }
+    }
+}
+
+/// Builds transcript sequences from a genome and its GTF annotation.
+///
+/// Exons are grouped by `transcript_id` and joined in genomic order; transcripts on the
+/// minus strand are reverse complemented. Transcripts are returned in order of first
+/// appearance in the annotation.
+///
+/// # Arguments
+/// * `records` - GTF features (only `exon` features are used)
+/// * `genome` - Genome sequences the annotation refers to
+///
+/// # Errors
+/// Returns an error if an exon lacks a `transcript_id`, refers to an unknown sequence or
+/// lies outside it, if the exons of a transcript disagree on sequence or strand, or if there
+/// are no exons
+pub fn splice_transcripts(
+    records: &[GtfRecord],
+    genome: &[FastaRecord],
+) -> Result<Vec<FastaRecord>> {
+    let sequence_indices: HashMap<&str, usize> = genome
+        .iter()
+        .enumerate()
+        .map(|(i, record)| (record.id.as_str(), i))
+        .collect();
+
+    let mut transcript_indices: HashMap<&str, usize> = HashMap::new();
+    let mut transcripts: Vec<AnnotatedTranscript> = Vec::new();
+    for record in records.iter().filter(|r| r.feature == "exon") {
+        let region = format!("{}:{}-{}", record.seqname, record.start, record.end);
+        let id = record
+            .attribute("transcript_id")
+            .ok_or_else(|| anyhow!("Exon {} has no transcript_id attribute", region))?;
+        let Some(&sequence_index) = sequence_indices.get(record.seqname.as_str()) else {
+            bail!("Exon {} refers to a sequence not in the genome", region);
+        };
+        if record.end > genome[sequence_index].sequence.len() {
+            bail!(
+                "Exon {} extends past the sequence end ({} bp)",
+                region,
+                genome[sequence_index].sequence.len()
+            );
+        }
+
+        let index = *transcript_indices.entry(id).or_insert_with(|| {
+            transcripts.push(AnnotatedTranscript {
+                id,
+                sequence_index,
+                strand: record.strand,
+                exons: Vec::new(),
+            });
+            transcripts.len() - 1
+        });
+        let transcript = &mut transcripts[index];
+        if transcript.sequence_index != sequence_index || transcript.strand != record.strand {
+            bail!(
+                "Exons of transcript {} lie on different sequences or strands",
+                id
+            );
+        }
+        transcript.exons.push(record.start - 1..record.end);
+    }
+
+    if transcripts.is_empty() {
+        bail!("No exons found in the annotation");
+    }
+
+    Ok(transcripts
+        .into_iter()
+        .map(|mut transcript| {
+            let genomic = &genome[transcript.sequence_index].sequence;
+            transcript.exons.sort_by_key(|exon| exon.start);
+            let mut sequence: Vec<u8> = transcript
+                .exons
+                .iter()
+                .flat_map(|exon| &genomic[exon.clone()])
+                .copied()
+                .collect();
+            if transcript.strand == '-' {
+                sequence = reverse_complement(&sequence);
+            }
+            FastaRecord {
+                id: transcript.id.to_string(),
+                sequence,
+            }
+        })
+        .collect())
+}
+
+/// Exons of an annotated transcript, as 0-based half-open genome intervals.
+struct AnnotatedTranscript<'a> {
+    id: &'a str,
+    sequence_index: usize,
+    strand: char,
+    exons: Vec<Range<usize>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    fn exon(start: usize, end: usize, strand: char, transcript: &str) -> GtfRecord {
+        GtfRecord {
+            seqname: "chr1".to_string(),
+            feature: "exon".to_string(),
+            start,
+            end,
+            strand,
+            attributes: format!("gene_id \"G\"; transcript_id \"{}\";", transcript),
+        }
+    }
+
+    #[test]
+    fn test_direct_rna_defaults() {
+        let model = RnaProtocol::DirectRna
+            .error_model(Some(0.5), None, Some(0.4), None, None)
+            .unwrap();
+        assert_eq!(model.substitution_rate, 0.5);
+        assert_eq!(model.insertion_rate, DIRECT_RNA_INSERTION_RATE);
+        assert_eq!(model.deletion_rate, 0.4);
+        assert_eq!(
+            model.deletion_extension_rate,
+            DIRECT_RNA_DELETION_EXTENSION_RATE
+        );
+
+        let mut sequence = b"ACGTtu".to_vec();
+        to_uracil(&mut sequence);
+        assert_eq!(sequence, b"ACGUuu");
+    }
+
+    #[test]
+    fn test_splice_transcripts() {
+        let genome = vec![FastaRecord {
+            id: "chr1".to_string(),
+            sequence: b"AACCGGTTAACC".to_vec(),
+        }];
+        let mut records = vec![
+            exon(9, 10, '+', "T1"),
+            exon(1, 2, '+', "T1"),
+            exon(3, 6, '-', "T2"),
+        ];
+        records.push(GtfRecord {
+            feature: "transcript".to_string(),
+            ..exon(1, 10, '+', "T1")
+        });
+
+        let transcripts = splice_transcripts(&records, &genome).unwrap();
+        assert_eq!(transcripts.len(), 2);
+        assert_eq!(transcripts[0].id, "T1");
+        assert_eq!(transcripts[0].sequence, b"AAAA");
+        assert_eq!(transcripts[1].id, "T2");
+        assert_eq!(transcripts[1].sequence, b"CCGG");
+
+        records.push(exon(11, 12, '-', "T1"));
+        assert!(splice_transcripts(&records, &genome).is_err());
+        assert!(splice_transcripts(&[exon(10, 13, '+', "T3")], &genome).is_err());
+    }
+
+    #[test]
+    fn test_sample_molecules() {
+        let reference = Reference::from(vec![
+            FastaRecord {
+                id: "T1|G1|".to_string(),
+                sequence: b"GGGGGCCCCC".to_vec(),
+            },
+            FastaRecord {
+                id: "T2".to_string(),
+                sequence: b"ACGT".to_vec(),
+            },
+        ]);
+        let expression = HashMap::from([("T1".to_string(), 5.0), ("T3".to_string(), 1.0)]);
+        let mut length_model = LengthModel::new();
+        length_model.add_value(4);
+
+        let transcriptome =
+            Transcriptome::new(&reference, Some(&expression), 0.5, CopyNumber::Fixed(3)).unwrap();
+        assert_eq!(transcriptome.expressed(), 1);
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut truncated = 0;
+        let mut reverse = 0;
+        for _ in 0..200 {
+            let template = transcriptome
+                .sample(&reference, &length_model, &mut rng)
+                .unwrap();
+            assert_eq!(template.sequence_index, 0);
+            assert_eq!(template.end, 10);
+            let expected: &[u8] = if template.start == 6 {
+                truncated += 1;
+                b"CCCCAAA"
+            } else {
+                assert_eq!(template.start, 0);
+                b"GGGGGCCCCCAAA"
+            };
+            if template.reverse {
+                reverse += 1;
+                assert_eq!(template.sequence, reverse_complement(expected));
+            } else {
+                assert_eq!(template.sequence, expected);
+            }
+        }
+        assert!((70..130).contains(&truncated));
+        assert!((70..130).contains(&reverse));
+
+        assert!(
+            Transcriptome::new(&reference, Some(&HashMap::new()), 0.5, CopyNumber::Fixed(0))
+                .is_err()
+        );
+        assert!(Transcriptome::new(&reference, None, 1.5, CopyNumber::Fixed(0)).is_err());
+    }
+}
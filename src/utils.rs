@@ -1,8 +1,9 @@
 use crate::io::bam::BamReader;
 use crate::io::fastq::FastqReader;
 use crate::models::{LengthModel, QualityModel};
-use rand::SeedableRng;
 use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f64::consts::TAU;
 use std::path::Path;
 use std::sync::LazyLock;
 
@@ -17,6 +18,68 @@ pub static QUALITY_MAPPING: LazyLock<[f32; 94]> = LazyLock::new(|| {
     mapping
 });
 
+/// Mean quality reads need to pass filtering, MinKNOW's default for high-accuracy models.
+pub const MIN_QSCORE: f32 = 10.0;
+
+/// Number of leading bases left out of the mean quality, as dorado's `mean_qscore_start_pos`.
+pub const MEAN_QSCORE_START: usize = 60;
+
+/// Computes the mean quality of a read the way dorado does: the mean of the per-base error
+/// probabilities, converted back to a Phred score.
+///
+/// The first [`MEAN_QSCORE_START`] bases are left out, unless the read is not longer than
+/// that, in which case all of its bases count.
+///
+/// # Arguments
+/// * `qualities` - Quality scores in Phred+33 ASCII encoding
+///
+/// # Returns
+/// The mean quality, or 0 for an empty read
+pub fn mean_qscore(qualities: &[u8]) -> f32 {
+    if qualities.is_empty() {
+        return 0.0;
+    }
+    let qualities = qualities
+        .get(MEAN_QSCORE_START..)
+        .filter(|q| !q.is_empty())
+        .unwrap_or(qualities);
+    let total: f32 = qualities
+        .iter()
+        .map(|&q| QUALITY_MAPPING[usize::from(q.saturating_sub(33).min(93))])
+        .sum();
+    -10.0 * (total / qualities.len() as f32).log10()
+}
+
+/// Derives an independent random stream from a seed, a stream identifier and an index.
+///
+/// The three values are packed into the 256-bit key of the underlying ChaCha generator,
+/// so every (seed, stream, index) combination yields its own stream. This lets each read
+/// be generated independently of the others (and of the thread that generates it).
+///
+/// # Arguments
+/// * `seed` - Base random seed of the run
+/// * `stream` - Identifier of the simulation stage consuming the stream
+/// * `index` - Index of the item within the stage (e.g. read index)
+pub fn derive_rng(seed: u64, stream: u64, index: u64) -> StdRng {
+    let mut key = [0u8; 32];
+    key[..8].copy_from_slice(&seed.to_le_bytes());
+    key[8..16].copy_from_slice(&stream.to_le_bytes());
+    key[16..24].copy_from_slice(&index.to_le_bytes());
+    StdRng::from_seed(key)
+}
+
+/// Draws a value from a normal distribution, using the Box-Muller transform.
+///
+/// # Arguments
+/// * `rng` - Random number generator
+/// * `mean` - Mean of the distribution
+/// * `sd` - Standard deviation of the distribution
+pub fn sample_normal(rng: &mut impl Rng, mean: f64, sd: f64) -> f64 {
+    let u1: f64 = rng.random_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.random();
+    mean + sd * (-2.0 * u1.ln()).sqrt() * (TAU * u2).cos()
+}
+
 /// Loads length and quality models from an existing FASTQ or BAM file.
 ///
 /// Automatically detects the file format based on the extension (.fastq, .fq, .bam).
@@ -101,4 +164,34 @@ mod tests {
         // Q20 should be approximately 0.01
         assert!((QUALITY_MAPPING[20] - 0.01).abs() < 0.001);
     }
+
+    #[test]
+    fn test_mean_qscore() {
+        assert!((mean_qscore(b"5555") - 20.0).abs() < 0.01);
+        // One Q10 base among Q30 bases dominates the mean error
+        assert!((mean_qscore(b"+???") - 15.9).abs() < 0.1);
+        assert_eq!(mean_qscore(b""), 0.0);
+
+        // The first 60 bases are left out as by dorado: 40 Q20 and 10 Q10 bases have a mean
+        // error of (40 * 0.01 + 10 * 0.1) / 50 = 0.028, which dorado reports as Q15.53
+        let mut qualities = vec![b'!'; MEAN_QSCORE_START];
+        qualities.extend_from_slice(&[b'5'; 40]);
+        qualities.extend_from_slice(&[b'+'; 10]);
+        assert!((mean_qscore(&qualities) - 15.53).abs() < 0.01);
+        // Reads no longer than that count every base
+        assert!((mean_qscore(&[b'+'; MEAN_QSCORE_START]) - 10.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_derive_rng_streams() {
+        use rand::Rng;
+
+        let a: u64 = derive_rng(42, 0, 7).random();
+        let b: u64 = derive_rng(42, 0, 7).random();
+        let c: u64 = derive_rng(42, 0, 8).random();
+        let d: u64 = derive_rng(42, 1, 7).random();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+    }
 }
@@ -0,0 +1,687 @@
+//! Sequence variants and their application to reference sequences.
+//!
+//! Variants are represented as replacements of a reference interval, which covers SNVs,
+//! MNVs, indels and simple structural variants alike. Placing them produces a
+//! [`VariantLayout`] that maps positions on the modified sequences back to the reference and
+//! tells which variants a read carries; the modified sequences are either built from it or
+//! read through it from the shared reference ([`EditedSequences`]).
+
+use crate::io::fasta::{FastaRecord, Reference};
+use crate::io::vcf::VcfRecord;
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Alleles longer than this are abbreviated in variant labels.
+const MAX_LABEL_ALLELE_LENGTH: usize = 20;
+
+/// Kind of a sequence variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantKind {
+    Snv,
+    Mnv,
+    Insertion,
+    Deletion,
+    Complex,
+    Inversion,
+    Duplication,
+    Translocation,
+}
+
+impl VariantKind {
+    /// Returns the VCF `SVTYPE`-style name of the kind.
+    pub fn name(&self) -> &'static str {
+        match self {
+            VariantKind::Snv => "SNV",
+            VariantKind::Mnv => "MNV",
+            VariantKind::Insertion => "INS",
+            VariantKind::Deletion => "DEL",
+            VariantKind::Complex => "COMPLEX",
+            VariantKind::Inversion => "INV",
+            VariantKind::Duplication => "DUP",
+            VariantKind::Translocation => "TRA",
+        }
+    }
+}
+
+/// A variant resolved against a reference sequence, as a replacement of a reference interval.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    /// Identifier reported in truth outputs
+    pub label: String,
+    pub kind: VariantKind,
+    pub sequence_index: usize,
+    /// Zero-based start of the replaced reference interval
+    pub start: usize,
+    /// Zero-based, exclusive end of the replaced reference interval
+    pub end: usize,
+    /// Sequence replacing the reference interval
+    pub replacement: Vec<u8>,
+}
+
+impl Variant {
+    /// Resolves one allele of a VCF record against its reference sequence.
+    ///
+    /// Supports sequence alleles as well as the symbolic `<DEL>`, `<INV>`, `<DUP>` and
+    /// `<INS>` (with `SVINSSEQ`) alleles.
+    ///
+    /// # Arguments
+    /// * `record` - VCF record
+    /// * `allele` - Allele index (1 for the first ALT allele)
+    /// * `sequence_index` - Position of the record's sequence in the reference
+    /// * `sequence` - Reference sequence the record refers to
+    ///
+    /// # Returns
+    /// The resolved variant, or None if the allele is the reference allele or is not supported
+    ///
+    /// # Errors
+    /// Returns an error if the REF allele does not match the reference or the variant
+    /// extends past the sequence end
+    pub fn from_vcf(
+        record: &VcfRecord,
+        allele: usize,
+        sequence_index: usize,
+        sequence: &[u8],
+    ) -> Result<Option<Self>> {
+        let Some(alternate) = allele.checked_sub(1).and_then(|i| record.alternates.get(i)) else {
+            return Ok(None);
+        };
+
+        let label = record
+            .id
+            .clone()
+            .unwrap_or_else(|| default_label(record, alternate));
+        let position = record.position - 1;
+
+        if alternate.starts_with('<') {
+            let Some(end) = symbolic_end(record) else {
+                return Ok(None);
+            };
+            if end > sequence.len() || end < record.position {
+                bail!(
+                    "Variant {} at {}:{} extends past the sequence end ({} bp)",
+                    label,
+                    record.chrom,
+                    record.position,
+                    sequence.len()
+                );
+            }
+            // Symbolic alleles start after the padding base at POS
+            let start = record.position;
+            let (kind, start, end, replacement) = match alternate.as_str() {
+                "<DEL>" => (VariantKind::Deletion, start, end, Vec::new()),
+                "<INV>" => (
+                    VariantKind::Inversion,
+                    start,
+                    end,
+                    reverse_complement(&sequence[start..end]),
+                ),
+                s if s.starts_with("<DUP") => (
+                    VariantKind::Duplication,
+                    end,
+                    end,
+                    sequence[start..end].to_vec(),
+                ),
+                s if s.starts_with("<INS") => match record.info_value("SVINSSEQ") {
+                    Some(inserted) => (
+                        VariantKind::Insertion,
+                        start,
+                        start,
+                        inserted.as_bytes().to_vec(),
+                    ),
+                    None => return Ok(None),
+                },
+                _ => return Ok(None),
+            };
+            return Ok(Some(Self {
+                label,
+                kind,
+                sequence_index,
+                start,
+                end,
+                replacement,
+            }));
+        }
+
+        if alternate.contains(['[', ']', '*', '.']) {
+            return Ok(None); // breakends and missing alleles
+        }
+
+        let reference_allele = record.reference.as_bytes();
+        let reference_end = position + reference_allele.len();
+        if reference_end > sequence.len()
+            || !sequence[position..reference_end].eq_ignore_ascii_case(reference_allele)
+        {
+            bail!(
+                "REF allele of variant {} does not match the reference at {}:{}",
+                label,
+                record.chrom,
+                record.position
+            );
+        }
+
+        // Trim bases shared by REF and ALT so the variant covers only what changes
+        let alternate = alternate.as_bytes();
+        let suffix = common_suffix(reference_allele, alternate);
+        let (reference_allele, alternate) = (
+            &reference_allele[..reference_allele.len() - suffix],
+            &alternate[..alternate.len() - suffix],
+        );
+        let prefix = common_prefix(reference_allele, alternate);
+        let (reference_allele, alternate) = (&reference_allele[prefix..], &alternate[prefix..]);
+
+        let kind = match (reference_allele.len(), alternate.len()) {
+            (0, 0) => return Ok(None),
+            (1, 1) => VariantKind::Snv,
+            (r, a) if r == a => VariantKind::Mnv,
+            (0, _) => VariantKind::Insertion,
+            (_, 0) => VariantKind::Deletion,
+            _ => VariantKind::Complex,
+        };
+
+        Ok(Some(Self {
+            label,
+            kind,
+            sequence_index,
+            start: position + prefix,
+            end: position + prefix + reference_allele.len(),
+            replacement: alternate.to_vec(),
+        }))
+    }
+}
+
+/// A variant placed on a modified sequence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedVariant {
+    pub label: String,
+    pub reference_start: usize,
+    pub reference_end: usize,
+    pub haplotype_start: usize,
+    pub haplotype_end: usize,
+    /// Sequence replacing the reference interval
+    pub replacement: Vec<u8>,
+}
+
+/// Layout of the variants applied to a set of sequences.
+#[derive(Debug, Clone, Default)]
+pub struct VariantLayout {
+    /// Placed variants per sequence, sorted by position
+    sequences: Vec<Vec<PlacedVariant>>,
+}
+
+impl VariantLayout {
+    /// Returns the variants placed on a sequence, sorted by position.
+    pub fn placed(&self, sequence_index: usize) -> &[PlacedVariant] {
+        self.sequences
+            .get(sequence_index)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the labels of all applied variants.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.sequences
+            .iter()
+            .flatten()
+            .map(|variant| variant.label.as_str())
+    }
+
+    /// Returns true if a variant with the given label was applied.
+    pub fn contains(&self, label: &str) -> bool {
+        self.sequences
+            .iter()
+            .flatten()
+            .any(|variant| variant.label == label)
+    }
+
+    /// Maps a position on a modified sequence to the corresponding reference position.
+    ///
+    /// Positions inside inserted or replaced sequence map to the start of the variant.
+    pub fn to_reference(&self, sequence_index: usize, position: usize) -> usize {
+        let placed = self.placed(sequence_index);
+        let idx = placed.partition_point(|v| v.haplotype_start <= position);
+        let Some(variant) = idx.checked_sub(1).map(|i| &placed[i]) else {
+            return position;
+        };
+
+        if position < variant.haplotype_end {
+            let offset = position - variant.haplotype_start;
+            variant.reference_start + offset.min(variant.reference_end - variant.reference_start)
+        } else {
+            variant.reference_end + (position - variant.haplotype_end)
+        }
+    }
+
+    /// Maps a reference position to the corresponding position on the modified sequence.
+    ///
+    /// Positions inside a replaced reference interval map to the start of the variant.
+    pub fn to_haplotype(&self, sequence_index: usize, position: usize) -> usize {
+        let placed = self.placed(sequence_index);
+        let idx = placed.partition_point(|v| v.reference_start <= position);
+        let Some(variant) = idx.checked_sub(1).map(|i| &placed[i]) else {
+            return position;
+        };
+
+        if position < variant.reference_end {
+            let offset = position - variant.reference_start;
+            variant.haplotype_start + offset.min(variant.haplotype_end - variant.haplotype_start)
+        } else {
+            variant.haplotype_end + (position - variant.reference_end)
+        }
+    }
+
+    /// Builds a modified sequence from its reference sequence.
+    pub fn apply(&self, sequence_index: usize, reference: &[u8]) -> Vec<u8> {
+        let mut sequence = Vec::with_capacity(reference.len());
+        let mut cursor = 0;
+        for variant in self.placed(sequence_index) {
+            sequence.extend_from_slice(&reference[cursor..variant.reference_start]);
+            sequence.extend_from_slice(&variant.replacement);
+            cursor = variant.reference_end;
+        }
+        sequence.extend_from_slice(&reference[cursor..]);
+        sequence
+    }
+
+    /// Returns the variants carried by a read spanning `start..end` of a modified sequence.
+    ///
+    /// Deletions are carried by reads spanning the deletion junction.
+    pub fn variants_in(&self, sequence_index: usize, start: usize, end: usize) -> Vec<&str> {
+        let placed = self.placed(sequence_index);
+        let first = placed.partition_point(|v| v.haplotype_end <= start);
+        placed[first..]
+            .iter()
+            .take_while(|v| v.haplotype_start < end)
+            .filter(|v| v.haplotype_start < v.haplotype_end || v.haplotype_start > start)
+            .map(|v| v.label.as_str())
+            .collect()
+    }
+}
+
+/// Resolves the variants of a VCF against the reference sequences.
+///
+/// # Arguments
+/// * `records` - VCF records
+/// * `sequences` - Reference sequences the records refer to
+/// * `allele` - Chooses the allele index to apply for each record (None to skip it)
+///
+/// # Returns
+/// Tuple of (resolved variants, number of records skipped as unsupported)
+///
+/// # Errors
+/// Returns an error if a record refers to an unknown sequence or does not match the reference
+pub fn variants_from_vcf(
+    records: &[VcfRecord],
+    sequences: &[FastaRecord],
+    allele: impl Fn(&VcfRecord) -> Option<usize>,
+) -> Result<(Vec<Variant>, usize)> {
+    let sequence_indices: HashMap<&str, usize> = sequences
+        .iter()
+        .enumerate()
+        .map(|(i, record)| (record.id.as_str(), i))
+        .collect();
+
+    let mut variants = Vec::new();
+    let mut unsupported = 0;
+    for record in records {
+        let Some(allele) = allele(record) else {
+            continue;
+        };
+        let Some(&sequence_index) = sequence_indices.get(record.chrom.as_str()) else {
+            bail!(
+                "Variant at {}:{} refers to a sequence not in the reference",
+                record.chrom,
+                record.position
+            );
+        };
+        match Variant::from_vcf(
+            record,
+            allele,
+            sequence_index,
+            &sequences[sequence_index].sequence,
+        )? {
+            Some(variant) => variants.push(variant),
+            None if allele > 0 => unsupported += 1,
+            None => {}
+        }
+    }
+
+    Ok((variants, unsupported))
+}
+
+/// Places variants on sequences, without building the modified sequences.
+///
+/// Variants overlapping an already placed variant are skipped.
+///
+/// # Arguments
+/// * `lengths` - Lengths of the reference sequences
+/// * `variants` - Variants to place, in any order
+///
+/// # Returns
+/// Tuple of (variant layout, number of overlapping variants skipped)
+pub fn place_variants(lengths: &[usize], mut variants: Vec<Variant>) -> (VariantLayout, usize) {
+    variants.sort_by_key(|v| (v.sequence_index, v.start, v.end));
+
+    let mut layout = VariantLayout::default();
+    let mut skipped = 0;
+    let mut variants = variants.into_iter().peekable();
+
+    for (sequence_index, &length) in lengths.iter().enumerate() {
+        let mut placed = Vec::new();
+        // Reference position after the last placed variant, and its modified position
+        let (mut cursor, mut haplotype_cursor) = (0, 0);
+
+        while let Some(variant) = variants.next_if(|v| v.sequence_index == sequence_index) {
+            if variant.start < cursor || variant.end > length {
+                skipped += 1;
+                continue;
+            }
+
+            let haplotype_start = haplotype_cursor + (variant.start - cursor);
+            let haplotype_end = haplotype_start + variant.replacement.len();
+            placed.push(PlacedVariant {
+                label: variant.label,
+                reference_start: variant.start,
+                reference_end: variant.end,
+                haplotype_start,
+                haplotype_end,
+                replacement: variant.replacement,
+            });
+            (cursor, haplotype_cursor) = (variant.end, haplotype_end);
+        }
+        layout.sequences.push(placed);
+    }
+
+    (layout, skipped)
+}
+
+/// Applies variants to reference sequences.
+///
+/// Variants overlapping an already applied variant are skipped.
+///
+/// # Arguments
+/// * `sequences` - Reference sequences
+/// * `variants` - Variants to apply, in any order
+///
+/// # Returns
+/// Tuple of (modified sequences, variant layout, number of overlapping variants skipped)
+pub fn apply_variants(
+    sequences: &[FastaRecord],
+    variants: Vec<Variant>,
+) -> (Vec<FastaRecord>, VariantLayout, usize) {
+    let lengths: Vec<usize> = sequences
+        .iter()
+        .map(|record| record.sequence.len())
+        .collect();
+    let (layout, skipped) = place_variants(&lengths, variants);
+    let modified = sequences
+        .iter()
+        .enumerate()
+        .map(|(sequence_index, record)| FastaRecord {
+            id: record.id.clone(),
+            sequence: layout.apply(sequence_index, &record.sequence),
+        })
+        .collect();
+    (modified, layout, skipped)
+}
+
+/// Sequences obtained by applying a variant layout to shared reference sequences.
+///
+/// Windows are assembled on demand from the reference and the replaced intervals, so that
+/// haplotypes share one copy of the reference instead of each holding a modified genome.
+pub struct EditedSequences {
+    reference: Arc<Reference>,
+    layout: Arc<VariantLayout>,
+    lengths: Vec<usize>,
+}
+
+impl EditedSequences {
+    /// Creates the sequences of a variant layout over reference sequences.
+    ///
+    /// # Arguments
+    /// * `reference` - Reference sequences the layout was placed on
+    /// * `layout` - Variants placed on the reference
+    pub fn new(reference: Arc<Reference>, layout: Arc<VariantLayout>) -> Self {
+        let lengths = (0..reference.len())
+            .map(|i| layout.to_haplotype(i, reference.sequence_length(i)))
+            .collect();
+        Self {
+            reference,
+            layout,
+            lengths,
+        }
+    }
+
+    /// Returns the reference sequences the variants were placed on.
+    pub fn reference(&self) -> &Reference {
+        &self.reference
+    }
+
+    /// Returns the length of a modified sequence.
+    pub fn sequence_length(&self, sequence_index: usize) -> usize {
+        self.lengths[sequence_index]
+    }
+
+    /// Returns a copy of a window of a modified sequence.
+    ///
+    /// # Arguments
+    /// * `sequence_index` - Position of the sequence
+    /// * `start` - Zero-based start of the window
+    /// * `length` - Length of the window
+    pub fn fetch(&self, sequence_index: usize, start: usize, length: usize) -> Result<Vec<u8>> {
+        let placed = self.layout.placed(sequence_index);
+        let end = start + length;
+        let mut sequence = Vec::with_capacity(length);
+        let mut position = start;
+        let mut next = placed.partition_point(|v| v.haplotype_end <= start);
+        while position < end {
+            match placed.get(next) {
+                Some(variant) if variant.haplotype_start <= position => {
+                    let stop = end.min(variant.haplotype_end);
+                    sequence.extend_from_slice(
+                        &variant.replacement
+                            [position - variant.haplotype_start..stop - variant.haplotype_start],
+                    );
+                    position = stop;
+                    next += 1;
+                }
+                variant => {
+                    let stop = variant.map_or(end, |v| end.min(v.haplotype_start));
+                    let reference_start = self.layout.to_reference(sequence_index, position);
+                    sequence.extend(self.reference.fetch(
+                        sequence_index,
+                        reference_start,
+                        stop - position,
+                    )?);
+                    position = stop;
+                }
+            }
+        }
+        Ok(sequence)
+    }
+}
+
+/// Returns the reverse complement of a nucleotide sequence, preserving case.
+pub fn reverse_complement(sequence: &[u8]) -> Vec<u8> {
+    sequence
+        .iter()
+        .rev()
+        .map(|&base| match base {
+            b'A' => b'T',
+            b'C' => b'G',
+            b'G' => b'C',
+            b'T' => b'A',
+            b'a' => b't',
+            b'c' => b'g',
+            b'g' => b'c',
+            b't' => b'a',
+            other => other,
+        })
+        .collect()
+}
+
+/// Returns the exclusive 0-based end of a symbolic allele from `END` or `SVLEN`.
+fn symbolic_end(record: &VcfRecord) -> Option<usize> {
+    if let Some(end) = record.info_value("END").and_then(|s| s.parse().ok()) {
+        return Some(end);
+    }
+    let length: i64 = record
+        .info_value("SVLEN")?
+        .split(',')
+        .next()?
+        .parse()
+        .ok()?;
+    Some(record.position + length.unsigned_abs() as usize)
+}
+
+/// Builds a `chrom:pos:REF>ALT` label, abbreviating long alleles.
+fn default_label(record: &VcfRecord, alternate: &str) -> String {
+    let abbreviate = |allele: &str| {
+        if allele.len() > MAX_LABEL_ALLELE_LENGTH {
+            format!("{}bp", allele.len())
+        } else {
+            allele.to_string()
+        }
+    };
+    format!(
+        "{}:{}:{}>{}",
+        record.chrom,
+        record.position,
+        abbreviate(&record.reference),
+        abbreviate(alternate)
+    )
+}
+
+fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .zip(b)
+        .take_while(|(x, y)| x.eq_ignore_ascii_case(y))
+        .count()
+}
+
+fn common_suffix(a: &[u8], b: &[u8]) -> usize {
+    a.iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x.eq_ignore_ascii_case(y))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vcf(position: usize, reference: &str, alternate: &str, info: &str) -> VcfRecord {
+        VcfRecord {
+            chrom: "chr1".to_string(),
+            position,
+            id: None,
+            reference: reference.to_string(),
+            alternates: vec![alternate.to_string()],
+            info: info.to_string(),
+            format: None,
+            samples: Vec::new(),
+        }
+    }
+
+    fn reference() -> Vec<FastaRecord> {
+        vec![FastaRecord {
+            id: "chr1".to_string(),
+            sequence: b"AAAACCCCGGGGTTTT".to_vec(),
+        }]
+    }
+
+    #[test]
+    fn test_resolve_small_variants() {
+        let sequence = &reference()[0].sequence;
+
+        let snv = Variant::from_vcf(&vcf(2, "A", "G", "."), 1, 0, sequence)
+            .unwrap()
+            .unwrap();
+        assert_eq!((snv.kind, snv.start, snv.end), (VariantKind::Snv, 1, 2));
+        assert_eq!(snv.label, "chr1:2:A>G");
+
+        let deletion = Variant::from_vcf(&vcf(4, "ACC", "A", "."), 1, 0, sequence)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            (deletion.kind, deletion.start, deletion.end),
+            (VariantKind::Deletion, 4, 6)
+        );
+
+        let insertion = Variant::from_vcf(&vcf(4, "A", "ATT", "."), 1, 0, sequence)
+            .unwrap()
+            .unwrap();
+        assert_eq!(insertion.kind, VariantKind::Insertion);
+        assert_eq!(insertion.replacement, b"TT");
+
+        assert!(Variant::from_vcf(&vcf(2, "C", "G", "."), 1, 0, sequence).is_err());
+        assert_eq!(
+            Variant::from_vcf(&vcf(2, "A", "G", "."), 0, 0, sequence).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_resolve_structural_variants() {
+        let sequence = &reference()[0].sequence;
+
+        let inversion = Variant::from_vcf(&vcf(4, "A", "<INV>", "END=8"), 1, 0, sequence)
+            .unwrap()
+            .unwrap();
+        assert_eq!((inversion.start, inversion.end), (4, 8));
+        assert_eq!(inversion.replacement, b"GGGG");
+
+        let duplication = Variant::from_vcf(&vcf(4, "A", "<DUP>", "SVLEN=4"), 1, 0, sequence)
+            .unwrap()
+            .unwrap();
+        assert_eq!((duplication.start, duplication.end), (8, 8));
+        assert_eq!(duplication.replacement, b"CCCC");
+
+        let breakend = Variant::from_vcf(&vcf(4, "A", "A]chr2:5]", "."), 1, 0, sequence).unwrap();
+        assert_eq!(breakend, None);
+    }
+
+    #[test]
+    fn test_apply_variants_and_layout() {
+        let records = vec![
+            vcf(2, "A", "G", "."),
+            vcf(4, "A", "<DEL>", "END=8"),
+            vcf(12, "G", "GTT", "."),
+            vcf(6, "C", "T", "."), // inside the deletion
+        ];
+        let (variants, unsupported) =
+            variants_from_vcf(&records, &reference(), |_| Some(1)).unwrap();
+        assert_eq!(unsupported, 0);
+
+        let (modified, layout, skipped) = apply_variants(&reference(), variants);
+        assert_eq!(skipped, 1);
+        assert_eq!(modified[0].sequence, b"AGAAGGGGTTTTTT");
+
+        // Edited sequences read the same windows from the shared reference
+        let edited = EditedSequences::new(Arc::new(reference().into()), Arc::new(layout.clone()));
+        assert_eq!(edited.sequence_length(0), 14);
+        for start in 0..14 {
+            for end in start..=14 {
+                assert_eq!(
+                    edited.fetch(0, start, end - start).unwrap(),
+                    &modified[0].sequence[start..end]
+                );
+            }
+        }
+
+        // Positions after the deletion and insertion map back to the reference
+        assert_eq!(layout.to_reference(0, 3), 3);
+        assert_eq!(layout.to_reference(0, 4), 8);
+        assert_eq!(layout.to_reference(0, 10), 12);
+        assert_eq!(layout.to_reference(0, 13), 15);
+        assert_eq!(layout.to_haplotype(0, 3), 3);
+        assert_eq!(layout.to_haplotype(0, 8), 4);
+        assert_eq!(layout.to_haplotype(0, 15), 13);
+
+        assert_eq!(layout.variants_in(0, 0, 4), vec!["chr1:2:A>G"]);
+        assert_eq!(layout.variants_in(0, 3, 6), vec!["chr1:4:A><DEL>"]);
+        assert!(layout.variants_in(0, 4, 6).is_empty());
+        assert_eq!(layout.variants_in(0, 9, 11), vec!["chr1:12:G>GTT"]);
+    }
+}